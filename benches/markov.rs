@@ -0,0 +1,101 @@
+//! Memory and time it takes to build each kind of markov cache from a large
+//! corpus, and to generate words from it. Run with `cargo bench`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use playground::markov::{create_cache, create_counted_cache, MarkovWords, WordChain};
+use rand::{Isaac64Rng, SeedableRng};
+
+// counts the bytes in use, to see how much a cache keeps
+struct Counting;
+
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        IN_USE.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const WORDS_GENERATED: usize = 200_000;
+
+// the embedded corpus, each copy with its words marked so they don't repeat
+// the ones before, making a corpus with a large vocabulary
+fn large_corpus(copies: usize) -> Vec<String> {
+    let corpus = include_str!("../src/input.txt");
+    (0..copies)
+        .flat_map(|copy| corpus.split_whitespace().map(move |word| format!("{}{}", word, copy)))
+        .collect()
+}
+
+// builds a cache from a fresh corpus, returning it with the time taken and
+// the bytes it holds on to
+fn build<C>(copies: usize, make: impl FnOnce(Vec<String>) -> C) -> (C, Duration, usize) {
+    let tokens = large_corpus(copies);
+    let before = IN_USE.load(Ordering::Relaxed);
+    let started_at = Instant::now();
+    let cache = make(tokens);
+    let took = started_at.elapsed();
+    // the tokens were dropped, so what's left is the cache
+    let kept = IN_USE.load(Ordering::Relaxed).saturating_sub(before);
+
+    (cache, took, kept)
+}
+
+fn generate<I: Iterator<Item = String>>(words: I) -> Duration {
+    let started_at = Instant::now();
+    let chars: usize = words.take(WORDS_GENERATED).map(|word| word.len()).sum();
+    assert!(chars > 0);
+    started_at.elapsed()
+}
+
+fn report(name: &str, built_in: Duration, kept: usize, generated_in: Duration) {
+    println!(
+        "{:<22} built in {:>8.1?}, keeps {:>7.1} MiB, {} words in {:>8.1?}",
+        name,
+        built_in,
+        kept as f64 / (1024.0 * 1024.0),
+        WORDS_GENERATED,
+        generated_in,
+    );
+}
+
+fn main() {
+    let copies = 20;
+    let tokens = large_corpus(copies);
+    println!(
+        "corpus of {} tokens, {:.1} MiB of text",
+        tokens.len(),
+        tokens.iter().map(|token| token.len() + 1).sum::<usize>() as f64 / (1024.0 * 1024.0),
+    );
+    drop(tokens);
+
+    let rng = || Isaac64Rng::from_seed(&[1][..]);
+
+    let (cache, built_in, kept) = build(copies, |tokens| create_cache(tokens, 2));
+    let generated_in = generate(MarkovWords::new(cache, rng()).unwrap().endless());
+    report("create_cache", built_in, kept, generated_in);
+
+    let (cache, built_in, kept) = build(copies, |tokens| create_counted_cache(tokens, 2));
+    let cache: HashMap<String, Vec<(String, u32)>> = cache;
+    let generated_in = generate(MarkovWords::new(cache, rng()).unwrap().endless());
+    report("create_counted_cache", built_in, kept, generated_in);
+
+    let (chain, built_in, kept) = build(copies, |tokens| WordChain::new(tokens, 2));
+    let chain = Rc::new(chain);
+    let generated_in = generate(MarkovWords::<_, WordChain>::new(chain, rng()).unwrap().endless());
+    report("WordChain", built_in, kept, generated_in);
+}
@@ -0,0 +1,79 @@
+//! Time it takes to draw a test typed faster than a slow terminal keeps up
+//! with, flushing after every key and with `GameConfig::flush_interval`.
+//! Run with `cargo bench --bench render`.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use playground::config::GameConfig;
+use playground::keys::KeyReader;
+use playground::{Game, TextSource};
+use termion::event::Key;
+
+// what every flush costs the terminal, a slow one over ssh or the like
+const FLUSH_COST: Duration = Duration::from_micros(200);
+const WORDS: usize = 200;
+
+// a terminal that takes `FLUSH_COST` for every flush
+#[derive(Default)]
+struct SlowTerminal {
+    bytes: usize,
+    flushes: usize,
+}
+
+impl Write for SlowTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushes += 1;
+        // busy, like a terminal that's slow to take it all in
+        let started_at = Instant::now();
+        while started_at.elapsed() < FLUSH_COST {}
+        Ok(())
+    }
+}
+
+// types the whole text with every key there at once, as fast as can be,
+// returning the time the test took and the terminal
+fn type_test(config: &GameConfig, text: &str) -> (Duration, SlowTerminal) {
+    let mut game = Game::headless(config.clone(), SlowTerminal::default(), (120, 40)).unwrap();
+    let mut keys: Vec<Key> = text.chars().map(Key::Char).collect();
+    // off the results screen
+    keys.push(Key::Ctrl('c'));
+
+    let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+    assert_eq!(results.uncorrected_errors(), 0);
+    let terminal = game.tui().writer();
+    (results.duration(), SlowTerminal { bytes: terminal.bytes, flushes: terminal.flushes })
+}
+
+fn report(name: &str, took: Duration, terminal: &SlowTerminal) {
+    println!(
+        "{:<16} typed in {:>8.1?}, {:>5} flushes, {:>7} bytes",
+        name, took, terminal.flushes, terminal.bytes,
+    );
+}
+
+fn main() {
+    let config = GameConfig {
+        num_words: WORDS,
+        seed: Some(1),
+        history: None,
+        autosave: None,
+        ..GameConfig::default()
+    };
+    let text = TextSource::new(&config).unwrap().words(&config, 1).unwrap().join(" ");
+    println!("{} words, {} keys, {:?} a flush", WORDS, text.chars().count(), FLUSH_COST);
+
+    let (took, terminal) = type_test(&config, &text);
+    report("every key", took, &terminal);
+
+    for interval in [8, 16, 33] {
+        let flush_interval = Some(Duration::from_millis(interval));
+        let (took, terminal) = type_test(&GameConfig { flush_interval, ..config.clone() }, &text);
+        report(&format!("every {}ms", interval), took, &terminal);
+    }
+}
@@ -0,0 +1,705 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::keyboard::KeyboardLayout;
+use crate::theme::Theme;
+use crate::{fnv1a, load_quotes, load_tokens, load_verbatim, GameError};
+
+/// Where the words of a test come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Text generated by a markov chain trained on the corpus.
+    Markov,
+    /// A random quote, typed verbatim.
+    Quotes,
+    /// A text file typed line by line as it was written, pressing enter at
+    /// the end of each line.
+    Verbatim,
+    /// Markov text with words mistyped in recent tests of the history mixed
+    /// in, the ones missed most often coming up most.
+    Drill,
+}
+
+impl Mode {
+    /// The mode called `name`: `markov`, `quotes`, `verbatim` or `drill`.
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "markov" => Some(Mode::Markov),
+            "quotes" => Some(Mode::Quotes),
+            "verbatim" => Some(Mode::Verbatim),
+            "drill" => Some(Mode::Drill),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Markov => "markov",
+            Mode::Quotes => "quotes",
+            Mode::Verbatim => "verbatim",
+            Mode::Drill => "drill",
+        }
+    }
+}
+
+/// What happens right away when a key doesn't match the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFeedback {
+    /// Nothing but the mistake being drawn.
+    Off,
+    /// The terminal bell.
+    Bell,
+    /// The screen's colors are inverted for a moment.
+    Flash,
+}
+
+/// Where lines are put across the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// In the middle of the screen.
+    Center,
+    /// Starting a few columns from the left edge.
+    Left,
+}
+
+/// Shape of the cursor while typing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorStyle {
+    BlinkingBar,
+    SteadyBar,
+    BlinkingBlock,
+    SteadyBlock,
+    BlinkingUnderline,
+    SteadyUnderline,
+}
+
+impl CursorStyle {
+    /// The style called `name`, e.g. `bar` or `steady-underline`. Styles
+    /// blink unless their name starts with `steady-`.
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "bar" | "blinking-bar" => Some(CursorStyle::BlinkingBar),
+            "steady-bar" => Some(CursorStyle::SteadyBar),
+            "block" | "blinking-block" => Some(CursorStyle::BlinkingBlock),
+            "steady-block" => Some(CursorStyle::SteadyBlock),
+            "underline" | "blinking-underline" => Some(CursorStyle::BlinkingUnderline),
+            "steady-underline" => Some(CursorStyle::SteadyUnderline),
+            _ => None,
+        }
+    }
+}
+
+/// Settings for a typing test, stored on `Game` and used on every restart.
+#[derive(Debug, Clone)]
+pub struct GameConfig {
+    pub mode: Mode,
+    /// Number of words generated for each test. Quotes are always typed in
+    /// full.
+    pub num_words: usize,
+    /// When set, the test ends once this much time has passed since the
+    /// first keystroke, scoring whatever was typed so far.
+    pub time_limit: Option<Duration>,
+    /// Text file the markov chain is trained on. The embedded corpus is
+    /// used when this is `None`.
+    pub corpus: Option<PathBuf>,
+    /// File with one quote per line for `Mode::Quotes`. The embedded quotes
+    /// are used when this is `None`.
+    pub quotes: Option<PathBuf>,
+    /// File typed in `Mode::Verbatim`, which has no embedded default.
+    pub text: Option<PathBuf>,
+    /// Number of preceding words the markov chain looks at when picking the
+    /// next one.
+    pub order: usize,
+    /// Seed for the word generator, making every test reproducible. A fresh
+    /// random text is generated on each restart when this is `None`.
+    pub seed: Option<u64>,
+    /// Mix capitals, punctuation and numbers into generated words.
+    pub punctuation: bool,
+    /// Fraction of words changed when `punctuation` is on.
+    pub punctuation_rate: f64,
+    /// Capitalize the first markov word and every one after a sentence
+    /// ends, see `text::prettify_sentences`.
+    pub prettify: bool,
+    /// Only markov words with at least this many letters, not counting
+    /// punctuation. The chain is walked as usual and other words are left
+    /// out, so the text reads a little less like the corpus.
+    pub min_word_len: Option<usize>,
+    /// Only markov words with at most this many letters, see
+    /// `min_word_len`.
+    pub max_word_len: Option<usize>,
+    /// Put the words of a test in reverse order, so they don't read the way
+    /// the fingers are used to. The words a timed test gets as it goes are
+    /// added in the order they come. Verbatim texts aren't reversed, and
+    /// share codes leave it to the config of whoever plays them.
+    pub reverse_words: bool,
+    /// Spell every word of a test backwards, see `reverse_words`.
+    pub reverse_letters: bool,
+    /// Treat the space between words like any other character, so typing
+    /// something else there is a mistake. Otherwise other keys are ignored
+    /// at a space until it's typed, and neither a missing space nor the key
+    /// pressed instead counts against accuracy. A space typed inside a word
+    /// is a mistake either way.
+    pub strict_space: bool,
+    /// Type the space at the end of a wrapped line like any other. Without
+    /// it the cursor goes straight on to the next line, and those spaces
+    /// aren't part of the scored text. Verbatim lines end in a newline
+    /// instead, which is always typed.
+    pub line_end_space: bool,
+    /// End the last line of words with a space like the others, which then
+    /// has to be typed, or is skipped without `line_end_space`, before the
+    /// test is done. Off, the test ends on the last word's last letter.
+    pub final_space: bool,
+    /// Show and record the results of finished tests. Without it a finished
+    /// test goes straight on to the next one until ctrl-c, for practice.
+    pub scoring: bool,
+    /// Ask before ctrl-r throws away the test being typed.
+    pub confirm_restart: bool,
+    /// Where the markov chain is saved once it's built, so the next start
+    /// with the same corpus and order reads it instead. None builds it every
+    /// time.
+    pub chain_cache: Option<PathBuf>,
+    /// Show the same words again on every restart, until ctrl-n asks for new
+    /// ones.
+    pub lock_text: bool,
+    /// Quit with escape too, not just ctrl-c, but for answering the restart
+    /// prompt, where it says no. A terminal sends alt and a key as escape
+    /// followed by that key, which is read as the alt key and not as escape,
+    /// so alt-backspace still deletes a word. Escape and a key pressed
+    /// quickly enough can arrive the same way and be taken for alt too.
+    pub esc_quits: bool,
+    /// Feedback on every mistake, off by default since bells annoy some
+    /// people.
+    pub error_feedback: ErrorFeedback,
+    /// Draw a mistake as the key that was typed, in the error color, rather
+    /// than the char of the text it was typed for.
+    pub show_typed_mistakes: bool,
+    /// Width of the text as a fraction of the terminal's. It never gets
+    /// narrower than 50 columns or wider than the terminal.
+    pub line_width: f64,
+    /// Most words on a line of the text, which wraps at the width or this,
+    /// whichever comes first.
+    pub line_words: usize,
+    pub alignment: Alignment,
+    /// Columns a tab in a verbatim text takes up.
+    pub tab_width: u16,
+    /// The cursor during tests. It's a steady block again once the game
+    /// exits, whatever this is.
+    pub cursor: CursorStyle,
+    /// Break mistakes down by finger on the results screen, as typed on
+    /// this layout.
+    pub keyboard: Option<KeyboardLayout>,
+    /// Draw the next character to type inverted, for terminals whose cursor
+    /// is hard to see.
+    pub highlight_next: bool,
+    /// Run a pacer through the text at this many wpm, a character drawn
+    /// underlined for the user to keep up with. It stops at the end of the
+    /// text if it gets there first.
+    pub pacer: Option<u32>,
+    /// Also show the wpm by correct words on the results screen, next to
+    /// the usual one by characters, see `GameResults::word_wpm`.
+    pub word_wpm: bool,
+    /// Show a running wpm and accuracy readout next to the hint line.
+    pub live_stats: bool,
+    /// Show the time since the first keystroke in the top right corner.
+    pub timer: bool,
+    /// Show how much of the text, or of the time limit, is done on the
+    /// bottom line.
+    pub progress: bool,
+    /// Show the mode, word count or time limit, and seed of the test at the
+    /// top, to tell tests apart or compare a shared one.
+    pub header: bool,
+    /// Show the keys to restart, pause and quit under the test. Hidden, the
+    /// row is left to the text, so a test fits a terminal a line shorter,
+    /// unless `live_stats` or `confirm_restart` still draw there.
+    pub hints: bool,
+    /// Draw what's typed at most this often rather than after every key,
+    /// for terminals slow enough to fall behind fast typing. Keys are still
+    /// scored one at a time as they come, only the drawing is batched.
+    pub flush_interval: Option<Duration>,
+    /// Seconds to count down before the test starts. Keys pressed during the
+    /// countdown are ignored, except ctrl-c.
+    pub countdown: Option<u8>,
+    /// Colors of the game, see `Theme::by_name` for the built-in ones.
+    pub theme: Theme,
+    /// File every completed test is appended to, see
+    /// `GameResults::append_to`. Nothing is recorded when this is `None`.
+    pub history: Option<PathBuf>,
+    /// File the test being typed is saved to every few seconds, for
+    /// `Game::resume` after a crash. It's deleted once a test ends in any
+    /// other way. Nothing is saved when this is `None`.
+    pub autosave: Option<PathBuf>,
+    /// CSV file every completed test is appended to, see
+    /// `GameResults::append_csv_to`.
+    pub export: Option<PathBuf>,
+    /// File every key pressed during tests is appended to, see `KeyLog`.
+    pub key_log: Option<PathBuf>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            mode: Mode::Markov,
+            num_words: 30,
+            time_limit: None,
+            corpus: None,
+            quotes: None,
+            text: None,
+            order: 2,
+            seed: None,
+            punctuation: false,
+            punctuation_rate: 0.3,
+            prettify: false,
+            min_word_len: None,
+            max_word_len: None,
+            reverse_words: false,
+            reverse_letters: false,
+            strict_space: false,
+            line_end_space: true,
+            final_space: false,
+            scoring: true,
+            confirm_restart: false,
+            chain_cache: None,
+            lock_text: false,
+            esc_quits: true,
+            error_feedback: ErrorFeedback::Off,
+            show_typed_mistakes: false,
+            line_width: 0.4,
+            line_words: 10,
+            alignment: Alignment::Center,
+            cursor: CursorStyle::BlinkingBar,
+            tab_width: 4,
+            keyboard: None,
+            highlight_next: false,
+            pacer: None,
+            word_wpm: false,
+            live_stats: true,
+            progress: true,
+            timer: true,
+            header: false,
+            hints: true,
+            flush_interval: None,
+            countdown: None,
+            theme: Theme::default(),
+            history: default_history_path(),
+            autosave: default_session_path(),
+            export: None,
+            key_log: None,
+        }
+    }
+}
+
+impl GameConfig {
+    /// A short code for the test this config generates, made of the mode,
+    /// order (followed by `p` when prettified), word count, time limit,
+    /// punctuation rate, seed and a hash of the corpus or quotes, then the
+    /// word lengths if they're limited. Fails without a seed, since the
+    /// words couldn't be generated again. Drill tests also take words from
+    /// the history, so they only come out the same with the same history.
+    pub fn to_share_code(&self) -> Result<String, GameError> {
+        self.share_code_for(self.source_hash()?)
+    }
+
+    // `to_share_code` for a source that was hashed when it was loaded, see
+    // `source_hash_of`
+    pub(crate) fn share_code_for(&self, source_hash: u32) -> Result<String, GameError> {
+        let seed = self
+            .seed
+            .ok_or_else(|| GameError::from(String::from("Only seeded tests can be shared")))?;
+        let mode = match self.mode {
+            Mode::Markov => 'm',
+            Mode::Quotes => 'q',
+            Mode::Verbatim => 'v',
+            Mode::Drill => 'd',
+        };
+        let punctuation = if self.punctuation {
+            (self.punctuation_rate * 1000.0).round() as u32
+        } else {
+            0
+        };
+
+        let mut code = format!(
+            "{}{}{}-{}-{}-{}-{:x}-{:08x}",
+            mode,
+            self.order,
+            if self.prettify { "p" } else { "" },
+            self.num_words,
+            self.time_limit.map_or(0, |limit| limit.as_secs()),
+            punctuation,
+            seed,
+            source_hash,
+        );
+        // left out otherwise, so older codes stay the same
+        if self.min_word_len.is_some() || self.max_word_len.is_some() {
+            code += &format!(
+                "-{}.{}",
+                self.min_word_len.unwrap_or(0),
+                self.max_word_len.unwrap_or(0)
+            );
+        }
+
+        Ok(code)
+    }
+
+    /// The default config, set up to play the test of a share code.
+    pub fn from_share_code(code: &str) -> Result<Self, GameError> {
+        GameConfig::default().with_share_code(code)
+    }
+
+    /// This config, set up to play the test of a share code. The corpus or
+    /// quotes file stays as it is, and has to be the one the code was made
+    /// with.
+    pub fn with_share_code(mut self, code: &str) -> Result<Self, GameError> {
+        let invalid = || GameError::from(format!("Invalid share code '{}'", code));
+
+        let fields: Vec<&str> = code.trim().split('-').collect();
+        // the word lengths may follow
+        let (fields, word_lens) = fields.split_at(fields.len().min(6));
+        let [mode_and_order, num_words, time_limit, punctuation, seed, hash] = fields[..] else {
+            return Err(invalid());
+        };
+
+        self.mode = match mode_and_order.chars().next() {
+            Some('m') => Mode::Markov,
+            Some('q') => Mode::Quotes,
+            Some('v') => Mode::Verbatim,
+            Some('d') => Mode::Drill,
+            _ => return Err(invalid()),
+        };
+        let order = &mode_and_order[1..];
+        let (order, prettify) = order
+            .strip_suffix('p')
+            .map_or((order, false), |order| (order, true));
+        self.prettify = prettify;
+        self.order = order.parse().map_err(|_| invalid())?;
+        self.num_words = num_words.parse().map_err(|_| invalid())?;
+        self.time_limit = match time_limit.parse().map_err(|_| invalid())? {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        let punctuation: u32 = punctuation.parse().map_err(|_| invalid())?;
+        self.punctuation = punctuation > 0;
+        if self.punctuation {
+            self.punctuation_rate = punctuation as f64 / 1000.0;
+        }
+        self.seed = Some(u64::from_str_radix(seed, 16).map_err(|_| invalid())?);
+        (self.min_word_len, self.max_word_len) = match word_lens {
+            [] => (None, None),
+            [word_lens] => {
+                let (min, max) = word_lens.split_once('.').ok_or_else(invalid)?;
+                let limit = |len: &str| -> Result<Option<usize>, GameError> {
+                    Ok(Some(len.parse().map_err(|_| invalid())?).filter(|len| *len > 0))
+                };
+                (limit(min)?, limit(max)?)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let hash = u32::from_str_radix(hash, 16).map_err(|_| invalid())?;
+        if hash != self.source_hash()? {
+            return Err(GameError::from(String::from(
+                "Share code was made with a different corpus",
+            )));
+        }
+
+        Ok(self)
+    }
+
+    /// The defaults, with the settings `save` wrote to `path` over them.
+    /// Anything that's missing or can't be read stays the default, so a
+    /// damaged file, or none at all, is never an error. Files that are gone
+    /// since are left out too.
+    pub fn load(path: &Path) -> Self {
+        let mut config = GameConfig::default();
+        let Ok(contents) = fs::read_to_string(path) else {
+            return config;
+        };
+
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            let string = || unquote(value);
+            let file = || string().map(PathBuf::from).filter(|path| path.is_file());
+            match key.trim() {
+                "words" => {
+                    config.num_words = value
+                        .parse()
+                        .ok()
+                        .filter(|words| *words > 0)
+                        .unwrap_or(config.num_words)
+                }
+                "mode" => {
+                    config.mode = string()
+                        .as_deref()
+                        .and_then(Mode::by_name)
+                        .unwrap_or(config.mode)
+                }
+                "theme" => {
+                    config.theme = string()
+                        .as_deref()
+                        .and_then(Theme::by_name)
+                        .unwrap_or(config.theme)
+                }
+                "width" => {
+                    if let Some(percent) = value
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|percent| (1..=100).contains(percent))
+                    {
+                        config.line_width = percent as f64 / 100.0;
+                    }
+                }
+                "corpus" => config.corpus = file(),
+                "quotes" => config.quotes = file(),
+                "text" => config.text = file(),
+                _ => {}
+            }
+        }
+
+        // a verbatim text has no default to fall back to
+        if config.mode == Mode::Verbatim && config.text.is_none() {
+            config.mode = Mode::Markov;
+        }
+        config
+    }
+
+    /// Writes the word count, mode, theme, width and the files the text
+    /// comes from to `path`, for `load` on the next start. It's TOML, named
+    /// like the command line options.
+    pub fn save(&self, path: &Path) -> Result<(), GameError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut contents = format!(
+            "words = {}\nmode = {}\n",
+            self.num_words,
+            quote(self.mode.name())
+        );
+        if let Some(theme) = self.theme.name() {
+            contents += &format!("theme = {}\n", quote(theme));
+        }
+        contents += &format!("width = {}\n", (self.line_width * 100.0).round());
+        for (key, file) in [
+            ("corpus", &self.corpus),
+            ("quotes", &self.quotes),
+            ("text", &self.text),
+        ] {
+            if let Some(file) = file.as_deref().and_then(Path::to_str) {
+                contents += &format!("{} = {}\n", key, quote(file));
+            }
+        }
+
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    // FNV-1a of the words the test is made from, which doesn't change with
+    // the platform or Rust version like `DefaultHasher` can. Only whitespace
+    // separated tokens count, so line endings don't matter.
+    fn source_hash(&self) -> Result<u32, GameError> {
+        let loaded = match self.mode {
+            Mode::Markov | Mode::Drill => load_tokens(self.corpus.as_deref())?,
+            Mode::Quotes => load_quotes(self.quotes.as_deref())?,
+            Mode::Verbatim => load_verbatim(self.text.as_deref())?,
+        };
+
+        Ok(source_hash_of(self.mode, &loaded))
+    }
+}
+
+// the hash share codes keep of what `mode` loaded: the corpus tokens, the
+// quotes or the lines of the text
+pub(crate) fn source_hash_of(mode: Mode, loaded: &[String]) -> u32 {
+    let source = match mode {
+        Mode::Markov | Mode::Drill => loaded.join(" "),
+        Mode::Quotes => loaded
+            .iter()
+            .map(|quote| quote.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Mode::Verbatim => loaded.join("\n"),
+    };
+
+    fnv1a(&source)
+}
+
+/// `$XDG_CONFIG_HOME/rusty-typeracer/config.toml`, falling back to
+/// `~/.config` when `XDG_CONFIG_HOME` is unset.
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_dir.join("rusty-typeracer").join("config.toml"))
+}
+
+/// `$XDG_DATA_HOME/rusty-typeracer/history.jsonl`, falling back to
+/// `~/.local/share` when `XDG_DATA_HOME` is unset.
+pub fn default_history_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("history.jsonl"))
+}
+
+/// `session` next to the default history, where it survives a reboot
+/// unlike in a temp directory.
+pub fn default_session_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("session"))
+}
+
+fn data_dir() -> Option<PathBuf> {
+    let data_dir = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local").join("share"),
+    };
+
+    Some(data_dir.join("rusty-typeracer"))
+}
+
+// a TOML basic string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unquote(value: &str) -> Option<String> {
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                c @ ('\\' | '"') => unquoted.push(c),
+                _ => return None,
+            },
+            c => unquoted.push(c),
+        }
+    }
+    Some(unquoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_code_round_trips() {
+        let config = GameConfig {
+            order: 3,
+            num_words: 45,
+            time_limit: Some(Duration::from_secs(60)),
+            seed: Some(0xdead_beef),
+            punctuation: true,
+            ..GameConfig::default()
+        };
+        let code = config.to_share_code().unwrap();
+        assert!(code.starts_with("m3-45-60-300-deadbeef-"), "{}", code);
+
+        let imported = GameConfig::from_share_code(&code).unwrap();
+        assert_eq!(imported.mode, Mode::Markov);
+        assert_eq!(imported.order, 3);
+        assert_eq!(imported.num_words, 45);
+        assert_eq!(imported.time_limit, Some(Duration::from_secs(60)));
+        assert_eq!(imported.seed, Some(0xdead_beef));
+        assert!(imported.punctuation);
+        assert_eq!(imported.punctuation_rate, 0.3);
+        assert_eq!(imported.to_share_code().unwrap(), code);
+    }
+
+    #[test]
+    fn share_code_keeps_word_lengths() {
+        let config = GameConfig {
+            seed: Some(1),
+            max_word_len: Some(5),
+            ..GameConfig::default()
+        };
+        let code = config.to_share_code().unwrap();
+        assert!(code.ends_with("-0.5"), "{}", code);
+
+        let imported = GameConfig::from_share_code(&code).unwrap();
+        assert_eq!((imported.min_word_len, imported.max_word_len), (None, Some(5)));
+        let imported = GameConfig::from_share_code(code.trim_end_matches("-0.5")).unwrap();
+        assert_eq!((imported.min_word_len, imported.max_word_len), (None, None));
+        assert!(GameConfig::from_share_code(&(code + "-1.1")).is_err());
+    }
+
+    #[test]
+    fn share_code_keeps_prettify() {
+        let config = GameConfig { seed: Some(1), prettify: true, ..GameConfig::default() };
+        let code = config.to_share_code().unwrap();
+        assert!(code.starts_with("m2p-"), "{}", code);
+
+        assert!(GameConfig::from_share_code(&code).unwrap().prettify);
+        assert!(!GameConfig::from_share_code(&code.replacen("m2p", "m2", 1)).unwrap().prettify);
+        assert!(GameConfig::from_share_code(&code.replacen("m2p", "m2pp", 1)).is_err());
+    }
+
+    #[test]
+    fn config_is_saved_and_loaded() {
+        let dir = env::temp_dir().join(format!("typeracer-config-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let text = dir.join("a \"text\" \\ file");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&text, "typed as it is").unwrap();
+
+        // nothing saved yet
+        assert_eq!(GameConfig::load(&path).num_words, GameConfig::default().num_words);
+
+        let config = GameConfig {
+            num_words: 12,
+            mode: Mode::Verbatim,
+            text: Some(text.clone()),
+            theme: Theme::high_contrast(),
+            line_width: 0.55,
+            ..GameConfig::default()
+        };
+        config.save(&path).unwrap();
+        let loaded = GameConfig::load(&path);
+        assert_eq!(loaded.num_words, 12);
+        assert_eq!(loaded.mode, Mode::Verbatim);
+        assert_eq!(loaded.text, Some(text.clone()));
+        assert_eq!(loaded.theme, Theme::high_contrast());
+        assert_eq!(loaded.line_width, 0.55);
+
+        // a text that's gone can't be typed in verbatim mode any more
+        fs::remove_file(&text).unwrap();
+        let loaded = GameConfig::load(&path);
+        assert_eq!((loaded.mode, loaded.text), (Mode::Markov, None));
+
+        // what can't be read is the default
+        fs::write(
+            &path,
+            "words = many\nmode = \"poetry\"\ntheme = solarized\nwidth = 0.5\n\0garbage\n",
+        )
+        .unwrap();
+        let loaded = GameConfig::load(&path);
+        let default = GameConfig::default();
+        assert_eq!(
+            (loaded.num_words, loaded.mode, loaded.theme),
+            (default.num_words, default.mode, default.theme)
+        );
+        assert_eq!(loaded.line_width, default.line_width);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn share_code_needs_a_seed() {
+        assert!(GameConfig::default().to_share_code().is_err());
+    }
+
+    #[test]
+    fn bad_share_codes_are_rejected() {
+        for code in ["", "m2-30", "x2-30-0-0-1-0", "m2-30-0-0-zz-0", "m2-30-0-0-1-0-0"] {
+            let error = GameConfig::from_share_code(code).unwrap_err();
+            assert!(error.msg.starts_with("Invalid share code"), "{}: {}", code, error.msg);
+        }
+
+        let code = GameConfig { seed: Some(1), ..GameConfig::default() }.to_share_code().unwrap();
+        let other_corpus = code.rsplit_once('-').unwrap().0.to_string() + "-00000000";
+        let error = GameConfig::from_share_code(&other_corpus).unwrap_err();
+        assert!(error.msg.contains("different corpus"));
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt::{self, Display};
+
+/// A keyboard layout, used to tell which physical key, and so which finger,
+/// types a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardLayout {
+    Qwerty,
+    Dvorak,
+    Colemak,
+}
+
+/// A row of keys on a keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Row {
+    Number,
+    Top,
+    Home,
+    Bottom,
+    Space,
+}
+
+/// The finger that types a key when touch typing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Finger {
+    LeftPinky,
+    LeftRing,
+    LeftMiddle,
+    LeftIndex,
+    Thumb,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightPinky,
+}
+
+// fingers of the keys of a letter row from the left, any keys further right
+// belong to the right pinky
+const ROW_FINGERS: [Finger; 10] = [
+    Finger::LeftPinky,
+    Finger::LeftRing,
+    Finger::LeftMiddle,
+    Finger::LeftIndex,
+    Finger::LeftIndex,
+    Finger::RightIndex,
+    Finger::RightIndex,
+    Finger::RightMiddle,
+    Finger::RightRing,
+    Finger::RightPinky,
+];
+
+// characters of each row from the left, without and with shift
+type Rows = [(&'static str, &'static str); 4];
+
+const NUMBER_ROW: (&str, &str) = ("`1234567890-=", "~!@#$%^&*()_+");
+
+const QWERTY: Rows = [
+    NUMBER_ROW,
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+    ("asdfghjkl;'", "ASDFGHJKL:\""),
+    ("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+
+const DVORAK: Rows = [
+    ("`1234567890[]", "~!@#$%^&*(){}"),
+    ("',.pyfgcrl/=\\", "\"<>PYFGCRL?+|"),
+    ("aoeuidhtns-", "AOEUIDHTNS_"),
+    (";qjkxbmwvz", ":QJKXBMWVZ"),
+];
+
+const COLEMAK: Rows = [
+    NUMBER_ROW,
+    ("qwfpgjluy;[]\\", "QWFPGJLUY:{}|"),
+    ("arstdhneio'", "ARSTDHNEIO\""),
+    ("zxcvbkm,./", "ZXCVBKM<>?"),
+];
+
+impl KeyboardLayout {
+    /// The layout called `name`: qwerty, dvorak or colemak.
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "qwerty" => Some(KeyboardLayout::Qwerty),
+            "dvorak" => Some(KeyboardLayout::Dvorak),
+            "colemak" => Some(KeyboardLayout::Colemak),
+            _ => None,
+        }
+    }
+
+    /// The row and finger of the key typing `c` on this layout, if it's on
+    /// a US keyboard at all.
+    pub fn key(self, c: char) -> Option<(Row, Finger)> {
+        if c == ' ' {
+            return Some((Row::Space, Finger::Thumb));
+        }
+
+        let rows = match self {
+            KeyboardLayout::Qwerty => &QWERTY,
+            KeyboardLayout::Dvorak => &DVORAK,
+            KeyboardLayout::Colemak => &COLEMAK,
+        };
+        let names = [Row::Number, Row::Top, Row::Home, Row::Bottom];
+        for (row, (plain, shifted)) in names.into_iter().zip(rows) {
+            let Some(column) = plain.chars().position(|key| key == c)
+                .or_else(|| shifted.chars().position(|key| key == c))
+            else {
+                continue;
+            };
+            // the number row starts half a key further left, its first
+            // two keys are both the left pinky's
+            let column = if row == Row::Number { column.saturating_sub(1) } else { column };
+            let finger = ROW_FINGERS.get(column).copied().unwrap_or(Finger::RightPinky);
+            return Some((row, finger));
+        }
+
+        None
+    }
+}
+
+impl Display for Finger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Finger::LeftPinky => "left pinky",
+            Finger::LeftRing => "left ring",
+            Finger::LeftMiddle => "left middle",
+            Finger::LeftIndex => "left index",
+            Finger::Thumb => "thumb",
+            Finger::RightIndex => "right index",
+            Finger::RightMiddle => "right middle",
+            Finger::RightRing => "right ring",
+            Finger::RightPinky => "right pinky",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_by_layout() {
+        assert_eq!(KeyboardLayout::Qwerty.key('f'), Some((Row::Home, Finger::LeftIndex)));
+        assert_eq!(KeyboardLayout::Dvorak.key('f'), Some((Row::Top, Finger::RightIndex)));
+        assert_eq!(KeyboardLayout::Colemak.key('f'), Some((Row::Top, Finger::LeftMiddle)));
+
+        assert_eq!(KeyboardLayout::Qwerty.key('P'), Some((Row::Top, Finger::RightPinky)));
+        assert_eq!(KeyboardLayout::Qwerty.key('|'), Some((Row::Top, Finger::RightPinky)));
+        assert_eq!(KeyboardLayout::Qwerty.key('1'), Some((Row::Number, Finger::LeftPinky)));
+        assert_eq!(KeyboardLayout::Qwerty.key('('), Some((Row::Number, Finger::RightRing)));
+        assert_eq!(KeyboardLayout::Dvorak.key(';'), Some((Row::Bottom, Finger::LeftPinky)));
+        assert_eq!(KeyboardLayout::Colemak.key(' '), Some((Row::Space, Finger::Thumb)));
+        assert_eq!(KeyboardLayout::Qwerty.key('é'), None);
+    }
+
+    #[test]
+    fn every_letter_has_a_key() {
+        for layout in [KeyboardLayout::Qwerty, KeyboardLayout::Dvorak, KeyboardLayout::Colemak] {
+            for c in ('a'..='z').chain('A'..='Z').chain('0'..='9') {
+                assert!(layout.key(c).is_some(), "{:?} {}", layout, c);
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use termion::event::Key;
+use termion::input::TermRead;
+
+use crate::GameError;
+
+/// Reads keys on a background thread, so the game can wait for a key with a
+/// timeout instead of blocking on stdin.
+pub struct KeyReader {
+    keys: Receiver<io::Result<Key>>,
+}
+
+impl KeyReader {
+    /// Reads keys from stdin until it is closed.
+    pub fn new() -> Self {
+        let (sender, keys) = mpsc::channel();
+
+        thread::spawn(move || {
+            for key in io::stdin().keys() {
+                if sender.send(key).is_err() {
+                    break;
+                }
+            }
+        });
+
+        KeyReader { keys }
+    }
+
+    /// Hands out the given keys, then behaves like a closed stdin.
+    pub fn from_keys<I>(keys: I) -> Self
+        where
+        I: IntoIterator<Item = Key>,
+    {
+        let (sender, receiver) = mpsc::channel();
+        for key in keys {
+            sender.send(Ok(key)).expect("receiver is still alive");
+        }
+
+        KeyReader { keys: receiver }
+    }
+
+    /// Hands out each key once its delay after the previous one passed,
+    /// then behaves like a closed stdin.
+    pub fn from_timed_keys<I>(keys: I) -> Self
+        where
+        I: IntoIterator<Item = (Duration, Key)> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (delay, key) in keys {
+                thread::sleep(delay);
+                if sender.send(Ok(key)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        KeyReader { keys: receiver }
+    }
+
+    /// Waits for the next key.
+    pub fn next_key(&self) -> Result<Key, GameError> {
+        match self.keys.recv() {
+            Ok(key) => Ok(key?),
+            Err(_) => Err(stdin_closed()),
+        }
+    }
+
+    /// Waits up to `timeout` for the next key, `None` meaning none came.
+    pub fn next_key_timeout(&self, timeout: Duration) -> Result<Option<Key>, GameError> {
+        match self.keys.recv_timeout(timeout) {
+            Ok(key) => Ok(Some(key?)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(stdin_closed()),
+        }
+    }
+}
+
+impl Default for KeyReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn stdin_closed() -> GameError {
+    GameError::from(String::from("Input was closed before the test ended"))
+}
+
+// first line of a key log, naming the columns
+const KEY_LOG_HEADER: &str = "ms\tkey\tindex";
+
+/// Every key pressed during tests, one tab separated line each: the
+/// milliseconds since the test's first key, the key as `key_name` writes
+/// it, and the cursor index into the text after the key was handled.
+/// Tests are appended one after another, each starting again at 0 ms.
+pub struct KeyLog {
+    file: File,
+}
+
+impl KeyLog {
+    /// Opens the log at `path` for appending, starting it with the column
+    /// names if it's new or empty.
+    pub fn open(path: &Path) -> Result<Self, GameError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| {
+                GameError::from(format!("Could not open {}: {}", path.display(), error))
+            })?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", KEY_LOG_HEADER)?;
+        }
+
+        Ok(KeyLog { file })
+    }
+
+    /// Appends a line for `key`, pressed `at` into the test, which left the
+    /// cursor on `index`.
+    pub fn log(&mut self, at: Duration, key: Key, index: usize) -> Result<(), GameError> {
+        writeln!(self.file, "{}\t{}\t{}", at.as_millis(), key_name(key), index)?;
+        Ok(())
+    }
+}
+
+/// `key` as a word for the key log: the character itself, with tabs,
+/// newlines and backslashes escaped like `\t`, or names like `backspace`,
+/// `ctrl-w` and `alt-b`.
+pub fn key_name(key: Key) -> String {
+    let char_name = |c: char| match c {
+        '\t' => String::from("\\t"),
+        '\n' => String::from("\\n"),
+        '\r' => String::from("\\r"),
+        '\\' => String::from("\\\\"),
+        '\x7f' => String::from("del"),
+        c => c.to_string(),
+    };
+
+    match key {
+        Key::Char(c) => char_name(c),
+        Key::Ctrl(c) => format!("ctrl-{}", char_name(c)),
+        Key::Alt(c) => format!("alt-{}", char_name(c)),
+        Key::Backspace => String::from("backspace"),
+        Key::Delete => String::from("delete"),
+        Key::Esc => String::from("esc"),
+        Key::Left => String::from("left"),
+        Key::Right => String::from("right"),
+        Key::Up => String::from("up"),
+        Key::Down => String::from("down"),
+        Key::Home => String::from("home"),
+        Key::End => String::from("end"),
+        Key::PageUp => String::from("pageup"),
+        Key::PageDown => String::from("pagedown"),
+        Key::BackTab => String::from("backtab"),
+        Key::Insert => String::from("insert"),
+        Key::F(n) => format!("f{}", n),
+        Key::Null => String::from("null"),
+        _ => String::from("unknown"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_keys_then_closed() {
+        let keys = KeyReader::from_keys([Key::Char('a'), Key::Backspace]);
+
+        assert_eq!(keys.next_key().unwrap(), Key::Char('a'));
+        assert_eq!(
+            keys.next_key_timeout(Duration::from_millis(10)).unwrap(),
+            Some(Key::Backspace)
+        );
+        assert!(keys.next_key().is_err());
+        assert!(keys.next_key_timeout(Duration::from_millis(10)).is_err());
+    }
+
+    #[test]
+    fn timed_keys_arrive_late() {
+        let keys = KeyReader::from_timed_keys([(Duration::from_millis(200), Key::Char('a'))]);
+
+        assert_eq!(keys.next_key_timeout(Duration::from_millis(10)).unwrap(), None);
+        assert_eq!(keys.next_key().unwrap(), Key::Char('a'));
+        assert!(keys.next_key().is_err());
+    }
+
+    #[test]
+    fn keys_are_named_for_the_log() {
+        let names: Vec<String> = [
+            Key::Char('a'),
+            Key::Char(' '),
+            Key::Char('\t'),
+            Key::Char('\n'),
+            Key::Char('\\'),
+            Key::Char('é'),
+            Key::Backspace,
+            Key::Ctrl('w'),
+            Key::Alt('\x7f'),
+            Key::F(5),
+        ]
+        .into_iter()
+        .map(key_name)
+        .collect();
+
+        assert_eq!(names[..6], ["a", " ", "\\t", "\\n", "\\\\", "é"]);
+        assert_eq!(names[6..], ["backspace", "ctrl-w", "alt-del", "f5"]);
+    }
+
+    #[test]
+    fn key_logs_are_appended_to() {
+        let dir = std::env::temp_dir().join(format!("typeracer-keylog-{}", std::process::id()));
+        let path = dir.join("keys.tsv");
+
+        let mut log = KeyLog::open(&path).unwrap();
+        log.log(Duration::ZERO, Key::Char('a'), 1).unwrap();
+        log.log(Duration::from_millis(1250), Key::Char('\t'), 2).unwrap();
+        drop(log);
+        // the header isn't written again
+        KeyLog::open(&path).unwrap().log(Duration::from_millis(7), Key::Backspace, 0).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(contents, "ms\tkey\tindex\n0\ta\t1\n1250\t\\t\t2\n7\tbackspace\t0\n");
+    }
+}
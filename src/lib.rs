@@ -1,35 +1,301 @@
+pub mod config;
+pub mod keyboard;
+pub mod keys;
+pub mod menu;
+pub mod picker;
+pub mod provider;
 pub mod results;
+pub mod scoring;
+pub mod session;
 pub mod tui;
 pub mod text;
+pub mod theme;
 pub mod markov;
 
 use std::io::{
-    StdinLock,
+    self,
     BufReader,
     BufRead,
-    self,
+    Stdout,
+    Write,
 };
 use std::path::Path;
-use std::time::Instant;
-use std::fs::{
-    File,
-};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use std::fs::File;
 
-use results::GameResults;
-use termion::input::Keys;
-use termion::{color, event::Key, input::TermRead};
-use tui::{GameTui};
-use text::Text;
+use config::{source_hash_of, ErrorFeedback, GameConfig, Mode};
+use rand::{Isaac64Rng, Rng, SeedableRng};
+use keyboard::KeyboardLayout;
+use keys::{KeyLog, KeyReader};
+use provider::{pick_quote, TextProvider};
+use results::{read_history_mistaken_words, read_history_wpm, GameResults};
+use scoring::Scorer;
+use session::Session;
+use termion::event::Key;
+use termion::raw::RawTerminal;
+use theme::{Theme, ThemeColor};
+use tui::{shown_char, untyped_text, GameTui};
+use text::{prettify, printable, punctuate, Text};
 use crate::markov::{
-    generate_text,
-    create_cache,
+    corpus_hash,
+    load_cache,
+    save_cache,
+    take_to_sentence_end,
+    MarkovWords,
+    WordChain,
 };
 
+const DEFAULT_CORPUS: &str = include_str!("./input.txt");
+const DEFAULT_QUOTES: &str = include_str!("./quotes.txt");
+
+/// Where `Game` takes the words of each test from.
+pub enum TextSource {
+    /// A markov chain, built once and shared by every test.
+    Markov(Rc<WordChain>),
+    Quotes(Vec<String>),
+    /// The lines of a text file, typed as they are.
+    Verbatim(Vec<String>),
+    /// The markov chain with words mistyped before mixed in, each with how
+    /// often it was missed. Sorted, so a seed always picks the same ones.
+    Drill(Rc<WordChain>, Vec<(String, u32)>),
+    /// Words from a provider plugged in with `Game::set_text_provider`,
+    /// which a seed can't generate again.
+    Provider(Rc<RefCell<Box<dyn TextProvider>>>),
+}
+
+// the words of a test, and where more come from in a timed one
+type GeneratedWords = (Vec<String>, Box<dyn Iterator<Item = String>>);
+
+impl TextSource {
+    /// Loads the corpus, quotes or text of `config.mode`, building the
+    /// markov chain once.
+    pub fn new(config: &GameConfig) -> Result<Self, GameError> {
+        Ok(Self::load(config)?.0)
+    }
+
+    // `new`, along with the hash of what was loaded for the share codes, so
+    // they don't need the file once it's loaded
+    fn load(config: &GameConfig) -> Result<(Self, u32), GameError> {
+        let mode = config.mode;
+        Ok(match mode {
+            Mode::Markov => {
+                let tokens = load_tokens(config.corpus.as_deref())?;
+                check_corpus(&tokens, config.order)?;
+                check_word_lens(&tokens, config.min_word_len, config.max_word_len)?;
+                let hash = source_hash_of(mode, &tokens);
+                (TextSource::Markov(Rc::new(load_chain(tokens, config)?)), hash)
+            }
+            Mode::Quotes => {
+                let quotes = load_quotes(config.quotes.as_deref())?;
+                let hash = source_hash_of(mode, &quotes);
+                (TextSource::Quotes(quotes), hash)
+            }
+            Mode::Verbatim => {
+                let lines = load_verbatim(config.text.as_deref())?;
+                let hash = source_hash_of(mode, &lines);
+                (TextSource::Verbatim(lines), hash)
+            }
+            Mode::Drill => {
+                let tokens = load_tokens(config.corpus.as_deref())?;
+                check_corpus(&tokens, config.order)?;
+                check_word_lens(&tokens, config.min_word_len, config.max_word_len)?;
+                // without a history there's nothing to drill, the markov
+                // text is all there is
+                let mistaken = match &config.history {
+                    Some(history) => read_history_mistaken_words(history, DRILL_TESTS)?,
+                    None => HashMap::new(),
+                };
+                let (min_len, max_len) = (config.min_word_len, config.max_word_len);
+                let mut drills: Vec<(String, u32)> = mistaken
+                    .into_iter()
+                    .filter(|(word, _)| word_len_fits(word, min_len, max_len))
+                    .collect();
+                drills.sort();
+                let hash = source_hash_of(mode, &tokens);
+                (TextSource::Drill(Rc::new(load_chain(tokens, config)?), drills), hash)
+            }
+        })
+    }
+
+    /// Which chars of `words`, joined by spaces as in the test, belong to
+    /// drill words, spelled `backwards` with `GameConfig::reverse_letters`.
+    /// Empty for the other sources.
+    pub fn drill_marks(&self, words: &[String], backwards: bool) -> Vec<bool> {
+        let TextSource::Drill(_, drills) = self else {
+            return Vec::new();
+        };
+
+        let mut marks = Vec::new();
+        for word in words.iter().filter(|word| !word.is_empty()) {
+            if !marks.is_empty() {
+                marks.push(false);
+            }
+            let drilled = drills.iter().any(|(drill, _)| {
+                if backwards { drill.chars().rev().eq(word.chars()) } else { drill == word }
+            });
+            marks.extend(std::iter::repeat_n(drilled, word.chars().count()));
+        }
+        marks
+    }
+
+    /// A warning that the markov chain is too repetitive for the texts to
+    /// differ much, for the sources built on one.
+    pub fn corpus_warning(&self) -> Option<String> {
+        let (TextSource::Markov(chain) | TextSource::Drill(chain, _)) = self else {
+            return None;
+        };
+
+        let stats = chain.stats();
+        (stats.avg_branching < MIN_BRANCHING).then(|| {
+            format!(
+                "This corpus is repetitive, texts will look alike ({:.2} words follow each key on average)",
+                stats.avg_branching,
+            )
+        })
+    }
+
+    /// The words of the test `Game` shows for `seed`, without a terminal.
+    pub fn words(&self, config: &GameConfig, seed: u64) -> Result<Vec<String>, GameError> {
+        Ok(self.generate(config, seed)?.0)
+    }
+
+    fn generate(&self, config: &GameConfig, seed: u64) -> Result<GeneratedWords, GameError> {
+        Ok(match self {
+            TextSource::Markov(cache) => {
+                take_words(markov_stream(Rc::clone(cache), config, seed)?, config)
+            }
+            TextSource::Drill(cache, drills) => {
+                let stream = markov_stream(Rc::clone(cache), config, seed)?;
+                take_words(drill_stream(stream, drills, seed), config)
+            }
+            TextSource::Quotes(quotes) => {
+                // the whole quote, nothing comes after it
+                let words = pick_quote(quotes, &mut Isaac64Rng::from_seed(&[seed][..]));
+                (words, Box::new(std::iter::empty()))
+            }
+            TextSource::Verbatim(lines) => {
+                let words = lines
+                    .iter()
+                    .flat_map(|line| line.split_whitespace())
+                    .map(String::from)
+                    .collect();
+                (words, Box::new(std::iter::empty()))
+            }
+            TextSource::Provider(provider) => {
+                let words = provider.borrow_mut().next_test(config.num_words)?;
+                let provider = Rc::clone(provider);
+                // a timed test ends early rather than failing once the
+                // provider can't go on
+                let more = std::iter::from_fn(move || {
+                    provider
+                        .borrow_mut()
+                        .next_test(WORDS_PER_REFILL)
+                        .ok()
+                        .filter(|words| !words.is_empty())
+                });
+                (words, Box::new(more.flatten()))
+            }
+        })
+    }
+}
+
+// the words of a test from an endless stream, the rest of it left for a
+// timed one
+fn take_words(mut stream: Box<dyn Iterator<Item = String>>, config: &GameConfig) -> GeneratedWords {
+    let words = if config.time_limit.is_some() {
+        // more words follow, there's no end to finish cleanly
+        stream.by_ref().take(config.num_words).collect()
+    } else {
+        take_to_sentence_end(&mut stream, config.num_words)
+    };
+    (words, stream)
+}
+
+// `words` with some swapped for drill words, picked in proportion to how
+// often each was missed
+fn drill_stream(
+    words: Box<dyn Iterator<Item = String>>,
+    drills: &[(String, u32)],
+    seed: u64,
+) -> Box<dyn Iterator<Item = String>> {
+    let total: u32 = drills.iter().map(|(_, misses)| misses).sum();
+    if total == 0 {
+        return words;
+    }
+
+    let drills = drills.to_vec();
+    let mut rng = Isaac64Rng::from_seed(&[seed, 2][..]);
+    Box::new(words.map(move |word| {
+        if rng.gen::<f64>() >= DRILL_RATE {
+            return word;
+        }
+        let mut pick = rng.gen_range(0, total);
+        for (drill, misses) in &drills {
+            if pick < *misses {
+                return drill.clone();
+            }
+            pick -= misses;
+        }
+        word
+    }))
+}
 
-pub struct Game {
-    tui: GameTui,
+// endless words from the corpus, seeded like the rest of the test
+fn markov_stream(
+    cache: Rc<WordChain>,
+    config: &GameConfig,
+    seed: u64,
+) -> Result<Box<dyn Iterator<Item = String>>, GameError> {
+    let words =
+        MarkovWords::<_, WordChain>::new(cache, Isaac64Rng::from_seed(&[seed][..]))?.endless();
+    let words: Box<dyn Iterator<Item = String>> = if config.punctuation {
+        let rate = config.punctuation_rate;
+        Box::new(punctuate(words, rate, Isaac64Rng::from_seed(&[seed, 1][..])))
+    } else {
+        Box::new(words)
+    };
+
+    // filtered last, numbers from the punctuation included. The chain
+    // would break off at every word left out otherwise, and
+    // `check_word_lens` made sure some are left
+    let (min_len, max_len) = (config.min_word_len, config.max_word_len);
+    let words = words.filter(move |word| word_len_fits(word, min_len, max_len));
+    // after the filter, so the word before is the one shown
+    Ok(if config.prettify { Box::new(prettify(words)) } else { Box::new(words) })
+}
+
+pub struct Game<W: Write = RawTerminal<Stdout>> {
+    tui: GameTui<W>,
+    config: GameConfig,
+    source: TextSource,
     text: Vec<Text>,
     words: Vec<String>,
+    // the lines to show as they are, instead of wrapping the words
+    lines: Option<Vec<String>>,
+    // where more words come from once the shown ones run low
+    stream: Box<dyn Iterator<Item = String>>,
+    // seed of the current test, random ones included
+    seed: u64,
+    // which chars of the flattened text belong to drill words, empty
+    // outside the drill mode
+    drill_marks: Vec<bool>,
+    // wpm of every recorded test, oldest first
+    history_wpm: Vec<f64>,
+    // what was typed and for how long, when the next test resumes a saved
+    // one
+    resumed: Option<(Vec<char>, Duration)>,
+    // shown above every test, see `TextSource::corpus_warning`
+    corpus_warning: Option<String>,
+    // hash of the source for share codes, none for a text provider
+    source_hash: Option<u32>,
+    // the words generated last, before a timed test took more, shown again
+    // by `restart` with `config.lock_text`
+    generated: Vec<String>,
+    // ctrl-n asked for new words on the next restart
+    new_text: bool,
 }
 
 
@@ -57,13 +323,71 @@ impl std::fmt::Debug for GameError {
     }
 }
 
-impl<'a> Game {
+impl std::fmt::Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl Game {
     pub fn new() -> Result<Self, GameError> {
+        Self::with_config(GameConfig::default())
+    }
+
+    pub fn with_config(config: GameConfig) -> Result<Self, GameError> {
+        Self::with_tui(config, GameTui::new)
+    }
+}
+
+impl<W: Write> Game<W> {
+    /// A game drawing into `writer` instead of the terminal, as if it were
+    /// `size` columns by rows. Together with `KeyReader::from_keys` this runs
+    /// a whole test without a TTY.
+    pub fn headless(config: GameConfig, writer: W, size: (u16, u16)) -> Result<Self, GameError> {
+        Self::with_tui(config, || GameTui::headless(writer, size))
+    }
+
+    // the TUI is made only once the config turned out to be usable
+    fn with_tui<F>(config: GameConfig, make_tui: F) -> Result<Self, GameError>
+        where
+        F: FnOnce() -> GameTui<W>,
+    {
+        let history_wpm = match &config.history {
+            Some(history) => read_history_wpm(history)?,
+            None => Vec::new(),
+        };
+
+        // fail before the terminal is switched to raw mode
+        let (source, source_hash) = TextSource::load(&config)?;
+        let corpus_warning = source.corpus_warning();
+
+        let mut tui = make_tui();
+        tui.set_line_width(config.line_width);
+        tui.set_line_words(config.line_words);
+        tui.set_alignment(config.alignment);
+        tui.set_cursor_style(config.cursor);
+        tui.set_tab_width(config.tab_width);
+        tui.set_final_space(config.final_space);
+        tui.set_show_typed_mistakes(config.show_typed_mistakes);
 
         let mut game = Game {
-            tui: GameTui::new(),
+            tui,
+            config,
+            source,
             words: Vec::new(),
+            lines: None,
             text: Vec::new(),
+            stream: Box::new(std::iter::empty()),
+            seed: 0,
+            drill_marks: Vec::new(),
+            history_wpm,
+            resumed: None,
+            corpus_warning,
+            source_hash: Some(source_hash),
+            generated: Vec::new(),
+            new_text: false,
         };
 
         game.restart()?;
@@ -71,54 +395,237 @@ impl<'a> Game {
         Ok(game)
     }
 
+    pub fn tui(&self) -> &GameTui<W> {
+        &self.tui
+    }
+
+    /// Starts the next test, with the same words as the last one if
+    /// `config.lock_text` is set and ctrl-n didn't ask for new ones.
     pub fn restart(&mut self) -> Result<(), GameError> {
-        self.tui.reset_screen()?;
+        if std::mem::take(&mut self.new_text) {
+            // a random text, the configured seed would give the same one
+            return self.restart_with_seed(None);
+        }
+        if self.config.lock_text && !self.generated.is_empty() {
+            let words = self.generated.clone();
+            // only a timed test takes more words, which are generated again
+            // for it. Other tests leave the source be, a provider would use
+            // up a test, a drill read the history again
+            let stream: Box<dyn Iterator<Item = String>> = if self.config.time_limit.is_some() {
+                self.source.generate(&self.config, self.seed)?.1
+            } else {
+                Box::new(std::iter::empty())
+            };
+            return self.start_test(words, stream);
+        }
+        self.restart_with_seed(self.config.seed)
+    }
 
-        let tokens = include_str!("./input.txt")
-            .split_whitespace()
-            .map(String::from)
-            .collect();
+    /// Takes the words of every test from `provider` from now on, starting a
+    /// test of them. Its tests have no share code.
+    pub fn set_text_provider(&mut self, provider: Box<dyn TextProvider>) -> Result<(), GameError> {
+        self.source = TextSource::Provider(Rc::new(RefCell::new(provider)));
+        self.corpus_warning = None;
+        self.source_hash = None;
+        self.restart_with_seed(None)
+    }
 
-        let cache = create_cache(tokens);
-        self.words = generate_text(cache, 30);
+    /// Restarts with the given seed instead of the configured one, `None`
+    /// meaning a random text.
+    pub fn restart_with_seed(&mut self, seed: Option<u64>) -> Result<(), GameError> {
+        // random texts get a random seed, so they can be shared too
+        let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+        self.seed = seed;
+
+        let (words, stream) = self.source.generate(&self.config, seed)?;
+        self.generated = words.clone();
+        self.start_test(words, stream)
+    }
+
+    // shows a test of `words`, taking more from `stream` if it's timed
+    fn start_test(
+        &mut self,
+        mut words: Vec<String>,
+        mut stream: Box<dyn Iterator<Item = String>>,
+    ) -> Result<(), GameError> {
+        self.tui.reset_screen()?;
+        self.lines = match &self.source {
+            TextSource::Verbatim(lines) => Some(lines.clone()),
+            _ => None,
+        };
+        // a verbatim text is laid out by its lines, which stay as they are
+        if self.lines.is_none() {
+            if self.config.reverse_words {
+                words.reverse();
+            }
+            if self.config.reverse_letters {
+                words = words.iter().map(|word| reversed(word)).collect();
+                stream = Box::new(stream.map(|word| reversed(&word)));
+            }
+        }
+        (self.words, self.stream) = (words, stream);
+        self.drill_marks = self.source.drill_marks(&self.words, self.config.reverse_letters);
+        self.resumed = None;
 
-        self.tui.display_lines_bottom(&[&[
-            Text::from("ctrl-r").with_color(color::Blue),
-            Text::from(" to restart, ").with_faint(),
-            Text::from("ctrl-c").with_color(color::Blue),
-            Text::from(" to abort").with_faint(),
-        ]])?;
+        let header = self.header();
+        self.tui.display_lines_top(&header)?;
+        self.tui.display_lines_bottom(&test_hints(&self.config))?;
 
         self.show_words()?;
 
         Ok(())
     }
 
+    /// Shows the test of `session` again, with what was typed drawn as it
+    /// was, laid out for the terminal as it is now. The next `run` goes on
+    /// where it stopped, counting what was typed as if it had been typed
+    /// once without corrections.
+    pub fn resume(&mut self, session: Session) -> Result<(), GameError> {
+        let original_text: Vec<char> = match &session.lines {
+            Some(lines) => lines.join("\n").chars().collect(),
+            None if self.config.final_space => (session.words.join(" ") + " ").chars().collect(),
+            None => session.words.join(" ").chars().collect(),
+        };
+        if session.input.len() >= original_text.len() {
+            return Err(GameError::from(String::from("Saved test was already finished")));
+        }
+
+        // a timed test goes on with the words that would have come next
+        let (generated, stream) = self.source.generate(&self.config, session.seed)?;
+        let refilled = session.words.len().saturating_sub(generated.len());
+        self.stream = Box::new(stream.skip(refilled));
+        self.seed = session.seed;
+        self.words = session.words;
+        self.lines = session.lines;
+        self.drill_marks = self.source.drill_marks(&self.words, self.config.reverse_letters);
+
+        let layout = Layout::new(&self.words, &self.lines);
+        let header = self.header();
+        self.text = redraw_test(
+            &mut self.tui,
+            &self.config.theme,
+            &header,
+            &test_hints(&self.config),
+            layout,
+            &session.input,
+            &original_text,
+            &self.drill_marks,
+        )?;
+        self.resumed = Some((session.input, session.elapsed));
+
+        Ok(())
+    }
+
+    // saves the test being typed for `resume`
+    fn autosave(&self, input: &[char], elapsed: Duration) -> Result<(), GameError> {
+        let Some(path) = &self.config.autosave else {
+            return Ok(());
+        };
+
+        Session {
+            seed: self.seed,
+            words: self.words.clone(),
+            lines: self.lines.clone(),
+            input: input.to_vec(),
+            elapsed,
+        }
+        .save(path)
+    }
+
+    /// The share code of the current test, see `GameConfig::to_share_code`.
+    /// Fails for the words of a `TextProvider`.
+    pub fn share_code(&self) -> Result<String, GameError> {
+        let Some(source_hash) = self.source_hash else {
+            return Err(GameError::from(String::from(
+                "Tests from a text provider have no share code",
+            )));
+        };
+        GameConfig {
+            seed: Some(self.seed),
+            ..self.config.clone()
+        }
+        .share_code_for(source_hash)
+    }
+
+    // the lines `config.header` shows above the test, after a warning about
+    // the corpus if there is one
+    fn header(&self) -> Vec<Vec<Text>> {
+        let mut lines = Vec::new();
+        if let Some(warning) = &self.corpus_warning {
+            lines.push(vec![Text::from(warning.as_str()).with_color(self.config.theme.error)]);
+        }
+        if !self.config.header {
+            return lines;
+        }
+
+        let mode = self.config.mode.name();
+        let length = match self.config.time_limit {
+            Some(limit) => format!("timed {}s", limit.as_secs()),
+            None => format!("{} words", self.words.len()),
+        };
+        lines.push(vec![Text::from(format!("{} · {}", mode, length)).with_bold()]);
+        lines.push(vec![Text::from(format!("seed {}", self.seed)).with_faint()]);
+        lines
+    }
+
     fn show_words(&mut self) -> Result<(), GameError> {
-        self.text = self.tui.display_words(&self.words)?;
+        let layout = Layout::new(&self.words, &self.lines);
+        self.text = layout.display(&mut self.tui, self.config.theme.faint)?;
+        if !self.drill_marks.is_empty() {
+            let original_text: Vec<char> = self
+                .text
+                .iter()
+                .flat_map(|line| line.text().chars())
+                .collect();
+            draw_drill_words(
+                &mut self.tui,
+                &self.config.theme,
+                &original_text,
+                &self.drill_marks,
+                0,
+            )?;
+        }
         Ok(())
     }
 
-    pub fn run(&mut self, stdin: StdinLock<'a>) -> Result<(bool, GameResults), GameError> {
-        let mut input = Vec::<char>::new();
-        let original_text = self
-            .text
-            .iter()
-            .fold(Vec::<char>::with_capacity(1000), |mut chars, text| {
-                chars.extend(text.text().chars());
-                chars
-            });
-        let original_text = self.text.iter()
-            .flat_map(|text| text.text().chars())
-            .collect::<Vec<_>>();
-        let mut num_errors = 0;
-        let mut num_chars_typed = 0;
+    /// Runs one test on the words shown by the last `restart`, reading keys
+    /// until the text is finished, the time runs out, or the user quits or
+    /// restarts. This is the entry point for playing; returns whether the
+    /// user asked to restart, along with the results. Fails instead of
+    /// panicking if the keys run out, e.g. when stdin is closed.
+    pub fn run(&mut self, keys: &KeyReader) -> Result<(bool, GameResults), GameError> {
+        let (input, resumed_elapsed) = self.resumed.take().unwrap_or_default();
+        let original_text = match &self.lines {
+            Some(lines) => lines.join("\n").chars().collect::<Vec<_>>(),
+            None => self.text.iter()
+                .flat_map(|text| text.text().chars())
+                .collect::<Vec<_>>(),
+        };
+        // a resumed test starts with what was typed before
+        let mut scorer =
+            Scorer::new(original_text, self.config.strict_space).resumed(input, resumed_elapsed);
+        let live_stats = self.config.live_stats;
+        let show_progress = self.config.progress;
+        let line_end_space = self.config.line_end_space || self.lines.is_some();
+        let error_feedback = self.config.error_feedback;
+        let theme = self.config.theme;
+        let header = self.header();
+        let hint = test_hints(&self.config);
+        let mut status_updated_at: Option<Instant> = None;
+        let show_timer = self.config.timer;
+        // whole seconds on the timer, `None` once it was cleared
+        let mut timer_shown_at: Option<u64> = None;
+        let mut saved_at: Option<Instant> = None;
+        // keys came since the test was last saved
+        let mut unsaved = false;
 
         enum TestStatus {
             // last key press did not quit/restart - more keys to be entered
             NotDone,
             // last letter was typed
             Done,
+            // time limit ran out
+            TimeUp,
             // user wants to quit test
             Quit,
             // user wants to restart test
@@ -131,7 +638,7 @@ impl<'a> Game {
             }
 
             fn to_display_results(&self) -> bool {
-                matches!(self, TestStatus::Done)
+                matches!(self, TestStatus::Done | TestStatus::TimeUp)
             }
 
             fn to_restart(&self) -> bool {
@@ -139,158 +646,621 @@ impl<'a> Game {
             }
         }
 
-        let mut process_key = |key: Key| -> Result<TestStatus, GameError> {
+        let time_limit = self.config.time_limit;
+        let mut paused_at: Option<Instant> = None;
+        let confirm_restart = self.config.confirm_restart;
+        let esc_quits = self.config.esc_quits;
+        // waiting for an answer to the restart prompt
+        let mut confirming_restart = false;
+        let mut paused_duration = Duration::ZERO;
+        // the first word and char on screen, once typed lines scrolled away
+        let mut shown_words_from = 0;
+        let mut shown_chars_from = 0;
+        let highlight_next = self.config.highlight_next;
+        // where the highlighted next character is drawn
+        let mut highlighted = None;
+        let pacer = self.config.pacer;
+        // where the pacer was drawn last
+        let mut paced = None;
+
+        // read first key, a ctrl-c during the countdown quits straight away
+        let key = if self.countdown(keys, scorer.input(), scorer.text())? {
+            if highlight_next {
+                move_highlight(
+                    &mut self.tui,
+                    &theme,
+                    scorer.text(),
+                    &self.drill_marks,
+                    0,
+                    scorer.input().len(),
+                    &mut highlighted,
+                )?;
+                self.tui.flush()?;
+            }
+            keys.next_key()?
+        } else {
+            Key::Ctrl('c')
+        };
+        // start the timer, or go on with it
+        let started_at = Instant::now().checked_sub(resumed_elapsed).unwrap_or_else(Instant::now);
+
+        // called with `None` when no key came for a while, which keeps the
+        // timer going and ends timed tests on time
+        let mut key_log = match &self.config.key_log {
+            Some(path) => Some(KeyLog::open(path)?),
+            None => None,
+        };
+
+        // batched drawing needs ticks often enough to flush what was put off
+        let flush_interval = self.config.flush_interval;
+        let tick = flush_interval.map_or(TICK_INTERVAL, |interval| interval.min(TICK_INTERVAL));
+        self.tui.set_flush_interval(flush_interval)?;
+
+        // the scorer is passed in rather than captured, so each key can be
+        // logged with where it left the cursor
+        let mut process_key = |key: Option<Key>,
+                               scorer: &mut Scorer|
+         -> Result<TestStatus, GameError> {
+            self.tui.flush_pending()?;
+            if let Some(at) = paused_at {
+                // any key resumes, the key itself isn't typed
+                let Some(key) = key else {
+                    return Ok(TestStatus::NotDone);
+                };
+                paused_at = None;
+                paused_duration += at.elapsed();
+                if quits(key, esc_quits) {
+                    return Ok(TestStatus::Quit);
+                }
+                self.tui.show_cursor()?;
+                self.text = redraw_test(
+                    &mut self.tui,
+                    &theme,
+                    &header,
+                    &hint,
+                    Layout::new(&self.words[shown_words_from..], &self.lines),
+                    &scorer.input()[shown_chars_from..],
+                    &scorer.text()[shown_chars_from..],
+                    self.drill_marks.get(shown_chars_from..).unwrap_or_default(),
+                )?;
+                if highlight_next {
+                    highlighted = None;
+                    move_highlight(
+                        &mut self.tui,
+                        &theme,
+                        scorer.text(),
+                        &self.drill_marks,
+                        shown_chars_from,
+                        scorer.input().len(),
+                        &mut highlighted,
+                    )?;
+                }
+                paced = None;
+                status_updated_at = None;
+                timer_shown_at = None;
+                return Ok(TestStatus::NotDone);
+            }
+
+            let elapsed = started_at.elapsed() - paused_duration;
+            if time_limit.is_some_and(|limit| elapsed >= limit) {
+                // keys pressed after the limit don't count
+                return Ok(TestStatus::TimeUp);
+            }
+
+            // noticed on the next key or tick
+            if self.tui.has_resized()? {
+                self.text = redraw_test(
+                    &mut self.tui,
+                    &theme,
+                    &header,
+                    &hint,
+                    Layout::new(&self.words[shown_words_from..], &self.lines),
+                    &scorer.input()[shown_chars_from..],
+                    &scorer.text()[shown_chars_from..],
+                    self.drill_marks.get(shown_chars_from..).unwrap_or_default(),
+                )?;
+                if confirming_restart {
+                    self.tui.update_status_line(&restart_prompt(&theme))?;
+                }
+                highlighted = None;
+                paced = None;
+                status_updated_at = None;
+                timer_shown_at = None;
+            }
+
+            if show_timer && timer_shown_at != Some(elapsed.as_secs()) {
+                self.tui.display_timer(elapsed)?;
+                timer_shown_at = Some(elapsed.as_secs());
+            }
+
             match key {
-                Key::Ctrl('c') => {
+                Some(key) if confirming_restart => {
+                    confirming_restart = false;
+                    match key {
+                        Key::Char('y') | Key::Char('Y') | Key::Ctrl('r') | Key::Ctrl('n') => {
+                            return Ok(TestStatus::Restart)
+                        }
+                        Key::Ctrl('c') => return Ok(TestStatus::Quit),
+                        // declined, the key isn't typed
+                        _ => {
+                            self.new_text = false;
+                            self.tui.update_status_line(&hint.concat())?;
+                            status_updated_at = None;
+                        }
+                    }
+                }
+                Some(key) if quits(key, esc_quits) => {
                     return Ok(TestStatus::Quit);
                 }
-                Key::Ctrl('r') => {
+                Some(Key::Ctrl('n')) if confirm_restart => {
+                    self.new_text = true;
+                    confirming_restart = true;
+                    self.tui.update_status_line(&restart_prompt(&theme))?;
+                }
+                Some(Key::Ctrl('n')) => {
+                    self.new_text = true;
                     return Ok(TestStatus::Restart);
                 }
-                Key::Ctrl('w') => {
-                    // delete last word
-                    while !matches!(input.last(), Some(' ') | None) {
-                        if input.pop().is_some() {
-                            self.tui.replace_text(
-                                Text::from(original_text[input.len()]).with_faint(),
-                            )?;
-                        }
+                Some(Key::Ctrl('r')) if confirm_restart => {
+                    confirming_restart = true;
+                    self.tui.update_status_line(&restart_prompt(&theme))?;
+                }
+                Some(Key::Ctrl('r')) => {
+                    return Ok(TestStatus::Restart);
+                }
+                Some(Key::Ctrl('p')) => {
+                    paused_at = Some(Instant::now());
+                    self.tui.reset_screen()?;
+                    self.tui.display_lines(&[
+                        [Text::from("Paused")],
+                        [Text::from("press any key to resume").with_faint()],
+                    ])?;
+                    self.tui.hide_cursor()?;
+                    return Ok(TestStatus::NotDone);
+                }
+                // ctrl-backspace arrives as ^H and alt-backspace as escape
+                // then DEL. Terminals that send ^H for a plain backspace
+                // delete a word each time, and ones that send DEL for
+                // ctrl-backspace delete a character, there's no telling
+                // them apart
+                Some(Key::Ctrl('w') | Key::Ctrl('h') | Key::Alt('\x7f')) => {
+                    // the redraw goes by index, so it's right even when the
+                    // word is back on the line before
+                    let typed_to = scorer.input().len();
+                    scorer.delete_word(shown_chars_from);
+                    for index in (scorer.input().len()..typed_to).rev() {
+                        self.tui.replace_text_at(
+                            index - shown_chars_from,
+                            untyped_at(scorer.text(), &self.drill_marks, index, &theme),
+                        )?;
                     }
                 }
-                Key::Char(c) => {
-                    input.push(c);
+                Some(Key::Char(c)) => {
+                    let now = started_at.elapsed() - paused_duration;
+                    let Some(orig_char) = scorer.type_char(c, now) else {
+                        // words can't run into each other, the space, tab or
+                        // newline has to be typed before the next word
+                        return Ok(TestStatus::NotDone);
+                    };
+                    let mut done = scorer.is_done();
+
+                    let typed = self.tui.typed_char_text(c, orig_char, &theme);
+                    self.tui.display_raw_text(&typed)?;
+                    self.tui.move_to_next_char()?;
+                    // the space ending a line may be typed for the user
+                    if !line_end_space
+                        && !done
+                        && line_end_spaces(&self.text, shown_chars_from)
+                            .contains(&scorer.input().len())
+                    {
+                        scorer.skip_space(now);
+                        self.tui.move_to_next_char()?;
+                        // the final space ends the test as it's skipped
+                        done = scorer.is_done();
+                    }
+                    if orig_char != c {
+                        match error_feedback {
+                            ErrorFeedback::Off => {}
+                            ErrorFeedback::Bell => self.tui.bell()?,
+                            ErrorFeedback::Flash => self.tui.flash(FLASH_DURATION)?,
+                        }
+                    }
 
-                    if input.len() >= original_text.len() {
+                    // the last character is drawn and scored like the others
+                    // before the test ends
+                    if done {
+                        self.tui.flush()?;
                         return Ok(TestStatus::Done);
                     }
 
-                    num_chars_typed += 1;
+                    // a timed test takes more words before reaching the end
+                    // of the last line
+                    if time_limit.is_some() && self.tui.current_line() + 1 >= self.text.len() {
+                        let more: Vec<String> =
+                            self.stream.by_ref().take(WORDS_PER_REFILL).collect();
+                        if !more.is_empty() {
+                            scorer.extend_text(&more);
+                            let marks = self.source.drill_marks(&more, self.config.reverse_letters);
+                            if !marks.is_empty() {
+                                // the space before the new words
+                                self.drill_marks.push(false);
+                                self.drill_marks.extend(marks);
+                            }
+                            self.words.extend(more);
 
-                    if original_text[input.len() - 1] == c {
-                        self.tui
-                            .display_raw_text(&Text::from(c).with_color(color::LightGreen))?;
-                        self.tui.move_to_next_char()?;
-                    } else {
-                        self.tui.display_raw_text(
-                            &Text::from(original_text[input.len() - 1])
-                                .with_underline()
-                                .with_color(color::Red),
-                        )?;
-                        self.tui.move_to_next_char()?;
-                        num_errors += 1;
+                            // the line being typed moves to the top, or the
+                            // start of its word if that was broken up over
+                            // the lines before
+                            let current_line = self.tui.current_line();
+                            let word_start = (0..current_line)
+                                .rev()
+                                .find(|line| self.text[*line].text().ends_with(' '))
+                                .map_or(0, |line| line + 1);
+                            let scrolled = &self.text[..word_start];
+                            // each word on them is followed by a space
+                            shown_words_from += scrolled
+                                .iter()
+                                .map(|line| line.text().matches(' ').count())
+                                .sum::<usize>();
+                            shown_chars_from += scrolled
+                                .iter()
+                                .map(|line| line.text().chars().count())
+                                .sum::<usize>();
+
+                            self.text = redraw_test(
+                                &mut self.tui,
+                                &theme,
+                                &header,
+                                &hint,
+                                Layout::new(&self.words[shown_words_from..], &self.lines),
+                                &scorer.input()[shown_chars_from..],
+                                &scorer.text()[shown_chars_from..],
+                                self.drill_marks.get(shown_chars_from..).unwrap_or_default(),
+                            )?;
+                            highlighted = None;
+                            paced = None;
+                            status_updated_at = None;
+                        }
                     }
                 }
-                Key::Backspace => {
-                    if input.pop().is_some() {
-                        self.tui
-                            .replace_text(Text::from(original_text[input.len()]).with_faint())?;
-                    }
+                Some(Key::Backspace) if scorer.backspace(shown_chars_from) => {
+                    // position by index, the cursor may have wrapped lines
+                    let index = scorer.input().len();
+                    let untyped = untyped_at(scorer.text(), &self.drill_marks, index, &theme);
+                    self.tui.replace_text_at(index - shown_chars_from, untyped)?;
                 }
                 _ => {}
             }
 
+            unsaved |= key.is_some();
+            if unsaved && saved_at.is_none_or(|at| at.elapsed() >= AUTOSAVE_INTERVAL) {
+                self.autosave(scorer.input(), elapsed)?;
+                saved_at = Some(Instant::now());
+                unsaved = false;
+            }
+
+            if highlight_next {
+                move_highlight(
+                    &mut self.tui,
+                    &theme,
+                    scorer.text(),
+                    &self.drill_marks,
+                    shown_chars_from,
+                    scorer.input().len(),
+                    &mut highlighted,
+                )?;
+            }
+            if let Some(wpm) = pacer {
+                move_pacer(
+                    &mut self.tui,
+                    &theme,
+                    scorer.text(),
+                    &self.drill_marks,
+                    shown_chars_from,
+                    scorer.input().len(),
+                    highlighted,
+                    pacer_at(elapsed, wpm),
+                    &mut paced,
+                )?;
+            }
+
+            if status_updated_at.is_none_or(|at| at.elapsed() >= STATUS_UPDATE_INTERVAL) {
+                let elapsed = started_at.elapsed() - paused_duration;
+
+                // the restart prompt stays until it's answered
+                if live_stats && !confirming_restart {
+                    let typed_correctly = scorer
+                        .input()
+                        .iter()
+                        .zip(scorer.text())
+                        .filter(|(typed_char, orig_char)| typed_char == orig_char)
+                        .count();
+                    let minutes = elapsed.as_secs_f64() / 60.0;
+                    let wpm = typed_correctly as f64 / 5.0 / minutes;
+                    let accuracy = if scorer.chars_typed() == 0 {
+                        1.0
+                    } else {
+                        (scorer.chars_typed() - scorer.errors()) as f64
+                            / scorer.chars_typed() as f64
+                    };
+
+                    let mut line = hint.concat();
+                    line.push(Text::from(format!(
+                        "  {:.0} wpm, {:.0}%",
+                        if wpm.is_finite() { wpm } else { 0.0 },
+                        accuracy * 100.0,
+                    )));
+                    self.tui.update_status_line(&line)?;
+                }
+
+                if show_progress {
+                    // timed tests get more words, so their time is the progress
+                    let fraction = match time_limit {
+                        Some(limit) => elapsed.as_secs_f64() / limit.as_secs_f64(),
+                        None => scorer.input().len() as f64 / scorer.text().len() as f64,
+                    };
+                    self.tui.display_progress(fraction, theme.correct)?;
+                }
+
+                status_updated_at = Some(Instant::now());
+            }
+
             self.tui.flush()?;
 
             Ok(TestStatus::NotDone)
         };
 
-        let mut keys = stdin.keys();
+        let mut process_and_log = |key: Option<Key>| -> Result<TestStatus, GameError> {
+            let status = process_key(key, &mut scorer)?;
+            if let (Some(log), Some(key)) = (key_log.as_mut(), key) {
+                log.log(started_at.elapsed(), key, scorer.input().len())?;
+            }
+            Ok(status)
+        };
 
-        // read first key
-        let key = keys.next().unwrap()?;
-        // start the timer
-        let started_at = Instant::now();
         // process first key
-        let mut status = process_key(key)?;
+        let mut status = process_and_log(Some(key));
 
-        if status.to_process_more_keys() {
-            for key in &mut keys {
-                status = process_key(key?)?;
-                if !status.to_process_more_keys() {
-                    break;
-                }
+        while status.as_ref().is_ok_and(|status| status.to_process_more_keys()) {
+            status = keys.next_key_timeout(tick).and_then(&mut process_and_log);
+        }
+
+        // whatever batching put off is drawn before what comes next
+        let flushed = self.tui.set_flush_interval(None);
+        let status = match status.and_then(|status| flushed.map(|()| status)) {
+            Ok(status) => status,
+            Err(error) => {
+                // like a crash, the test can still be resumed. The error
+                // matters more than one saving it
+                let paused = paused_at.map_or(Duration::ZERO, |at| at.elapsed());
+                let _ = self.autosave(
+                    scorer.input(),
+                    started_at
+                        .elapsed()
+                        .saturating_sub(paused_duration + paused),
+                );
+                return Err(error);
             }
+        };
+        if let Some(path) = &self.config.autosave {
+            Session::remove(path)?;
         }
 
         // stop the timer
-        let ended_at = Instant::now();
-
-        let (final_chars_typed_correctly, final_uncorrected_errors) =
-            input.iter().zip(original_text.iter()).fold(
-                (0, 0),
-                |(total_chars_typed_correctly, total_uncorrected_errors),
-                 (typed_char, orig_char)| {
-                    if typed_char == orig_char {
-                        (total_chars_typed_correctly + 1, total_uncorrected_errors)
-                    } else {
-                        (total_chars_typed_correctly, total_uncorrected_errors + 1)
-                    }
-                },
-            );
-
-        let results = GameResults {
-            total_words: self.words.len(),
-            total_chars_typed: num_chars_typed,
-            total_chars_in_text: input.len(),
-            total_char_errors: num_errors,
-            final_chars_typed_correctly,
-            final_uncorrected_errors,
-            started_at,
-            ended_at,
+        let ended_at = match (&status, time_limit) {
+            (TestStatus::TimeUp, Some(limit)) => started_at + paused_duration + limit,
+            _ => Instant::now(),
         };
 
-        let to_restart = if status.to_display_results() {
-            self.display_results(results.clone(), keys)?
+        // skipped spaces the input didn't reach don't count as text either
+        if !line_end_space {
+            let unreached = line_end_spaces(&self.text, shown_chars_from)
+                .iter()
+                .filter(|at| **at >= scorer.input().len())
+                .count();
+            scorer.skip_unreached(unreached);
+        }
+        // kept for the review, finishing uses up the scorer
+        let (input, original_text) = (scorer.input().to_vec(), scorer.text().to_vec());
+        let results = scorer.finish(started_at, ended_at, paused_duration, time_limit);
+
+        let to_restart = if status.to_display_results() && self.config.scoring {
+            if let Some(history) = &self.config.history {
+                results.append_to(history)?;
+            }
+            if let Some(export) = &self.config.export {
+                results.append_csv_to(export)?;
+            }
+            self.display_results(results.clone(), &input, &original_text, keys)?
         } else {
-            status.to_restart()
+            // without scoring a finished test is followed by the next one
+            status.to_restart() || status.to_display_results()
         };
 
         Ok((to_restart, results))
     }
 
+    // counts down from the configured number, swallowing keys; returns false
+    // if the user pressed ctrl-c to quit
+    fn countdown(
+        &mut self,
+        keys: &KeyReader,
+        input: &[char],
+        original_text: &[char],
+    ) -> Result<bool, GameError> {
+        let Some(count) = self.config.countdown else {
+            return Ok(true);
+        };
+
+        self.tui.hide_cursor()?;
+        for n in (1..=count).rev() {
+            self.tui.reset_screen()?;
+            self.tui.display_lines(&[[
+                Text::from(n.to_string()).with_color(self.config.theme.hint),
+            ]])?;
+
+            let tick_ends_at = Instant::now() + Duration::from_secs(1);
+            while let Some(left) = tick_ends_at.checked_duration_since(Instant::now()) {
+                if keys
+                    .next_key_timeout(left)?
+                    .is_some_and(|key| quits(key, self.config.esc_quits))
+                {
+                    self.tui.show_cursor()?;
+                    return Ok(false);
+                }
+            }
+        }
+        self.tui.show_cursor()?;
+
+        let layout = Layout::new(&self.words, &self.lines);
+        let header = self.header();
+        self.text = redraw_test(
+            &mut self.tui,
+            &self.config.theme,
+            &header,
+            &test_hints(&self.config),
+            layout,
+            input,
+            original_text,
+            &self.drill_marks,
+        )?;
+
+        Ok(true)
+    }
+
     fn display_results(
         &mut self,
         results: GameResults,
-        mut keys: Keys<StdinLock>,
+        input: &[char],
+        original_text: &[char],
+        keys: &KeyReader,
     ) -> Result<bool, GameError> {
-        self.tui.reset_screen()?;
-
-        self.tui.display_lines::<&[Text], _>(&[
-            &[Text::from(format!(
+        let summary = match results.time_limit() {
+            Some(limit) => format!(
+                "Typed {} words in a {}s test",
+                results.words(),
+                limit.as_secs(),
+            ),
+            None => format!(
                 "Took {}s for {} words",
                 results.duration().as_secs(),
-                results.total_words,
-            ))],
-            &[
+                results.words(),
+            ),
+        };
+
+        let theme = self.config.theme;
+        let wpm = results.wpm();
+        let mut lines = vec![
+            vec![Text::from(summary)],
+            vec![
                 Text::from(format!("Accuracy: {:.1}%", results.accuracy() * 100.0))
-                    .with_color(color::Blue),
+                    .with_color(theme.hint),
             ],
-            &[Text::from(format!(
-                "Mistakes: {} out of {} characters",
-                results.total_char_errors, results.total_chars_in_text
+            // every mistake, then the ones never fixed
+            vec![Text::from(format!(
+                "Mistakes made: {}, left uncorrected: {} (of {} characters)",
+                results.errors(), results.uncorrected_errors(), results.chars_reached()
+            ))],
+            vec![Text::from(format!(
+                "Longest streak: {} correct characters",
+                results.longest_streak()
             ))],
-            &[
+            // corrections included, for how much it took to get there
+            vec![Text::from(format!("Keystrokes: {}", results.total_keystrokes()))],
+            vec![Text::from(format!("Completed: {:.1}%", results.completion() * 100.0))],
+            vec![
                 Text::from("Speed: "),
-                Text::from(format!("{:.1} wpm", results.wpm())).with_color(color::Green),
+                Text::from(format!("{:.1} wpm", wpm)).with_color(theme.correct),
                 Text::from(" (words per minute)"),
             ],
-        ])?;
-        self.tui.display_lines_bottom(&[&[
-            Text::from("ctrl-r").with_color(color::Blue),
-            Text::from(" to restart, ").with_faint(),
-            Text::from("ctrl-c").with_color(color::Blue),
-            Text::from(" to quit ").with_faint(),
-        ]])?;
-        // no cursor on results page
-        self.tui.hide_cursor()?;
+            vec![Text::from(format!("Raw: {:.1} wpm", results.raw_wpm())).with_faint()],
+        ];
+        if self.config.word_wpm {
+            let by_words = format!(
+                "By words: {:.1} wpm ({} correct)",
+                results.word_wpm(),
+                results.correct_words()
+            );
+            // under the raw wpm
+            lines.push(vec![Text::from(by_words).with_faint()]);
+        }
+        lines.extend([
+            vec![Text::from(format!("Consistency: {:.1}%", results.consistency() * 100.0))],
+            vec![
+                Text::from("Speed over time: "),
+                self.tui.sparkline(&results.wpm_series(), theme.correct)?,
+            ],
+            slowest_words_line(&results, &theme),
+            mistaken_chars_line(&results, &theme),
+        ]);
+        if let Some(keyboard) = self.config.keyboard {
+            lines.push(finger_errors_line(&results, keyboard, &theme));
+        }
+        // left out for tests that can't be shared
+        if let Ok(code) = self.share_code() {
+            lines.push(vec![Text::from(format!("Test code: {}", code)).with_faint()]);
+        }
+
+        match self.history_wpm.iter().copied().reduce(f64::max) {
+            Some(best) if wpm > best => {
+                lines.push(vec![Text::from("New personal best!").with_color(theme.correct)]);
+            }
+            Some(best) => lines.push(vec![
+                Text::from(format!("Best: {:.1} wpm", best)).with_faint()
+            ]),
+            None => {}
+        }
+        if self.config.history.is_some() {
+            let recent = &self.history_wpm[self.history_wpm.len().saturating_sub(RECENT_TESTS)..];
+            let ranking = match results.percentile_against(recent) {
+                Some(percentile) => format!(
+                    "Faster than {:.0}% of your recent tests",
+                    percentile * 100.0
+                ),
+                None => String::from("Not enough tests yet to rank this one"),
+            };
+            lines.push(vec![Text::from(ranking).with_faint()]);
+            if wpm.is_finite() {
+                self.history_wpm.push(wpm);
+            }
+        }
 
         let mut to_restart: Option<bool> = None;
+        // drawn again after the review
+        let mut shown = false;
         while to_restart.is_none() {
-            match keys.next().unwrap()? {
+            if !shown {
+                self.tui.reset_screen()?;
+                self.tui.display_lines(&lines)?;
+                self.tui.display_lines_bottom(&[&[
+                    Text::from("ctrl-r").with_color(theme.hint),
+                    Text::from(" to restart, ").with_faint(),
+                    Text::from("v").with_color(theme.hint),
+                    Text::from(" to review, ").with_faint(),
+                    Text::from(quit_keys(self.config.esc_quits)).with_color(theme.hint),
+                    Text::from(" to quit ").with_faint(),
+                ]])?;
+                // no cursor on results page
+                self.tui.hide_cursor()?;
+                shown = true;
+            }
+
+            match keys.next_key()? {
                 // press ctrl + 'r' to restart
                 Key::Ctrl('r') => to_restart = Some(true),
-                // press ctrl + 'c' to quit
-                Key::Ctrl('c') => to_restart = Some(false),
+                // or ctrl + 'n' for new words
+                Key::Ctrl('n') => {
+                    self.new_text = true;
+                    to_restart = Some(true);
+                }
+                // press ctrl + 'c', or escape, to quit
+                key if quits(key, self.config.esc_quits) => to_restart = Some(false),
+                Key::Char('v') => {
+                    if !self.review(input, original_text, keys)? {
+                        to_restart = Some(false);
+                    }
+                    shown = false;
+                }
                 _ => {}
             }
         }
@@ -299,4 +1269,1958 @@ impl<'a> Game {
 
         Ok(to_restart.unwrap_or(false))
     }
+
+    // shows the text as it was typed, a page at a time, until escape goes
+    // back to the results. Returns false if the user pressed ctrl-c to quit
+    fn review(
+        &mut self,
+        input: &[char],
+        original_text: &[char],
+        keys: &KeyReader,
+    ) -> Result<bool, GameError> {
+        let theme = self.config.theme;
+        // words are separated by a space, lines by a newline
+        let (items, per_page) = match &self.lines {
+            Some(lines) => (lines, REVIEW_LINES),
+            None => (&self.words, REVIEW_WORDS),
+        };
+        let pages = items.len().div_ceil(per_page).max(1);
+
+        let mut page = 0;
+        let mut shown = None;
+        loop {
+            if shown != Some(page) {
+                let first = page * per_page;
+                let page_items = &items[first..(first + per_page).min(items.len())];
+                let layout = match self.lines {
+                    Some(_) => Layout::Verbatim(page_items),
+                    None => Layout::Words(page_items),
+                };
+
+                self.tui.reset_screen()?;
+                self.tui.display_lines_top(&[vec![
+                    Text::from("Review").with_bold(),
+                    Text::from(format!("  page {}/{}", page + 1, pages)).with_faint(),
+                ]])?;
+                self.tui.display_lines_bottom(&[[
+                    Text::from("↑/↓").with_color(theme.hint),
+                    Text::from(" to change pages, ").with_faint(),
+                    Text::from("esc").with_color(theme.hint),
+                    Text::from(" to go back").with_faint(),
+                ]])?;
+                let lines = layout.display(&mut self.tui, theme.faint)?;
+
+                let from = chars_before(items, first).min(original_text.len());
+                let drawn = lines.iter().map(|line| line.text().chars().count()).sum::<usize>();
+                let to = (from + drawn).min(original_text.len());
+                let typed = &input[from.min(input.len())..to.min(input.len())];
+                self.tui.redraw_state(&original_text[from..to], typed, &theme)?;
+                self.tui.hide_cursor()?;
+                shown = Some(page);
+            }
+
+            match keys.next_key()? {
+                Key::Down | Key::PageDown | Key::Right | Key::Char('j') => {
+                    page = (page + 1).min(pages - 1)
+                }
+                Key::Up | Key::PageUp | Key::Left | Key::Char('k') => page = page.saturating_sub(1),
+                Key::Esc => return Ok(true),
+                Key::Ctrl('c') => return Ok(false),
+                _ => {}
+            }
+        }
+    }
+}
+
+// how often the live wpm readout is refreshed while typing
+const STATUS_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+// how many of the last tests in the history a test is ranked against
+const RECENT_TESTS: usize = 50;
+
+// how often the test being typed is saved
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+// how long to wait for a key before updating the timer anyway
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+// how long the screen stays inverted after a mistake
+const FLASH_DURATION: Duration = Duration::from_millis(50);
+
+// words taken from the stream each time a timed test nears the end
+const WORDS_PER_REFILL: usize = 10;
+
+// columns a word on the results screen is cut to, so a long one doesn't
+// push its line off the screen
+const MAX_RESULTS_WORD_COLS: usize = 16;
+
+// words and lines of a verbatim text shown on each page of the review
+const REVIEW_WORDS: usize = 50;
+const REVIEW_LINES: usize = 10;
+
+// recent tests of the history the drill words are taken from
+const DRILL_TESTS: usize = 50;
+
+// distinct followers of a key on average below which the corpus is warned
+// about. The default corpus has about 1.2 at order 2, most of its keys lead
+// to one word only
+const MIN_BRANCHING: f64 = 1.1;
+
+// share of the words in a drill test swapped for drill words
+const DRILL_RATE: f64 = 0.5;
+
+// whether `key` quits, ctrl-c always does
+fn quits(key: Key, esc_quits: bool) -> bool {
+    key == Key::Ctrl('c') || (esc_quits && key == Key::Esc)
+}
+
+// the keys that quit, as the hints name them
+fn quit_keys(esc_quits: bool) -> &'static str {
+    if esc_quits {
+        "ctrl-c/esc"
+    } else {
+        "ctrl-c"
+    }
+}
+
+fn test_hint(theme: &Theme, esc_quits: bool) -> Vec<Text> {
+    vec![
+        Text::from("ctrl-r").with_color(theme.hint),
+        Text::from(" to restart, ").with_faint(),
+        Text::from("ctrl-p").with_color(theme.hint),
+        Text::from(" to pause, ").with_faint(),
+        Text::from(quit_keys(esc_quits)).with_color(theme.hint),
+        Text::from(" to abort").with_faint(),
+    ]
+}
+
+// `word` spelled backwards
+fn reversed(word: &str) -> String {
+    word.chars().rev().collect()
+}
+
+// the lines under a test. With `GameConfig::hints` off the row is kept
+// empty for the live stats or the restart prompt, which are drawn there, and
+// left to the text only when neither is
+fn test_hints(config: &GameConfig) -> Vec<Vec<Text>> {
+    if config.hints {
+        vec![test_hint(&config.theme, config.esc_quits)]
+    } else if config.live_stats || config.confirm_restart {
+        vec![Vec::new()]
+    } else {
+        Vec::new()
+    }
+}
+
+// replaces the hint line while ctrl-r waits to be confirmed
+fn restart_prompt(theme: &Theme) -> Vec<Text> {
+    vec![
+        Text::from("Restart? ").with_color(theme.hint),
+        Text::from("y/n").with_faint(),
+    ]
+}
+
+// how the text of a test is put on screen
+#[derive(Clone, Copy)]
+enum Layout<'a> {
+    // wrapped to fit
+    Words(&'a [String]),
+    // lines as they were written
+    Verbatim(&'a [String]),
+}
+
+impl<'a> Layout<'a> {
+    fn new(words: &'a [String], lines: &'a Option<Vec<String>>) -> Self {
+        match lines {
+            Some(lines) => Layout::Verbatim(lines),
+            None => Layout::Words(words),
+        }
+    }
+
+    fn display<W: Write>(
+        self,
+        tui: &mut GameTui<W>,
+        color: ThemeColor,
+    ) -> Result<Vec<Text>, GameError> {
+        match self {
+            Layout::Words(words) => tui.display_words(words, color),
+            Layout::Verbatim(lines) => tui.display_verbatim(lines, color),
+        }
+    }
+}
+
+// lays the words out again and draws what was typed so far over them,
+// returning the new lines
+#[allow(clippy::too_many_arguments)]
+fn redraw_test<W: Write>(
+    tui: &mut GameTui<W>,
+    theme: &Theme,
+    header: &[Vec<Text>],
+    hint: &[Vec<Text>],
+    layout: Layout,
+    input: &[char],
+    original_text: &[char],
+    drill_marks: &[bool],
+) -> Result<Vec<Text>, GameError> {
+    tui.reset_screen()?;
+    tui.display_lines_top(header)?;
+    tui.display_lines_bottom(hint)?;
+    // the flattened text stays the same, only the wrapping moves
+    let lines = layout.display(tui, theme.faint)?;
+    tui.redraw_state(original_text, input, theme)?;
+    draw_drill_words(tui, theme, original_text, drill_marks, input.len())?;
+    tui.flush()?;
+
+    Ok(lines)
+}
+
+// the three words that took longest, in the error color
+fn slowest_words_line(results: &GameResults, theme: &Theme) -> Vec<Text> {
+    let mut line = vec![Text::from("Slowest:")];
+    for (word, time) in results.slowest_words(3) {
+        line.push(Text::from(" "));
+        line.push(
+            Text::from(word.as_str())
+                .truncate(MAX_RESULTS_WORD_COLS)
+                .with_color(theme.error),
+        );
+        line.push(Text::from(format!(" ({:.1}s)", time.as_secs_f64())).with_faint());
+    }
+    line
+}
+
+// the characters mistyped most, in the error color
+fn mistaken_chars_line(results: &GameResults, theme: &Theme) -> Vec<Text> {
+    let top = results.top_mistaken_chars(3);
+    if top.is_empty() {
+        return vec![Text::from("No characters missed").with_color(theme.correct)];
+    }
+
+    let mut line = vec![Text::from("Most missed:")];
+    for (c, misses) in top {
+        let shown = if c == ' ' { String::from("space") } else { c.to_string() };
+        line.push(Text::from(" "));
+        line.push(Text::from(shown).with_color(theme.error));
+        line.push(Text::from(format!(" ({}x)", misses)).with_faint());
+    }
+    line
+}
+
+// mistakes by finger, or that there weren't any
+fn finger_errors_line(results: &GameResults, keyboard: KeyboardLayout, theme: &Theme) -> Vec<Text> {
+    let fingers = results.errors_by_finger(keyboard);
+    if fingers.is_empty() {
+        return vec![Text::from("No mistakes on any finger").with_faint()];
+    }
+
+    let mut line = vec![Text::from("By finger:")];
+    for (finger, misses) in fingers {
+        line.push(Text::from(" "));
+        line.push(Text::from(finger.to_string()).with_color(theme.error));
+        line.push(Text::from(format!(" ({}x)", misses)).with_faint());
+    }
+    line
+}
+
+// an untyped character of a drill word, faint like the rest but in another
+// color
+fn drill_text(orig_char: char, theme: &Theme) -> Text {
+    Text::from(shown_char(orig_char)).with_faint().with_color(theme.hint)
+}
+
+// the untyped character at `at` of the text, drill words told apart
+fn untyped_at(original_text: &[char], drill_marks: &[bool], at: usize, theme: &Theme) -> Text {
+    if drill_marks.get(at) == Some(&true) {
+        drill_text(original_text[at], theme)
+    } else {
+        untyped_text(original_text[at], theme)
+    }
+}
+
+// draws the untyped drill words from `typed` on over the text laid out in
+// the usual color, leaving the cursor on `typed`
+fn draw_drill_words<W: Write>(
+    tui: &mut GameTui<W>,
+    theme: &Theme,
+    original_text: &[char],
+    drill_marks: &[bool],
+    typed: usize,
+) -> Result<(), GameError> {
+    if !drill_marks.iter().skip(typed).any(|drilled| *drilled) {
+        return Ok(());
+    }
+
+    for (at, drilled) in drill_marks.iter().enumerate().skip(typed) {
+        if *drilled {
+            tui.replace_text_at(at, drill_text(original_text[at], theme))?;
+        }
+    }
+    tui.move_to_char(typed)?;
+
+    Ok(())
+}
+
+// draws the character at `next` inverted, and the one that was highlighted
+// before, if it's still untyped, as usual. Leaves the cursor on `next`
+fn move_highlight<W: Write>(
+    tui: &mut GameTui<W>,
+    theme: &Theme,
+    original_text: &[char],
+    drill_marks: &[bool],
+    shown_chars_from: usize,
+    next: usize,
+    highlighted: &mut Option<usize>,
+) -> Result<(), GameError> {
+    if *highlighted == Some(next) {
+        return Ok(());
+    }
+
+    if let Some(at) = highlighted.filter(|at| *at > next && *at < original_text.len()) {
+        tui.replace_text_at(
+            at - shown_chars_from,
+            untyped_at(original_text, drill_marks, at, theme),
+        )?;
+    }
+    if next < original_text.len() {
+        let highlight = untyped_at(original_text, drill_marks, next, theme).with_invert();
+        tui.replace_text_at(next - shown_chars_from, highlight)?;
+    }
+    *highlighted = Some(next);
+
+    Ok(())
+}
+
+// chars of the flattened text before the word or line at `at` of `items`,
+// each followed by the space or newline between them
+fn chars_before(items: &[String], at: usize) -> usize {
+    items[..at].iter().map(|item| item.chars().count() + 1).sum()
+}
+
+// the character the pacer is on after `elapsed` at `wpm`, five characters
+// to a word like the wpm of the results
+fn pacer_at(elapsed: Duration, wpm: u32) -> usize {
+    (elapsed.as_secs_f64() * wpm as f64 * 5.0 / 60.0) as usize
+}
+
+// an untyped character the pacer is on
+fn pacer_text(orig_char: char, theme: &Theme) -> Text {
+    Text::from(shown_char(orig_char)).with_underline().with_color(theme.hint)
+}
+
+// draws the pacer at `next` and the character it was on before as usual,
+// as long as they're untyped and on screen. Past the end of the text
+// nothing is drawn, and neither over the `highlighted` next character.
+// Leaves the cursor on the next character to type
+#[allow(clippy::too_many_arguments)]
+fn move_pacer<W: Write>(
+    tui: &mut GameTui<W>,
+    theme: &Theme,
+    original_text: &[char],
+    drill_marks: &[bool],
+    shown_chars_from: usize,
+    typed: usize,
+    highlighted: Option<usize>,
+    next: usize,
+    paced: &mut Option<usize>,
+) -> Result<(), GameError> {
+    if *paced == Some(next) {
+        return Ok(());
+    }
+
+    let drawable = |at: usize| {
+        at >= typed.max(shown_chars_from) && at < original_text.len() && Some(at) != highlighted
+    };
+    if let Some(at) = paced.filter(|at| drawable(*at)) {
+        tui.replace_text_at(
+            at - shown_chars_from,
+            untyped_at(original_text, drill_marks, at, theme),
+        )?;
+    }
+    if drawable(next) {
+        tui.replace_text_at(next - shown_chars_from, pacer_text(original_text[next], theme))?;
+    }
+    *paced = Some(next);
+    tui.move_to_char(typed - shown_chars_from)?;
+
+    Ok(())
+}
+
+// indices of the spaces ending the wrapped `lines`, which start at
+// `shown_chars_from` in the text
+fn line_end_spaces(lines: &[Text], shown_chars_from: usize) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut end = shown_chars_from;
+    for line in lines {
+        end += line.text().chars().count();
+        if line.text().ends_with(' ') {
+            ends.push(end - 1);
+        }
+    }
+    ends
+}
+
+fn check_corpus(tokens: &[String], order: usize) -> Result<(), GameError> {
+    // a key of `order` words needs at least one word after it
+    let min_words = order.max(1) + 1;
+    if tokens.len() < min_words {
+        return Err(GameError::from(format!(
+            "Corpus is empty or too short, it needs at least {} words but has {}",
+            min_words,
+            tokens.len(),
+        )));
+    }
+
+    Ok(())
+}
+
+// the markov text could never get anywhere without one word of the corpus
+// between the lengths
+fn check_word_lens(
+    tokens: &[String],
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+) -> Result<(), GameError> {
+    let lens = match (min_len, max_len) {
+        (None, None) => return Ok(()),
+        (Some(min), None) => format!("at least {}", min),
+        (None, Some(max)) => format!("at most {}", max),
+        (Some(min), Some(max)) => format!("{} to {}", min, max),
+    };
+    if !tokens.iter().any(|token| word_len_fits(token, min_len, max_len)) {
+        return Err(GameError::from(format!("Corpus has no words of {} letters", lens)));
+    }
+
+    Ok(())
+}
+
+// whether `word` has between `min_len` and `max_len` letters, punctuation
+// aside
+fn word_len_fits(word: &str, min_len: Option<usize>, max_len: Option<usize>) -> bool {
+    let len = word.chars().filter(|c| c.is_alphanumeric()).count();
+    min_len.is_none_or(|min| len >= min) && max_len.is_none_or(|max| len <= max)
+}
+
+/// The corpus path that reads it from stdin instead, which only works while
+/// stdin isn't needed for keys, i.e. for `--print-only`.
+pub const STDIN_CORPUS: &str = "-";
+
+// FNV-1a of `text`, which doesn't change with the platform or Rust version
+// like `DefaultHasher` can
+fn fnv1a(text: &str) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in text.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+// the chain of `tokens`, read from `config.chain_cache` if it was saved there
+// for the same corpus and order, built and saved there otherwise
+fn load_chain(tokens: Vec<String>, config: &GameConfig) -> Result<WordChain, GameError> {
+    let Some(path) = &config.chain_cache else {
+        return Ok(WordChain::new(tokens, config.order));
+    };
+
+    let hash = corpus_hash(&tokens, config.order);
+    // a damaged cache is only slower, it's built again
+    if let Ok(Some(chain)) = load_cache(path, hash) {
+        return Ok(chain);
+    }
+    let chain = WordChain::new(tokens, config.order);
+    // the chain is there either way, it's only built again next time
+    let _ = save_cache(&chain, hash, path);
+    Ok(chain)
+}
+
+// words of the corpus, without anything that could mess up the terminal
+fn load_tokens(path: Option<&Path>) -> Result<Vec<String>, GameError> {
+    match path {
+        None => Ok(DEFAULT_CORPUS.split_whitespace().map(String::from).collect()),
+        Some(path) if path == Path::new(STDIN_CORPUS) => read_tokens(io::stdin().lock()),
+        Some(path) => read_tokens(BufReader::new(File::open(path)?)),
+    }
+}
+
+fn read_tokens<R: BufRead>(reader: R) -> Result<Vec<String>, GameError> {
+    let mut tokens = Vec::new();
+    for line in reader.lines() {
+        tokens.extend(printable(&line?).split_whitespace().map(String::from));
+    }
+
+    Ok(tokens)
+}
+
+// the lines of the text for `Mode::Verbatim`, without trailing whitespace
+// or blank lines before and after the text. Tabs are kept to be typed
+fn load_verbatim(path: Option<&Path>) -> Result<Vec<String>, GameError> {
+    let Some(path) = path else {
+        return Err(GameError::from(String::from("Verbatim mode needs a text file")));
+    };
+
+    let lines: Vec<String> = BufReader::new(File::open(path)?)
+        .lines()
+        .map(|line| {
+            line.map(|line| {
+                let parts: Vec<String> = line.split('\t').map(printable).collect();
+                parts.join("\t").trim_end().to_string()
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    let first = lines.iter().position(|line| !line.is_empty());
+    let last = lines.iter().rposition(|line| !line.is_empty());
+    match (first, last) {
+        (Some(first), Some(last)) => Ok(lines[first..=last].to_vec()),
+        _ => Err(GameError::from(String::from("Text file is empty"))),
+    }
+}
+
+fn load_quotes(path: Option<&Path>) -> Result<Vec<String>, GameError> {
+    let quotes: Vec<String> = match path {
+        Some(path) => BufReader::new(File::open(path)?)
+            .lines()
+            .collect::<Result<_, _>>()?,
+        None => DEFAULT_QUOTES.lines().map(String::from).collect(),
+    };
+    let quotes: Vec<String> = quotes
+        .iter()
+        .map(|quote| printable(quote))
+        .filter(|quote| !quote.trim().is_empty())
+        .collect();
+
+    if quotes.is_empty() {
+        return Err(GameError::from(String::from("Quotes file has no quotes")));
+    }
+
+    Ok(quotes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::{mistyped_text, typed_text};
+
+    fn headless_game(config: GameConfig) -> Game<Vec<u8>> {
+        let config = GameConfig {
+            history: None,
+            autosave: None,
+            seed: Some(1),
+            num_words: 5,
+            ..config
+        };
+        Game::headless(config, Vec::new(), (100, 30)).unwrap()
+    }
+
+    fn target_text<W: Write>(game: &Game<W>) -> Vec<char> {
+        game.text.iter().flat_map(|text| text.text().chars()).collect()
+    }
+
+    fn output(game: &Game<Vec<u8>>) -> String {
+        String::from_utf8_lossy(game.tui().writer()).into_owned()
+    }
+
+    // a char typed in place of `c` that's always a mistake
+    fn mistype(c: char) -> char {
+        if c == 'x' {
+            'y'
+        } else {
+            'x'
+        }
+    }
+
+    // a file in the temp dir, removed again when dropped so a failing assert
+    // doesn't leave it behind
+    struct TempFile(std::path::PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("typeracer-{}-{}", name, std::process::id()));
+            std::fs::write(&path, contents).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    // a game of `mode` reading `contents` as its corpus, quotes or text. The
+    // file is gone again once the game is made
+    fn game_with_file(
+        name: &str,
+        contents: &str,
+        mode: Mode,
+    ) -> Result<Game<Vec<u8>>, GameError> {
+        let file = TempFile::new(name, contents);
+        let path = Some(file.0.clone());
+        let config = match mode {
+            Mode::Quotes => GameConfig { quotes: path, ..GameConfig::default() },
+            Mode::Verbatim => GameConfig { text: path, ..GameConfig::default() },
+            Mode::Markov | Mode::Drill => GameConfig { corpus: path, ..GameConfig::default() },
+        };
+        let config = GameConfig {
+            mode,
+            history: None,
+            autosave: None,
+            seed: Some(1),
+            num_words: 5,
+            ..config
+        };
+        Game::headless(config, Vec::new(), (100, 30))
+    }
+
+    #[test]
+    fn text_source_words_match_the_game() {
+        for punctuation in [false, true] {
+            let config = GameConfig { punctuation, ..GameConfig::default() };
+            let game = headless_game(config);
+
+            let source = TextSource::new(&game.config).unwrap();
+            assert_eq!(source.words(&game.config, 1).unwrap(), game.words);
+            assert_ne!(source.words(&game.config, 2).unwrap(), game.words);
+        }
+    }
+
+    #[test]
+    fn prettified_words_start_sentences_capitalized() {
+        let config = GameConfig {
+            punctuation: true,
+            time_limit: Some(Duration::from_secs(30)),
+            ..GameConfig::default()
+        };
+        let source = TextSource::new(&config).unwrap();
+        let pretty_config = GameConfig { prettify: true, ..config.clone() };
+
+        for seed in 0..10 {
+            let (words, stream) = source.generate(&config, seed).unwrap();
+            let words: Vec<String> = words.into_iter().chain(stream.take(50)).collect();
+            let (pretty, stream) = source.generate(&pretty_config, seed).unwrap();
+            let pretty: Vec<String> = pretty.into_iter().chain(stream.take(50)).collect();
+
+            // the same words, only some capitalized
+            assert_eq!(pretty.len(), words.len());
+            assert_eq!(pretty.join(" ").to_lowercase(), words.join(" ").to_lowercase());
+            let starts = std::iter::once(&pretty[0]).chain(
+                pretty
+                    .windows(2)
+                    .filter(|pair| markov::ends_sentence(&pair[0]))
+                    .map(|pair| &pair[1]),
+            );
+            for word in starts {
+                let first = word.chars().find(|c| c.is_alphabetic());
+                assert!(first.is_none_or(char::is_uppercase), "'{}' starts a sentence", word);
+            }
+        }
+    }
+
+    #[test]
+    fn words_stay_within_the_lengths() {
+        let config = GameConfig {
+            min_word_len: Some(3),
+            max_word_len: Some(5),
+            punctuation: true,
+            seed: Some(1),
+            time_limit: Some(Duration::from_secs(30)),
+            ..GameConfig::default()
+        };
+        let source = TextSource::new(&config).unwrap();
+        for seed in 0..20 {
+            let (words, stream) = source.generate(&config, seed).unwrap();
+            assert_eq!(words.len(), config.num_words);
+            for word in words.iter().chain(&stream.take(100).collect::<Vec<_>>()) {
+                let letters = word.chars().filter(|c| c.is_alphanumeric()).count();
+                assert!((3..=5).contains(&letters), "'{}' has {} letters", word, letters);
+            }
+        }
+
+        let error = TextSource::new(&GameConfig {
+            min_word_len: Some(100),
+            ..GameConfig::default()
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.msg, "Corpus has no words of at least 100 letters");
+    }
+
+    #[test]
+    fn drill_words_are_picked_by_their_misses() {
+        let config = GameConfig { mode: Mode::Drill, history: None, ..GameConfig::default() };
+        let markov = TextSource::new(&GameConfig { mode: Mode::Markov, ..config.clone() }).unwrap();
+        // nothing to drill without a history
+        let plain = TextSource::new(&config).unwrap();
+        assert_eq!(
+            plain.words(&config, 1).unwrap(),
+            markov.words(&config, 1).unwrap()
+        );
+        assert!(plain
+            .drill_marks(&markov.words(&config, 1).unwrap(), false)
+            .iter()
+            .all(|drilled| !drilled));
+
+        let chain = Rc::new(WordChain::new(load_tokens(None).unwrap(), config.order));
+        let drills = vec![(String::from("yak"), 1), (String::from("zebra"), 3)];
+        let source = TextSource::Drill(chain, drills);
+        let words: Vec<String> = (0..20)
+            .flat_map(|seed| source.words(&config, seed).unwrap())
+            .collect();
+        let count = |drill: &str| words.iter().filter(|word| *word == drill).count();
+        assert!(
+            count("zebra") > count("yak") && count("yak") > 0,
+            "{} zebras, {} yaks",
+            count("zebra"),
+            count("yak")
+        );
+        assert!(count("zebra") + count("yak") < words.len());
+
+        let words: Vec<String> = ["a", "zebra", "", "yak"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        let marks = source.drill_marks(&words, false);
+        assert_eq!(marks, [false, false, true, true, true, true, true, false, true, true, true]);
+        assert!(markov.drill_marks(&words, false).is_empty());
+        let backwards: Vec<String> = ["a", "arbez", "kay"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        assert_eq!(source.drill_marks(&backwards, true), marks);
+    }
+
+    #[test]
+    fn drill_words_are_drawn_apart() {
+        let dir = std::env::temp_dir().join(format!("typeracer-drill-{}", std::process::id()));
+        let history = dir.join("history.jsonl");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&history, "{\"wpm\":10.0,\"mistaken_words\":{\"zebra\":4}}\n").unwrap();
+        let config = GameConfig {
+            mode: Mode::Drill,
+            history: Some(history),
+            autosave: None,
+            seed: Some(1),
+            num_words: 5,
+            ..GameConfig::default()
+        };
+        let mut game = Game::headless(config, Vec::new(), (100, 30)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let text = target_text(&game);
+        let at = game.drill_marks.iter().position(|drilled| *drilled).expect("no drill word");
+        assert_eq!(text[at..at + 5].iter().collect::<String>(), "zebra");
+        let theme = game.config.theme;
+        assert!(output(&game).contains(&drill_text('z', &theme).to_string()));
+
+        // a drill word goes back to its color when its letters are deleted
+        let mut keys = text[..at + 1].to_vec();
+        keys.push('x');
+        let mut keys: Vec<Key> = keys.into_iter().map(Key::Char).collect();
+        keys.extend([Key::Backspace, Key::Ctrl('c')]);
+        let drawn_before = output(&game).len();
+        game.run(&KeyReader::from_keys(keys)).unwrap();
+        assert!(output(&game)[drawn_before..].contains(&drill_text('e', &theme).to_string()));
+    }
+
+    #[test]
+    fn long_slow_words_are_cut_on_the_results() {
+        use crate::text::HasLength;
+
+        let text = "to antidisestablishmentarianism";
+        let keys: Vec<_> = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| (scoring::TypedKey::Char(c), Duration::from_secs(i as u64)))
+            .collect();
+        let results = scoring::score(text, &keys, false);
+
+        let line = slowest_words_line(&results, &Theme::default());
+        let words: Vec<&str> = line.iter().map(|text| text.text().as_str()).collect();
+        assert_eq!(words[..3], ["Slowest:", " ", "antidisestablis…"]);
+        assert_eq!(line[2].length(), MAX_RESULTS_WORD_COLS);
+    }
+
+    #[test]
+    fn keys_are_logged_with_the_cursor() {
+        let dir =
+            std::env::temp_dir().join(format!("typeracer-game-keylog-{}", std::process::id()));
+        let path = dir.join("keys.tsv");
+        let mut game = headless_game(GameConfig {
+            key_log: Some(path.clone()),
+            ..GameConfig::default()
+        });
+        let text = target_text(&game);
+
+        let keys = vec![Key::Char(text[0]), Key::Char('\t'), Key::Backspace, Key::Ctrl('c')];
+        game.run(&KeyReader::from_keys(keys)).unwrap();
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let rows: Vec<Vec<&str>> = log.lines().map(|line| line.split('\t').collect()).collect();
+        assert_eq!(rows[0], ["ms", "key", "index"]);
+        let keys: Vec<(&str, &str)> = rows[1..].iter().map(|row| (row[1], row[2])).collect();
+        let first = text[0].to_string();
+        assert_eq!(
+            keys,
+            [
+                (first.as_str(), "1"),
+                ("\\t", "2"),
+                ("backspace", "1"),
+                ("ctrl-c", "1")
+            ]
+        );
+        assert!(rows[1..].iter().all(|row| row[0].parse::<u64>().is_ok()));
+    }
+
+    #[test]
+    fn headless_game_types_the_whole_text() {
+        let mut game = headless_game(GameConfig::default());
+        let target = target_text(&game);
+
+        let mut keys: Vec<Key> = target.iter().map(|c| Key::Char(*c)).collect();
+        // quit from the results screen
+        keys.push(Key::Ctrl('c'));
+        let (to_restart, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert!(!to_restart);
+        // at least the 5 words asked for, finishing the last sentence
+        assert!(results.total_words >= 5);
+        assert_eq!(results.total_words, game.words.len());
+        assert!(markov::ends_sentence(game.words.last().unwrap()) || results.total_words == 5);
+        assert_eq!(results.total_chars_in_text, target.len());
+        let timed_words: Vec<String> = results
+            .word_times
+            .iter()
+            .map(|(word, _)| word.clone())
+            .collect();
+        assert_eq!(timed_words, game.words);
+        assert_eq!(results.final_uncorrected_errors, 0);
+        assert_eq!(results.accuracy(), 1.0);
+        assert!(output(&game).contains("Accuracy: 100.0%"));
+    }
+
+    #[test]
+    fn results_open_a_review_of_the_typed_text() {
+        let theme = Theme::default();
+        let config = GameConfig {
+            history: None,
+            autosave: None,
+            seed: Some(1),
+            num_words: 60,
+            ..GameConfig::default()
+        };
+        let mut game = Game::headless(config, Vec::new(), (120, 40)).unwrap();
+        let target = target_text(&game);
+        let wrong = mistype(target[0]);
+
+        let mut keys: Vec<Key> = target.iter().map(|c| Key::Char(*c)).collect();
+        keys[0] = Key::Char(wrong);
+        keys.extend([Key::Char('v'), Key::Down, Key::Down, Key::Up, Key::Esc, Key::Ctrl('c')]);
+        game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        let drawn = output(&game);
+        let review = &drawn[drawn.find("Review").unwrap()..];
+        let pages = game.words.len().div_ceil(REVIEW_WORDS);
+        assert_eq!(review.matches(&format!("page 1/{}", pages)).count(), 2);
+        // a second down stays on the last page
+        assert_eq!(review.matches(&format!("page 2/{}", pages)).count(), 1);
+        assert!(review.contains(&typed_text(wrong, target[0], &theme).to_string()));
+        let last = *target.last().unwrap();
+        assert!(review.contains(&typed_text(last, last, &theme).to_string()));
+        // escape goes back to the results
+        assert_eq!(drawn.matches("Test code:").count(), 2);
+    }
+
+    #[test]
+    fn review_starts_pages_at_their_words() {
+        let words: Vec<String> = ["one", "two", "three"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        assert_eq!(chars_before(&words, 0), 0);
+        assert_eq!(chars_before(&words, 2), 8);
+        assert_eq!(chars_before(&words, 3), 14);
+    }
+
+    #[test]
+    fn headless_game_draws_mistakes() {
+        let mut game = headless_game(GameConfig::default());
+        let target = target_text(&game);
+        let theme = game.config.theme;
+        let wrong = mistype(target[0]);
+
+        let keys = KeyReader::from_keys([Key::Char(wrong), Key::Ctrl('c')]);
+        let (to_restart, results) = game.run(&keys).unwrap();
+
+        assert!(!to_restart);
+        assert_eq!(results.total_char_errors, 1);
+        assert_eq!(results.top_mistaken_chars(3), vec![(target[0], 1)]);
+        assert!(output(&game).contains(&typed_text(wrong, target[0], &theme).to_string()));
+    }
+
+    #[test]
+    fn results_tell_corrected_and_uncorrected_mistakes_apart() {
+        let mut game = headless_game(GameConfig::default());
+        let target = target_text(&game);
+
+        // the first mistake is fixed, the one on the last character isn't
+        let mut keys = vec![Key::Char(mistype(target[0])), Key::Backspace];
+        keys.extend(target[..target.len() - 1].iter().map(|c| Key::Char(*c)));
+        keys.push(Key::Char(mistype(target[target.len() - 1])));
+        keys.push(Key::Ctrl('c'));
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert_eq!((results.errors(), results.uncorrected_errors()), (2, 1));
+        let expected = format!(
+            "Mistakes made: 2, left uncorrected: 1 (of {} characters)",
+            target.len()
+        );
+        assert!(output(&game).contains(&expected));
+    }
+
+    #[test]
+    fn final_space_has_to_be_typed_to_finish() {
+        for line_end_space in [true, false] {
+            let config = GameConfig { final_space: true, line_end_space, ..GameConfig::default() };
+            let mut game = headless_game(config.clone());
+            let target = target_text(&game);
+            assert_eq!(target.last(), Some(&' '));
+            assert_eq!(target.len(), game.words.join(" ").chars().count() + 1);
+
+            // every letter typed, the space after the last word left. The
+            // spaces ending lines are typed for the user without
+            // `line_end_space`
+            let skipped = if line_end_space { Vec::new() } else { line_end_spaces(&game.text, 0) };
+            let letters: Vec<Key> = target[..target.len() - 1]
+                .iter()
+                .enumerate()
+                .filter(|(at, _)| !skipped.contains(at))
+                .map(|(_, c)| Key::Char(*c))
+                .collect();
+            let mut keys = letters.clone();
+            keys.push(Key::Ctrl('c'));
+            let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+            // the last letter finishes the test only when the space is
+            // skipped for the user
+            assert_eq!(
+                output(&game).contains("Accuracy"),
+                !line_end_space,
+                "line end space {}",
+                line_end_space
+            );
+            if line_end_space {
+                assert_eq!(results.chars_reached(), target.len() - 1);
+            }
+
+            if line_end_space {
+                let mut game = headless_game(config);
+                let mut keys = letters;
+                keys.extend([Key::Char(' '), Key::Ctrl('c')]);
+                let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+                assert!(output(&game).contains("Accuracy: 100.0%"));
+                assert_eq!(results.text_len(), target.len());
+            }
+        }
+    }
+
+    #[test]
+    fn word_wpm_is_shown_when_asked_for() {
+        for word_wpm in [false, true] {
+            let mut game = headless_game(GameConfig { word_wpm, ..GameConfig::default() });
+            let mut keys: Vec<Key> = target_text(&game).into_iter().map(Key::Char).collect();
+            keys.push(Key::Ctrl('c'));
+            let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+            assert_eq!(results.correct_words(), game.words.len());
+            let shown = format!("wpm ({} correct)", game.words.len());
+            let screen = output(&game);
+            assert_eq!(screen.contains(&shown), word_wpm);
+            if word_wpm {
+                // right under the raw wpm
+                let (raw, by_words) = (
+                    screen.find("Raw: ").unwrap(),
+                    screen.find("By words: ").unwrap(),
+                );
+                assert!(raw < by_words);
+                assert!(!screen[raw..by_words].contains("Consistency: "));
+            }
+        }
+    }
+
+    // keys for the first word, then `instead` where the space should be
+    fn first_word_then(game: &Game<Vec<u8>>, instead: char) -> Vec<Key> {
+        let mut keys: Vec<Key> = game.words[0].chars().map(Key::Char).collect();
+        keys.push(Key::Char(instead));
+        keys.push(Key::Ctrl('c'));
+        keys
+    }
+
+    #[test]
+    fn headless_game_waits_for_loose_spaces() {
+        let mut game = headless_game(GameConfig::default());
+        let first_word_len = game.words[0].chars().count();
+
+        let keys = KeyReader::from_keys(first_word_then(&game, 'x'));
+        let (_, results) = game.run(&keys).unwrap();
+
+        // the key is ignored rather than typed over the space
+        assert_eq!(results.total_chars_in_text, first_word_len);
+        assert_eq!(results.keystrokes.len(), first_word_len);
+        assert_eq!(results.total_char_errors, 0);
+        assert_eq!(results.accuracy(), 1.0);
+    }
+
+    #[test]
+    fn headless_game_scores_strict_spaces() {
+        let mut game = headless_game(GameConfig {
+            strict_space: true,
+            ..GameConfig::default()
+        });
+        let first_word_len = game.words[0].chars().count();
+
+        let keys = KeyReader::from_keys(first_word_then(&game, 'x'));
+        let (_, results) = game.run(&keys).unwrap();
+
+        // a missing space counts like any other mistake
+        assert_eq!(results.total_chars_in_text, first_word_len + 1);
+        assert_eq!(results.total_char_errors, 1);
+        assert_eq!(results.final_uncorrected_errors, 1);
+        assert_eq!(results.top_mistaken_chars(1), vec![(' ', 1)]);
+    }
+
+    #[test]
+    fn headless_game_scores_spaces_inside_words() {
+        for strict_space in [false, true] {
+            let mut game = headless_game(GameConfig {
+                strict_space,
+                ..GameConfig::default()
+            });
+            let first_char = game.words[0].chars().next().unwrap();
+
+            let keys = KeyReader::from_keys([Key::Char(' '), Key::Ctrl('c')]);
+            let (_, results) = game.run(&keys).unwrap();
+
+            assert_eq!(results.total_char_errors, 1, "strict: {}", strict_space);
+            assert_eq!(results.top_mistaken_chars(1), vec![(first_char, 1)]);
+        }
+    }
+
+    #[test]
+    fn headless_game_deletes_words_with_any_binding() {
+        for delete_word in [Key::Ctrl('w'), Key::Ctrl('h'), Key::Alt('\x7f')] {
+            let mut game = headless_game(GameConfig::default());
+            let first_word = game.words[0].clone();
+            let second_word = game.words[1].clone();
+
+            // a typo in the second word, deleted along with the word
+            let mut keys: Vec<Key> = format!("{} x", first_word).chars().map(Key::Char).collect();
+            keys.push(delete_word);
+            keys.extend(second_word.chars().map(Key::Char));
+            keys.push(Key::Ctrl('c'));
+            let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+            let typed = first_word.chars().count() + 1 + second_word.chars().count();
+            assert_eq!(results.total_chars_in_text, typed, "{:?}", delete_word);
+            assert_eq!(results.final_uncorrected_errors, 0, "{:?}", delete_word);
+        }
+    }
+
+    #[test]
+    fn headless_game_deletes_words_back_across_a_wrap() {
+        let config = GameConfig {
+            history: None,
+            autosave: None,
+            seed: Some(1),
+            num_words: 30,
+            ..GameConfig::default()
+        };
+        let mut game = Game::headless(config, Vec::new(), (100, 30)).unwrap();
+        let theme = game.config.theme;
+        let first_line = game.text[0].text().to_string();
+        assert!(game.text.len() > 1);
+        let last_word: Vec<char> = first_line.split_whitespace().last().unwrap().chars().collect();
+
+        // the whole first line, its last word and the space after it gone
+        // again
+        let mut keys: Vec<Key> = first_line.chars().map(Key::Char).collect();
+        keys.extend([Key::Ctrl('w'), Key::Ctrl('c')]);
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert_eq!(results.total_chars_in_text, first_line.chars().count() - last_word.len() - 1);
+        assert_eq!(game.tui().current_line(), 0);
+        let drawn = output(&game);
+        for c in last_word.iter().chain([&' ']) {
+            assert!(drawn.contains(&untyped_text(*c, &theme).to_string()), "'{}' not redrawn", c);
+        }
+    }
+
+    #[test]
+    fn mistakes_are_drawn_as_configured() {
+        for show_typed_mistakes in [false, true] {
+            let mut game = headless_game(GameConfig {
+                show_typed_mistakes,
+                ..GameConfig::default()
+            });
+            let target = target_text(&game);
+            let wrong = mistype(target[0]);
+
+            let keys = vec![Key::Char(wrong), Key::Ctrl('c')];
+            game.run(&KeyReader::from_keys(keys)).unwrap();
+            let theme = game.config.theme;
+            let as_typed = mistyped_text(wrong, target[0], &theme).to_string();
+            let as_text = typed_text(wrong, target[0], &theme).to_string();
+            assert_eq!(output(&game).contains(&as_typed), show_typed_mistakes);
+            assert_eq!(output(&game).contains(&as_text), !show_typed_mistakes);
+        }
+    }
+
+    #[test]
+    fn cursor_index_advances_with_the_keys() {
+        let mut game = headless_game(GameConfig::default());
+        assert_eq!((game.tui().cursor_position(), game.tui().cursor_index()), ((0, 0), 0));
+        let target = target_text(&game);
+
+        // a backspace takes the cursor back, the keys running out stop it
+        let mut keys: Vec<Key> = target[..4].iter().map(|c| Key::Char(*c)).collect();
+        keys.push(Key::Backspace);
+        assert!(game.run(&KeyReader::from_keys(keys)).is_err());
+        assert_eq!(game.tui().cursor_index(), 3);
+        assert_eq!(game.tui().cursor_position(), (0, 3));
+        assert_eq!(game.tui().num_lines(), game.text.len());
+    }
+
+    #[test]
+    fn batched_drawing_scores_every_key() {
+        let mut plain = headless_game(GameConfig::default());
+        let mut batched = headless_game(GameConfig {
+            flush_interval: Some(Duration::from_millis(16)),
+            ..GameConfig::default()
+        });
+        let target = target_text(&plain);
+        let wrong = mistype(target[1]);
+
+        let mut keys = vec![Key::Char(target[0]), Key::Char(wrong), Key::Backspace];
+        keys.extend(target[1..].iter().map(|c| Key::Char(*c)));
+        keys.push(Key::Ctrl('c'));
+        let (_, plain_results) = plain.run(&KeyReader::from_keys(keys.clone())).unwrap();
+        let (_, batched_results) = batched.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert_eq!(batched_results.chars_typed(), plain_results.chars_typed());
+        assert_eq!(batched_results.total_keystrokes(), target.len() + 2);
+        assert_eq!(batched_results.errors(), 1);
+        assert_eq!(batched_results.uncorrected_errors(), 0);
+        assert_eq!(batched_results.mistaken_chars, plain_results.mistaken_chars);
+    }
+
+    #[test]
+    fn reversed_texts_are_typed_as_shown() {
+        let forwards = headless_game(GameConfig::default());
+        let mut game = headless_game(GameConfig {
+            reverse_words: true,
+            reverse_letters: true,
+            ..GameConfig::default()
+        });
+
+        let backwards: Vec<String> = forwards
+            .words
+            .iter()
+            .rev()
+            .map(|word| reversed(word))
+            .collect();
+        assert_eq!(game.words, backwards);
+        let shown = target_text(&game);
+        assert_eq!(shown.iter().collect::<String>(), backwards.join(" "));
+
+        let mut keys: Vec<Key> = shown.iter().map(|c| Key::Char(*c)).collect();
+        keys.push(Key::Ctrl('c'));
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+        assert_eq!(results.chars_reached(), shown.len());
+        assert_eq!(results.errors(), 0);
+        assert_eq!(results.correct_words(), backwards.len());
+        let timed_words: Vec<String> = results
+            .word_times
+            .iter()
+            .map(|(word, _)| word.clone())
+            .collect();
+        assert_eq!(timed_words, backwards);
+    }
+
+    #[test]
+    fn games_need_a_terminal_size() {
+        let config = GameConfig { history: None, autosave: None, ..GameConfig::default() };
+        let error = Game::headless(config, Vec::new(), (0, 0)).err().unwrap();
+        assert!(error.msg.starts_with("Could not determine terminal size"), "{}", error.msg);
+    }
+
+    #[test]
+    fn hidden_hints_leave_their_row_to_the_text() {
+        let config = GameConfig {
+            history: None,
+            autosave: None,
+            seed: Some(1),
+            num_words: 5,
+            ..GameConfig::default()
+        };
+        // the words take a line, with a margin and the hint line
+        let error = Game::headless(config.clone(), Vec::new(), (100, 3)).err().unwrap();
+        assert!(error.msg.contains("at least 4 lines"), "{}", error.msg);
+        // the live stats and the restart prompt still need the row
+        let hidden = GameConfig {
+            hints: false,
+            ..config.clone()
+        };
+        for config in [
+            hidden.clone(),
+            GameConfig {
+                live_stats: false,
+                confirm_restart: true,
+                ..hidden.clone()
+            },
+        ] {
+            let error = Game::headless(config, Vec::new(), (100, 3)).err().unwrap();
+            assert!(error.msg.contains("at least 4 lines"), "{}", error.msg);
+        }
+
+        let config = GameConfig { live_stats: false, ..hidden };
+        let mut game = Game::headless(config.clone(), Vec::new(), (100, 3)).unwrap();
+        assert!(!output(&game).contains("to restart"));
+        let error = Game::headless(config, Vec::new(), (100, 2)).err().unwrap();
+        assert!(error.msg.contains("at least 3 lines"), "{}", error.msg);
+
+        // the text fills the screen, nothing is drawn over the status row
+        let target = target_text(&game);
+        // quit before the results, which don't fit
+        let mut keys: Vec<Key> = target[..target.len() - 1].iter().map(|c| Key::Char(*c)).collect();
+        keys.push(Key::Ctrl('c'));
+        let drawn_before = game.tui().writer().len();
+        game.run(&KeyReader::from_keys(keys)).unwrap();
+        let status_row = format!("{}{}", termion::cursor::Goto(1, 1), termion::clear::CurrentLine);
+        let screen = String::from_utf8_lossy(&game.tui().writer()[drawn_before..]).into_owned();
+        assert!(!screen.contains(&status_row), "{:?}", screen);
+    }
+
+    #[test]
+    fn header_shows_the_mode_and_seed() {
+        let mut game = headless_game(GameConfig { header: true, ..GameConfig::default() });
+        game.restart().unwrap();
+
+        let screen = String::from_utf8_lossy(game.tui.writer()).into_owned();
+        let mode = format!("markov · {} words", game.words.len());
+        assert!(screen.contains(&Text::from(mode).with_bold().to_string()), "{:?}", screen);
+        assert!(screen.contains(&Text::from("seed 1").with_faint().to_string()));
+
+        game.config.time_limit = Some(Duration::from_secs(60));
+        assert_eq!(game.header()[0][0].text(), "markov · timed 60s");
+        game.config.header = false;
+        assert!(game.header().is_empty());
+    }
+
+    #[test]
+    fn headless_game_records_the_longest_streak() {
+        let mut game = headless_game(GameConfig {
+            strict_space: true,
+            ..GameConfig::default()
+        });
+        let target = target_text(&game);
+
+        // 3 right, a mistake, 6 right with one of them typed twice, and a
+        // mistake
+        let mut keys: Vec<Key> = target[..3].iter().map(|c| Key::Char(*c)).collect();
+        keys.push(Key::Char(mistype(target[3])));
+        keys.extend(target[4..8].iter().map(|c| Key::Char(*c)));
+        keys.extend([Key::Backspace, Key::Char(target[7]), Key::Char(target[8])]);
+        keys.extend([Key::Char(mistype(target[9])), Key::Char(target[10]), Key::Ctrl('c')]);
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert_eq!(results.errors(), 2);
+        assert_eq!(results.longest_streak(), 6);
+    }
+
+    #[test]
+    fn headless_game_scores_the_chars_of_its_lines() {
+        for line_end_space in [true, false] {
+            let config = GameConfig {
+                history: None,
+                autosave: None,
+                seed: Some(1),
+                line_end_space,
+                ..GameConfig::default()
+            };
+            let mut game = Game::headless(config, Vec::new(), (100, 30)).unwrap();
+            // the chars to type, without the spaces skipped at line ends
+            let lines: Vec<String> = game
+                .text
+                .iter()
+                .map(|line| {
+                    if line_end_space {
+                        line.text().clone()
+                    } else {
+                        line.text().trim_end().to_string()
+                    }
+                })
+                .collect();
+            assert!(lines.len() > 1);
+            let shown: usize = lines.iter().map(|line| line.chars().count()).sum();
+
+            // a mistake fixed back across the end of the first line
+            let mut keys: Vec<Key> = lines[0].chars().map(Key::Char).collect();
+            keys.extend([Key::Char('#'), Key::Backspace, Key::Backspace]);
+            keys.push(Key::Char(lines[0].chars().last().unwrap()));
+            keys.extend(lines[1..].concat().chars().map(Key::Char));
+            keys.push(Key::Ctrl('c'));
+            let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+            assert_eq!(results.chars_reached(), shown, "line end space {}", line_end_space);
+            assert_eq!(results.text_len(), shown, "line end space {}", line_end_space);
+            assert_eq!(results.uncorrected_errors(), 0, "line end space {}", line_end_space);
+            assert_eq!(results.final_chars_typed_correctly, shown);
+            let timed_words: Vec<String> = results
+                .word_times
+                .iter()
+                .map(|(word, _)| word.clone())
+                .collect();
+            assert_eq!(timed_words, game.words, "line end space {}", line_end_space);
+        }
+    }
+
+    #[test]
+    fn crashed_test_resumes_from_its_autosave() {
+        let path = std::env::temp_dir().join(format!("typeracer-autosave-{}", std::process::id()));
+        let mut game = headless_game(GameConfig::default());
+        game.config.autosave = Some(path.clone());
+        let target = target_text(&game);
+        let wrong = mistype(target[2]);
+
+        // the keys running out ends the game like a crash
+        let mut typed = target[..6].to_vec();
+        typed[2] = wrong;
+        let error = game
+            .run(&KeyReader::from_keys(typed.iter().map(|c| Key::Char(*c))))
+            .unwrap_err();
+        assert!(error.msg.contains("closed"), "{}", error.msg);
+        let session = Session::load(&path).unwrap().expect("test was saved");
+        assert_eq!(session.words, game.words);
+        assert_eq!(session.input, typed);
+
+        // on a narrower terminal, the mistake drawn as one
+        let mut resumed = headless_game(GameConfig::default());
+        resumed.config.autosave = Some(path.clone());
+        resumed.tui.set_terminal_size((60, 30));
+        resumed.resume(session).unwrap();
+        assert_eq!(target_text(&resumed), target);
+        let theme = resumed.config.theme;
+        assert!(output(&resumed).contains(&typed_text(wrong, target[2], &theme).to_string()));
+
+        let mut keys: Vec<Key> = target[6..].iter().map(|c| Key::Char(*c)).collect();
+        keys.push(Key::Ctrl('c'));
+        let (_, results) = resumed.run(&KeyReader::from_keys(keys)).unwrap();
+        assert_eq!(results.chars_reached(), target.len());
+        assert_eq!(results.chars_typed(), target.len());
+        assert_eq!(results.errors(), 1);
+        assert_eq!(results.uncorrected_errors(), 1);
+        // finished, so there's nothing to resume
+        assert_eq!(Session::load(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn finished_sessions_cant_be_resumed() {
+        let mut game = headless_game(GameConfig::default());
+        let session = Session {
+            seed: 1,
+            words: game.words.clone(),
+            lines: None,
+            input: target_text(&game),
+            elapsed: Duration::from_secs(10),
+        };
+        assert!(game.resume(session).is_err());
+    }
+
+    #[test]
+    fn headless_game_gives_feedback_on_mistakes() {
+        for (error_feedback, signal) in [
+            (ErrorFeedback::Bell, "\x07"),
+            (ErrorFeedback::Flash, "\x1b[?5h"),
+        ] {
+            let mut game = headless_game(GameConfig {
+                error_feedback,
+                ..GameConfig::default()
+            });
+            let first_char = game.words[0].chars().next().unwrap();
+            let wrong = mistype(first_char);
+
+            let keys = KeyReader::from_keys([Key::Char(first_char), Key::Ctrl('c')]);
+            game.run(&keys).unwrap();
+            assert!(!output(&game).contains(signal), "{:?} on a correct key", error_feedback);
+
+            let keys = KeyReader::from_keys([Key::Char(wrong), Key::Ctrl('c')]);
+            game.run(&keys).unwrap();
+            assert_eq!(output(&game).matches(signal).count(), 1, "{:?}", error_feedback);
+        }
+    }
+
+    #[test]
+    fn headless_game_without_scoring_goes_on() {
+        let mut game = headless_game(GameConfig { scoring: false, ..GameConfig::default() });
+        let keys: Vec<Key> = target_text(&game).into_iter().map(Key::Char).collect();
+
+        let (to_restart, _) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert!(to_restart);
+        assert!(!output(&game).contains("Accuracy"));
+
+        // ctrl-c still quits
+        game.restart().unwrap();
+        let (to_restart, _) = game.run(&KeyReader::from_keys([Key::Ctrl('c')])).unwrap();
+        assert!(!to_restart);
+    }
+
+    #[test]
+    fn headless_game_highlights_the_next_char() {
+        use crate::text::HasLength;
+
+        let mut game = headless_game(GameConfig { highlight_next: true, ..GameConfig::default() });
+        let target = target_text(&game);
+        let theme = game.config.theme;
+        let highlight = |c: char| untyped_text(c, &theme).with_invert().to_string();
+
+        let keys = KeyReader::from_keys([Key::Char(target[0]), Key::Backspace, Key::Ctrl('c')]);
+        game.run(&keys).unwrap();
+
+        let screen = output(&game);
+        let first = screen.find(&highlight(target[0])).expect("first char highlighted");
+        let second = screen.find(&highlight(target[1])).expect("second char highlighted");
+        let again = screen.rfind(&highlight(target[0])).unwrap();
+        // highlighted before the first key, moved on, then back
+        assert!(first < second && second < again);
+        assert_eq!(untyped_text('x', &theme).with_invert().length(), 1);
+    }
+
+    #[test]
+    fn headless_game_runs_the_pacer_ahead() {
+        let mut game = headless_game(GameConfig { pacer: Some(60), ..GameConfig::default() });
+        let target = target_text(&game);
+        let theme = game.config.theme;
+
+        // 5 characters a second, the pacer passes the second and third one
+        let keys = KeyReader::from_timed_keys([
+            (Duration::ZERO, Key::Char(target[0])),
+            (Duration::from_millis(700), Key::Ctrl('c')),
+        ]);
+        game.run(&keys).unwrap();
+
+        let screen = output(&game);
+        let second = screen
+            .find(&pacer_text(target[1], &theme).to_string())
+            .expect("pacer on the second char");
+        let third = screen
+            .find(&pacer_text(target[2], &theme).to_string())
+            .expect("pacer on the third char");
+        assert!(second < third);
+    }
+
+    #[test]
+    fn pacer_skips_typed_chars_and_stops_at_the_end() {
+        let theme = Theme::default();
+        let mut tui = GameTui::headless(Vec::new(), (100, 30));
+        tui.display_words(&[String::from("abc"), String::from("de")], theme.faint).unwrap();
+        let text: Vec<char> = "abc de".chars().collect();
+        let mut paced = None;
+        let drawn_after =
+            |tui: &mut GameTui<Vec<u8>>, typed, highlighted, next, paced: &mut Option<usize>| {
+                let from = tui.writer().len();
+                move_pacer(tui, &theme, &text, &[], 0, typed, highlighted, next, paced).unwrap();
+                String::from_utf8_lossy(&tui.writer()[from..]).into_owned()
+            };
+
+        // behind the user
+        assert!(!drawn_after(&mut tui, 2, None, 1, &mut paced).contains('b'));
+        assert!(drawn_after(&mut tui, 2, None, 4, &mut paced)
+            .contains(&pacer_text('d', &theme).to_string()));
+        // past the end only the last place is put back
+        let end = drawn_after(&mut tui, 2, None, 6, &mut paced);
+        assert!(end.contains(&untyped_text('d', &theme).to_string()));
+        assert!(!end.contains(&pacer_text('e', &theme).to_string()));
+        assert_eq!(paced, Some(6));
+        // the highlighted next char stays highlighted
+        assert!(!drawn_after(&mut tui, 2, Some(2), 2, &mut paced).contains('c'));
+    }
+
+    #[test]
+    fn escape_quits_unless_turned_off() {
+        let mut game = headless_game(GameConfig::default());
+        let target = target_text(&game);
+        let keys = KeyReader::from_keys([Key::Char(target[0]), Key::Esc]);
+        let (to_restart, results) = game.run(&keys).unwrap();
+        assert!(!to_restart);
+        assert_eq!(results.chars_typed(), 1);
+        assert!(!output(&game).contains("Accuracy"));
+
+        // the results quit on escape too
+        let mut game = headless_game(GameConfig::default());
+        let mut keys: Vec<Key> = target.iter().map(|c| Key::Char(*c)).collect();
+        keys.push(Key::Esc);
+        assert!(!game.run(&KeyReader::from_keys(keys.clone())).unwrap().0);
+
+        // turned off, escape is ignored while typing and on the results
+        let mut game = headless_game(GameConfig { esc_quits: false, ..GameConfig::default() });
+        keys.insert(1, Key::Esc);
+        keys.push(Key::Ctrl('c'));
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+        assert_eq!(results.final_uncorrected_errors, 0);
+        assert!(output(&game).contains("Accuracy: 100.0%"));
+    }
+
+    #[test]
+    fn headless_game_confirms_restarts() {
+        let mut game = headless_game(GameConfig { confirm_restart: true, ..GameConfig::default() });
+        let target = target_text(&game);
+
+        let keys = KeyReader::from_keys([
+            Key::Char(target[0]),
+            Key::Ctrl('r'),
+            // declined, and not typed
+            Key::Char('n'),
+            Key::Char(target[1]),
+            Key::Ctrl('r'),
+            Key::Char('y'),
+        ]);
+        let (to_restart, results) = game.run(&keys).unwrap();
+
+        assert!(to_restart);
+        assert_eq!(results.chars_typed(), 2);
+        assert_eq!(results.errors(), 0);
+        assert!(output(&game).contains("Restart? "));
+    }
+
+    #[test]
+    fn headless_game_ends_on_time_without_keys() {
+        let mut game = headless_game(GameConfig {
+            time_limit: Some(Duration::from_millis(300)),
+            ..GameConfig::default()
+        });
+        let first_char = game.words[0].chars().next().unwrap();
+
+        // nothing is pressed until well after the limit
+        let keys = KeyReader::from_timed_keys([
+            (Duration::ZERO, Key::Char(first_char)),
+            (Duration::from_millis(800), Key::Ctrl('c')),
+        ]);
+        let started_at = Instant::now();
+        let (to_restart, results) = game.run(&keys).unwrap();
+
+        assert!(!to_restart);
+        assert_eq!(results.duration(), Duration::from_millis(300));
+        assert_eq!(results.total_chars_in_text, 1);
+        assert!(started_at.elapsed() >= Duration::from_millis(800));
+        let output = output(&game);
+        assert!(output.contains("Typed 1 words in a 0s test"));
+        assert!(output.contains("0:00"));
+    }
+
+    #[test]
+    fn headless_game_restarts() {
+        let mut game = headless_game(GameConfig::default());
+
+        let keys = KeyReader::from_keys([Key::Char('a'), Key::Ctrl('r')]);
+        let (to_restart, _) = game.run(&keys).unwrap();
+        assert!(to_restart);
+
+        // keys running out is an error, not a panic
+        assert!(game.run(&keys).is_err());
+    }
+
+    #[test]
+    fn locked_text_comes_back_until_ctrl_n() {
+        let random = GameConfig {
+            history: None,
+            autosave: None,
+            seed: None,
+            num_words: 5,
+            ..GameConfig::default()
+        };
+        let mut game = Game::headless(
+            GameConfig {
+                lock_text: true,
+                ..random.clone()
+            },
+            Vec::new(),
+            (100, 30),
+        )
+        .unwrap();
+        let words = game.words.clone();
+        let seed = game.seed;
+        game.restart().unwrap();
+        assert_eq!(game.words, words);
+        assert_eq!(game.seed, seed);
+
+        // ctrl-n during the test asks for new words
+        let (to_restart, _) = game
+            .run(&KeyReader::from_keys([Key::Char('a'), Key::Ctrl('n')]))
+            .unwrap();
+        assert!(to_restart);
+        game.restart().unwrap();
+        assert_ne!(game.words, words);
+        let words = game.words.clone();
+        game.restart().unwrap();
+        assert_eq!(game.words, words);
+
+        let mut game = Game::headless(random, Vec::new(), (100, 30)).unwrap();
+        let words = game.words.clone();
+        game.restart().unwrap();
+        assert_ne!(game.words, words);
+    }
+
+    #[test]
+    fn headless_timed_game_takes_more_words() {
+        let mut game = headless_game(GameConfig {
+            time_limit: Some(Duration::from_secs(600)),
+            ..GameConfig::default()
+        });
+
+        // the same seed gives the same stream of words
+        let cache = markov::create_counted_cache(load_tokens(None).unwrap(), 2);
+        let words: Vec<String> = MarkovWords::new(cache, Isaac64Rng::from_seed(&[1][..]))
+            .unwrap()
+            .endless()
+            .take(40)
+            .collect();
+        assert_eq!(target_text(&game), words[..5].join(" ").chars().collect::<Vec<_>>());
+
+        let typed = words.join(" ");
+        let mut keys: Vec<Key> = typed.chars().map(Key::Char).collect();
+        keys.push(Key::Ctrl('c'));
+        let (to_restart, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        // quit rather than finish, the text never ran out
+        assert!(!to_restart);
+        assert_eq!(results.total_words, 40);
+        assert_eq!(results.total_chars_in_text, typed.chars().count());
+        assert_eq!(results.total_char_errors, 0);
+        assert!(!output(&game).contains("Accuracy"));
+    }
+
+    #[test]
+    fn share_code_replays_the_same_words() {
+        let config = GameConfig {
+            history: None,
+            autosave: None,
+            punctuation: true,
+            ..GameConfig::default()
+        };
+        let random = Game::headless(config.clone(), Vec::new(), (100, 30)).unwrap();
+        let code = random.share_code().unwrap();
+
+        let replay = Game::headless(
+            config.with_share_code(&code).unwrap(),
+            Vec::new(),
+            (100, 30),
+        )
+        .unwrap();
+        assert_eq!(replay.words, random.words);
+        assert_eq!(replay.share_code().unwrap(), code);
+    }
+
+    #[test]
+    fn share_code_outlives_the_corpus_file() {
+        let corpus = load_tokens(None).unwrap().join(" ");
+        // the file is gone once the game started
+        let mut game = game_with_file("share-code", &corpus, Mode::Markov).unwrap();
+        // the same words as the built in corpus
+        let config = GameConfig { corpus: None, ..game.config.clone() };
+        assert_eq!(game.share_code().unwrap(), config.to_share_code().unwrap());
+
+        let mut keys: Vec<Key> = target_text(&game).into_iter().map(Key::Char).collect();
+        keys.push(Key::Ctrl('c'));
+        game.run(&KeyReader::from_keys(keys)).unwrap();
+        let code = game.share_code().unwrap();
+        assert!(output(&game).contains(&format!("Test code: {}", code)));
+    }
+
+    #[test]
+    fn repetitive_corpora_are_warned_about() {
+        let warning = "This corpus is repetitive";
+        let corpus = "one two three four. ".repeat(10);
+        let game = game_with_file("repetitive", &corpus, Mode::Markov).unwrap();
+        assert!(output(&game).contains(warning));
+        assert!(game.header()[0][0].text().starts_with(warning));
+
+        let game = headless_game(GameConfig::default());
+        assert!(!output(&game).contains(warning));
+        assert_eq!(TextSource::new(&GameConfig::default()).unwrap().corpus_warning(), None);
+    }
+
+    #[test]
+    fn chain_cache_is_saved_and_read_back() {
+        let path =
+            std::env::temp_dir().join(format!("typeracer-chain-cache-{}", std::process::id()));
+        let config = GameConfig {
+            chain_cache: Some(path.clone()),
+            ..GameConfig::default()
+        };
+        let built = headless_game(config.clone());
+        let tokens = load_tokens(None).unwrap();
+        let hash = corpus_hash(&tokens, config.order);
+        assert!(load_cache(&path, hash).unwrap().is_some());
+        let read = headless_game(config.clone());
+        assert_eq!(read.words, built.words);
+
+        // a chain of other words saved for this corpus is what's played
+        let other: Vec<String> = "zig zag zog. "
+            .repeat(5)
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        save_cache(&WordChain::new(other.clone(), config.order), hash, &path).unwrap();
+        let read = headless_game(config.clone());
+        assert!(read.words.iter().all(|word| other.contains(word)), "{:?}", read.words);
+        std::fs::remove_file(&path).unwrap();
+
+        // a cache that can't be written doesn't stop the game
+        let unwritable =
+            std::env::temp_dir().join(format!("typeracer-no-dir-{}", std::process::id()));
+        std::fs::write(&unwritable, "a file, not a dir").unwrap();
+        let config = GameConfig { chain_cache: Some(unwritable.join("chain")), ..config };
+        assert_eq!(headless_game(config).words, built.words);
+        std::fs::remove_file(unwritable).unwrap();
+    }
+
+    #[test]
+    fn words_wider_than_the_lines_are_typed_across_them() {
+        let long = "z".repeat(60);
+        let corpus = format!("{} is a word. it is long. ", long).repeat(5);
+        let mut game = game_with_file("long-words", &corpus, Mode::Markov).unwrap();
+        assert!(game.words.contains(&long));
+        // the width is 50 columns on a terminal of 100
+        assert!(game.text.iter().all(|line| line.text().trim_end().chars().count() <= 50));
+        assert!(game.text.iter().any(|line| *line.text() == "z".repeat(50)));
+
+        let target = target_text(&game);
+        assert_eq!(target, game.words.join(" ").chars().collect::<Vec<_>>());
+        let mut keys: Vec<Key> = target.iter().map(|c| Key::Char(*c)).collect();
+        keys.push(Key::Ctrl('c'));
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+        assert_eq!(results.total_char_errors, 0);
+        assert!(output(&game).contains("Accuracy: 100.0%"));
+    }
+
+    #[test]
+    fn provided_words_are_played() {
+        use crate::provider::EchoProvider;
+
+        let mut game = headless_game(GameConfig::default());
+        let words = vec![String::from("hello"), String::from("world")];
+        game.set_text_provider(Box::new(EchoProvider::new(words.clone()))).unwrap();
+        assert_eq!(game.words, words);
+        assert_eq!(target_text(&game), "hello world".chars().collect::<Vec<_>>());
+
+        let mut keys: Vec<Key> = "hello world".chars().map(Key::Char).collect();
+        keys.push(Key::Ctrl('c'));
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+        assert_eq!(results.total_words, 2);
+        // a provider can't replay its words from a seed
+        assert!(!output(&game).contains("Test code"));
+        assert!(game.share_code().is_err());
+        game.restart().unwrap();
+        assert_eq!(game.words, words);
+    }
+
+    #[test]
+    fn locked_text_leaves_the_provider_be() {
+        // counts the tests it hands out, each with new words
+        struct Counting(Rc<std::cell::Cell<usize>>);
+
+        impl TextProvider for Counting {
+            fn next_test(&mut self, _num_words: usize) -> Result<Vec<String>, GameError> {
+                self.0.set(self.0.get() + 1);
+                Ok(vec![format!("test{}", self.0.get())])
+            }
+        }
+
+        let count = Rc::new(std::cell::Cell::new(0));
+        let mut game = headless_game(GameConfig { lock_text: true, ..GameConfig::default() });
+        game.set_text_provider(Box::new(Counting(Rc::clone(&count)))).unwrap();
+        assert_eq!(count.get(), 1);
+        for _ in 0..3 {
+            game.restart().unwrap();
+        }
+        assert_eq!(game.words, ["test1"]);
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn restart_reuses_the_cache() {
+        let corpus = load_tokens(None).unwrap().join(" ");
+        let mut game = game_with_file("reuse", &corpus, Mode::Markov).unwrap();
+        let TextSource::Markov(cache) = &game.source else { panic!("not a markov game") };
+        let cache = Rc::clone(cache);
+
+        // restarting never reads the corpus again, it's gone by now
+        for seed in 0..50 {
+            game.restart_with_seed(Some(seed)).unwrap();
+        }
+
+        let TextSource::Markov(restarted) = &game.source else { panic!("not a markov game") };
+        assert!(Rc::ptr_eq(&cache, restarted));
+    }
+
+    #[test]
+    fn corpus_control_chars_are_stripped() {
+        let corpus = "one\ttwo \x1b[31mthree\x1b[0m fo\0ur five\nsix \x1b[2J seven";
+        let game = game_with_file("controls", corpus, Mode::Markov).unwrap();
+
+        for word in &game.words {
+            assert!(!word.chars().any(char::is_control), "{:?}", word);
+        }
+        let TextSource::Markov(cache) = &game.source else { panic!("not a markov game") };
+        assert!(cache.followers("one two").is_some());
+        assert!(cache.followers("three four").is_some());
+        // the screen clear was left out entirely
+        assert_eq!(cache.followers("five six"), Some(vec![("seven", 1)]));
+    }
+
+    #[test]
+    fn game_errors_are_errors() {
+        fn boxed() -> Result<(), Box<dyn std::error::Error>> {
+            Err(GameError::from(String::from("Corpus is empty")))?
+        }
+
+        let error = boxed().unwrap_err();
+        assert_eq!(error.to_string(), "Corpus is empty");
+        assert_eq!(format!("{:?}", GameError::from(String::from("x"))), "GameError: x");
+    }
+
+    #[test]
+    fn piped_corpus_is_read_like_a_file() {
+        let piped: &[u8] = b"one two\n\x1b[31mthree\x1b[0m\tfour\r\n\n five ";
+        assert_eq!(read_tokens(piped).unwrap(), ["one", "two", "three", "four", "five"]);
+        assert!(read_tokens(&b""[..]).unwrap().is_empty());
+        assert!(read_tokens(&b"\xff\xfe"[..]).is_err());
+    }
+
+    #[test]
+    fn corpus_of_only_control_chars_is_an_error() {
+        let corpus = "\x1b[31m \0\x07 \x1b[0m\n\t";
+        let error = game_with_file("only-controls", corpus, Mode::Markov)
+            .err()
+            .expect("game should not start");
+        assert!(error.msg.starts_with("Corpus is empty or too short"), "{}", error.msg);
+    }
+
+    #[test]
+    fn empty_corpus_is_an_error() {
+        for (name, corpus) in [("empty", ""), ("blank", " \n\t \n"), ("short", "two words")] {
+            let error = game_with_file(name, corpus, Mode::Markov)
+                .err()
+                .expect("game should not start");
+            assert!(error.msg.starts_with("Corpus is empty or too short"), "{}", error.msg);
+        }
+    }
+
+    #[test]
+    fn verbatim_text_keeps_its_lines() {
+        let text = "\n\nfirst line  \n\n  indented\t\nlast\n\n";
+        let mut game = game_with_file("verbatim", text, Mode::Verbatim).unwrap();
+
+        assert_eq!(game.lines.as_deref().unwrap(), ["first line", "", "  indented", "last"]);
+        assert_eq!(game.words, vec!["first", "line", "indented", "last"]);
+
+        let keys: Vec<Key> = "first line\n\n  indented\nlast"
+            .chars()
+            .map(Key::Char)
+            .chain([Key::Ctrl('c')])
+            .collect();
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert_eq!(results.total_char_errors, 0);
+        let timed_words: Vec<String> = results
+            .word_times
+            .iter()
+            .map(|(word, _)| word.clone())
+            .collect();
+        assert_eq!(timed_words, game.words);
+    }
+
+    #[test]
+    fn verbatim_tabs_are_typed() {
+        let text = "fn x() {\n\tyes\x1b[1m\t\n}\n";
+        let mut game = game_with_file("tabs", text, Mode::Verbatim).unwrap();
+        assert_eq!(
+            game.lines.as_deref(),
+            Some(&["fn x() {".to_string(), "\tyes".to_string(), "}".to_string()][..])
+        );
+
+        let keys: Vec<Key> = "fn x() {\n\tyes\n}"
+            .chars()
+            .map(Key::Char)
+            .chain([Key::Ctrl('c')])
+            .collect();
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert_eq!(results.errors(), 0);
+        assert_eq!(results.chars_typed(), 15);
+    }
+
+    #[test]
+    fn verbatim_mode_needs_a_text() {
+        let config = GameConfig {
+            mode: Mode::Verbatim,
+            history: None,
+            autosave: None,
+            ..GameConfig::default()
+        };
+        let error = Game::headless(config, Vec::new(), (100, 30))
+            .err()
+            .expect("game should not start");
+        assert!(error.msg.contains("needs a text file"), "{}", error.msg);
+
+        let blank = TempFile::new("blank", " \n\t\n");
+        let error = load_verbatim(Some(&blank.0)).unwrap_err();
+        assert_eq!(error.msg, "Text file is empty");
+    }
+
+    #[test]
+    fn last_char_is_scored() {
+        let mut game = game_with_file("abc", "abc\n", Mode::Quotes).unwrap();
+        let theme = game.config.theme;
+
+        let keys = KeyReader::from_keys([
+            Key::Char('a'),
+            Key::Char('b'),
+            Key::Char('x'),
+            Key::Ctrl('c'),
+        ]);
+        let (_, results) = game.run(&keys).unwrap();
+
+        assert_eq!(results.chars_typed(), 3);
+        assert_eq!(results.errors(), 1);
+        assert_eq!(results.uncorrected_errors(), 1);
+        assert_eq!(results.keystrokes().len(), 3);
+        assert_eq!(results.top_mistaken_chars(1), vec![('c', 1)]);
+        assert!(output(&game).contains(&typed_text('x', 'c', &theme).to_string()));
+    }
+
+    #[test]
+    fn accented_and_wide_text_is_typed_char_by_char() {
+        let quote = "Ça naïve 日本 cafe\u{301}\n";
+        let mut game = game_with_file("accented", quote, Mode::Quotes).unwrap();
+        let theme = game.config.theme;
+        let target = target_text(&game);
+        assert_eq!(target.len(), 17);
+
+        // 'ï' mistyped as 'i' and corrected
+        let mut keys: Vec<Key> = "Ça nai".chars().map(Key::Char).collect();
+        keys.push(Key::Backspace);
+        keys.extend(target[5..].iter().map(|c| Key::Char(*c)));
+        keys.push(Key::Ctrl('c'));
+        let (_, results) = game.run(&KeyReader::from_keys(keys)).unwrap();
+
+        assert_eq!(results.chars_reached(), 17);
+        assert_eq!(results.errors(), 1);
+        assert_eq!(results.uncorrected_errors(), 0);
+        assert_eq!(results.top_mistaken_chars(1), vec![('ï', 1)]);
+        for c in ['Ç', '日', '本', '\u{301}'] {
+            assert!(output(&game).contains(&typed_text(c, c, &theme).to_string()), "{}", c);
+        }
+    }
 }
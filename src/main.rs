@@ -1,20 +1,457 @@
-use std::io::stdin;
-use playground::Game;
-use playground::GameError;
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::time::Duration;
+
+use playground::{Game, GameError, TextSource, STDIN_CORPUS};
+use playground::config::{self, Alignment, CursorStyle, ErrorFeedback, GameConfig, Mode};
+use playground::keyboard::KeyboardLayout;
+use playground::keys::KeyReader;
+use playground::{menu, picker};
+use playground::session::Session;
+use playground::text;
+use playground::theme::Theme;
+use playground::tui::GameTui;
+
+const USAGE: &str = "\
+Usage: playground [OPTIONS]
+
+Options:
+  --words N         number of words in each test (default 30)
+  --time S          end each test after S seconds
+  --seed N          generate the same text on every run
+  --mode MODE       where the text comes from: markov (default), quotes,
+                    verbatim or drill, which mixes in words mistyped before
+  --corpus PATH     train the markov chain on this file, or on stdin if PATH
+                    is - together with --print-only
+  --pick-corpus DIR choose the corpus from the files in DIR at startup
+  --chain-cache PATH
+                    save the markov chain to PATH and read it from there
+                    while the corpus and order stay the same
+  --quotes PATH     pick quotes from this file, one per line
+  --text PATH       type this file line by line in verbatim mode
+  --tab-width N     columns a tab takes up in verbatim mode (default 4)
+  --order N         words of context for the markov chain (default 2)
+  --theme NAME      default, high-contrast or solarized
+  --width PCT       wrap the text to PCT% of the terminal width (default 40)
+  --line-words N    put at most N words on a line of the text (default 10)
+  --align WHERE     center (default) or left
+  --cursor STYLE    bar (default), block or underline, each also steady-,
+                    e.g. steady-block
+  --countdown N     count down N seconds before each test
+  --punctuation     mix capitals, punctuation and numbers into the words
+  --prettify        capitalize the words starting sentences
+  --min-word-len N  only use words of at least N letters
+  --max-word-len N  only use words of at most N letters
+  --reverse         put the words in reverse order
+  --reverse-letters spell every word backwards
+  --on-error WHAT   bell or flash on every mistake
+  --show-typed      draw mistakes as the keys typed instead of the text
+  --keyboard NAME   show mistakes by finger on qwerty, dvorak or colemak
+  --strict-space    count anything but a space between words as a mistake
+  --no-line-end-space
+                    go on to the next line without typing a space
+  --final-space     end the last line with a space to type too
+  --zen             keep typing new texts without a results screen
+  --confirm-restart ask before ctrl-r restarts a test
+  --no-esc-quit     quit with ctrl-c only, not with escape too
+  --lock-text       keep the same text on every restart, ctrl-n for a new one
+  --highlight       draw the next character to type inverted
+  --pacer WPM       run a pacer through the text at WPM to keep up with
+  --word-wpm        also show the wpm counted by correct words on the results
+  --no-live-stats   hide the live wpm readout
+  --no-timer        hide the time since the first keystroke
+  --no-progress     hide the progress bar
+  --header          show the mode, word count and seed above the text
+  --no-hints        hide the keys to restart, pause and quit under the test
+  --batch-draw MS   draw what's typed every MS milliseconds, not every key
+  --no-history      don't record results in the history file
+  --no-autosave     don't save the test being typed every few seconds
+  --resume          go on with the test that was being typed when the game
+                    last crashed
+  --print-only      print the text of a test and exit, without the game
+  --export PATH     append a CSV row for every finished test to PATH
+  --log-keys PATH   append every key pressed, when and where the cursor went,
+                    to PATH as tab separated lines
+  --code CODE       replay the test shown as \"Test code\" on a results screen
+  --menu            choose a words or timed test and its length first, as
+                    when started without options
+  --help            show this message
+
+The word count, mode, theme, width and files given are remembered for the
+next run, in $XDG_CONFIG_HOME/rusty-typeracer/config.toml.";
+
+#[derive(Default)]
+struct Args {
+    config: GameConfig,
+    // directory to choose the corpus from before the game starts
+    pick_corpus: Option<PathBuf>,
+    // choose between a words and a timed test before the game starts
+    menu: bool,
+    print_only: bool,
+    resume: bool,
+    help: bool,
+}
+
+// the options in `args` over `config`
+fn parse_args<I>(args: I, config: GameConfig) -> Result<Args, String>
+    where
+    I: IntoIterator<Item = String>,
+{
+    let mut parsed = Args { config, ..Args::default() };
+    let config = &mut parsed.config;
+    let mut args = args.into_iter().peekable();
+    let mut code = None;
+    // started without options, the menu sets up the test instead
+    parsed.menu = args.peek().is_none();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "--words" => config.num_words = parse_value(&arg, value()?)?,
+            "--time" => config.time_limit = Some(Duration::from_secs(parse_value(&arg, value()?)?)),
+            "--seed" => config.seed = Some(parse_value(&arg, value()?)?),
+            "--mode" => {
+                let name = value()?;
+                config.mode =
+                    Mode::by_name(&name).ok_or_else(|| format!("Unknown mode '{}'", name))?;
+            }
+            "--corpus" => config.corpus = Some(PathBuf::from(value()?)),
+            "--chain-cache" => config.chain_cache = Some(PathBuf::from(value()?)),
+            "--pick-corpus" => parsed.pick_corpus = Some(PathBuf::from(value()?)),
+            "--quotes" => config.quotes = Some(PathBuf::from(value()?)),
+            "--text" => config.text = Some(PathBuf::from(value()?)),
+            "--tab-width" => config.tab_width = parse_value(&arg, value()?)?,
+            "--line-words" => config.line_words = parse_value(&arg, value()?)?,
+            "--order" => config.order = parse_value(&arg, value()?)?,
+            "--theme" => {
+                let name = value()?;
+                config.theme =
+                    Theme::by_name(&name).ok_or_else(|| format!("Unknown theme '{}'", name))?;
+            }
+            "--width" => {
+                let percent: u8 = parse_value(&arg, value()?)?;
+                if !(1..=100).contains(&percent) {
+                    return Err(format!("--width must be a percentage, got {}", percent));
+                }
+                config.line_width = percent as f64 / 100.0;
+            }
+            "--align" => {
+                config.alignment = match value()?.as_str() {
+                    "center" => Alignment::Center,
+                    "left" => Alignment::Left,
+                    alignment => return Err(format!("Unknown alignment '{}'", alignment)),
+                }
+            }
+            "--cursor" => {
+                let name = value()?;
+                config.cursor = CursorStyle::by_name(&name)
+                    .ok_or_else(|| format!("Unknown cursor style '{}'", name))?;
+            }
+            "--countdown" => config.countdown = Some(parse_value(&arg, value()?)?),
+            "--punctuation" => config.punctuation = true,
+            "--prettify" => config.prettify = true,
+            "--min-word-len" => config.min_word_len = Some(parse_value(&arg, value()?)?),
+            "--max-word-len" => config.max_word_len = Some(parse_value(&arg, value()?)?),
+            "--reverse" => config.reverse_words = true,
+            "--reverse-letters" => config.reverse_letters = true,
+            "--show-typed" => config.show_typed_mistakes = true,
+            "--on-error" => {
+                config.error_feedback = match value()?.as_str() {
+                    "bell" => ErrorFeedback::Bell,
+                    "flash" => ErrorFeedback::Flash,
+                    "off" => ErrorFeedback::Off,
+                    feedback => return Err(format!("Unknown error feedback '{}'", feedback)),
+                }
+            }
+            "--keyboard" => {
+                let name = value()?;
+                config.keyboard = Some(
+                    KeyboardLayout::by_name(&name)
+                        .ok_or_else(|| format!("Unknown keyboard layout '{}'", name))?,
+                );
+            }
+            "--strict-space" => config.strict_space = true,
+            "--no-line-end-space" => config.line_end_space = false,
+            "--final-space" => config.final_space = true,
+            "--zen" => config.scoring = false,
+            "--confirm-restart" => config.confirm_restart = true,
+            "--no-esc-quit" => config.esc_quits = false,
+            "--lock-text" => config.lock_text = true,
+            "--highlight" => config.highlight_next = true,
+            "--pacer" => {
+                let wpm: u32 = parse_value(&arg, value()?)?;
+                if wpm == 0 {
+                    return Err(String::from("--pacer needs a speed above 0 wpm"));
+                }
+                config.pacer = Some(wpm);
+            }
+            "--word-wpm" => config.word_wpm = true,
+            "--no-live-stats" => config.live_stats = false,
+            "--no-timer" => config.timer = false,
+            "--no-progress" => config.progress = false,
+            "--header" => config.header = true,
+            "--no-hints" => config.hints = false,
+            "--batch-draw" => {
+                config.flush_interval = Some(Duration::from_millis(parse_value(&arg, value()?)?))
+            }
+            "--no-history" => config.history = None,
+            "--print-only" => parsed.print_only = true,
+            "--no-autosave" => config.autosave = None,
+            "--resume" => parsed.resume = true,
+            "--export" => config.export = Some(PathBuf::from(value()?)),
+            "--log-keys" => config.key_log = Some(PathBuf::from(value()?)),
+            "--code" => code = Some(value()?),
+            "--menu" => parsed.menu = true,
+            "--help" | "-h" => parsed.help = true,
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+    }
+
+    // after the other options, since the corpus has to match the code
+    if let Some(code) = code {
+        parsed.config = parsed.config.with_share_code(&code).map_err(|error| error.msg)?;
+    }
+
+    // stdin is where the keys come from while playing
+    if parsed.config.corpus.as_deref() == Some(Path::new(STDIN_CORPUS)) && !parsed.print_only {
+        return Err(format!("--corpus {} only works with --print-only", STDIN_CORPUS));
+    }
+    if parsed.config.num_words == 0 {
+        return Err(String::from("--words must be at least 1"));
+    }
+    if parsed.config.time_limit == Some(Duration::ZERO) {
+        return Err(String::from("--time must be at least 1 second"));
+    }
+    if let (Some(min), Some(max)) = (parsed.config.min_word_len, parsed.config.max_word_len) {
+        if min > max {
+            return Err(format!("--min-word-len {} is more than --max-word-len {}", min, max));
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' for {}", value, arg))
+}
 
 fn main() -> Result<(), GameError> {
+    // the options of the last run, which the ones given now override
+    let config_path = config::default_config_path();
+    let saved = config_path.as_deref().map_or_else(GameConfig::default, GameConfig::load);
+    let args = match parse_args(env::args().skip(1), saved) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    if let Some(path) = &config_path {
+        // not worth giving up the game for
+        if let Err(error) = args.config.save(path) {
+            eprintln!("Could not save the options to {}: {}", path.display(), error.msg);
+        }
+    }
+
+    text::set_colors(text::colors_supported());
 
-    let mut game = Game::new()?;
+    if args.print_only {
+        return print_text(&args.config);
+    }
 
-    let stdin = stdin();
+    let keys = KeyReader::new();
 
-    loop {
-        let stdin = stdin.lock();
-        if let Ok((true, _)) = game.run(stdin) {
-            game.restart()?;
-        } else {
-            break;
+    // errors, like stdin being closed, end the program with a failure
+    // rather than looking like a normal quit
+    let played = play(args.config, args.pick_corpus.as_deref(), args.menu, args.resume, &keys);
+    if let Err(error) = &played {
+        if termion::is_tty(&io::stdout()) {
+            show_error(error, &keys);
         }
     }
+    played
+}
+
+fn play(
+    mut config: GameConfig,
+    pick_corpus: Option<&Path>,
+    menu: bool,
+    resume: bool,
+    keys: &KeyReader,
+) -> Result<(), GameError> {
+    // an empty directory leaves the embedded corpus
+    if let Some(dir) = pick_corpus {
+        let files = picker::corpus_files(dir)?;
+        if !files.is_empty() {
+            let picked = picker::pick_file(&mut GameTui::new(), keys, &files, &config)?;
+            let Some(corpus) = picked else {
+                return Ok(());
+            };
+            config.corpus = Some(corpus);
+        }
+    }
+
+    // a resumed test already has its length
+    if menu && !resume {
+        let Some(picked) = menu::pick_length(&mut GameTui::new(), keys, &config)? else {
+            return Ok(());
+        };
+        config = picked;
+    }
+
+    // read before the terminal is taken over, like the corpus
+    let session = match (resume, &config.autosave) {
+        (false, _) => None,
+        (true, None) => return Err(GameError::from(String::from("--resume needs autosave"))),
+        (true, Some(path)) => Some(
+            Session::load(path)?
+                .ok_or_else(|| GameError::from(String::from("There's no test to resume")))?,
+        ),
+    };
+
+    let mut game = Game::with_config(config)?;
+    if let Some(session) = session {
+        game.resume(session)?;
+    }
+
+    while let (true, _) = game.run(keys)? {
+        game.restart()?;
+    }
+    Ok(())
+}
+
+// the text the game would show, verbatim lines as they are and anything else
+// as one line of words
+fn print_text(config: &GameConfig) -> Result<(), GameError> {
+    let source = TextSource::new(config)?;
+    if let TextSource::Verbatim(lines) = &source {
+        for line in lines {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    if let Some(warning) = source.corpus_warning() {
+        eprintln!("{}", warning);
+    }
+    let seed = config.seed.unwrap_or_else(rand::random);
+    println!("{}", source.words(config, seed)?.join(" "));
     Ok(())
 }
+
+// keeps the error on screen until a key is pressed, since the game clears
+// the screen when it's dropped. Failing here too, e.g. because stdin was
+// closed, just moves on to exiting
+fn show_error(error: &GameError, keys: &KeyReader) {
+    let mut tui = GameTui::new();
+    if tui.display_error(error).is_ok() {
+        let _ = keys.next_key();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()), GameConfig::default())
+    }
+
+    #[test]
+    fn no_args_is_the_default_config() {
+        let config = parse(&[]).unwrap().config;
+        let default = GameConfig::default();
+
+        assert_eq!(config.num_words, default.num_words);
+        assert_eq!(config.mode, Mode::Markov);
+        assert_eq!(config.seed, None);
+        assert_eq!(config.time_limit, None);
+    }
+
+    #[test]
+    fn menu_shows_without_options() {
+        assert!(parse(&[]).unwrap().menu);
+        assert!(!parse(&["--words", "50"]).unwrap().menu);
+        assert!(parse(&["--theme", "solarized", "--menu"]).unwrap().menu);
+    }
+
+    #[test]
+    fn parses_words_time_seed_and_mode() {
+        let args = parse(&[
+            "--words", "50", "--time", "60", "--seed", "7", "--mode", "quotes",
+        ])
+        .unwrap();
+
+        assert_eq!(args.config.num_words, 50);
+        assert_eq!(args.config.time_limit, Some(Duration::from_secs(60)));
+        assert_eq!(args.config.seed, Some(7));
+        assert_eq!(args.config.mode, Mode::Quotes);
+        assert!(!args.help);
+    }
+
+    #[test]
+    fn rejects_bad_args() {
+        assert!(parse(&["--words"]).is_err());
+        assert!(parse(&["--words", "many"]).is_err());
+        assert!(parse(&["--words", "0"]).is_err());
+        assert!(parse(&["--time", "0"]).is_err());
+        assert!(parse(&["--mode", "poetry"]).is_err());
+        assert!(parse(&["--theme", "neon"]).is_err());
+        assert!(parse(&["--on-error", "siren"]).is_err());
+        assert!(parse(&["--width", "0"]).is_err());
+        assert!(parse(&["--pacer", "0"]).is_err());
+        assert!(parse(&["--align", "right"]).is_err());
+        assert!(parse(&["--keyboard", "azerty"]).is_err());
+        assert!(parse(&["--cursor", "steady-beam"]).is_err());
+        assert!(parse(&["--width", "150"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+        assert!(parse(&["--code", "m2-30"]).is_err());
+        assert!(parse(&["--min-word-len", "6", "--max-word-len", "5"]).is_err());
+        assert!(parse(&["--corpus", "-"]).is_err());
+        assert!(parse(&["--corpus", "-", "--print-only"]).is_ok());
+    }
+
+    #[test]
+    fn options_override_the_saved_ones() {
+        let saved = GameConfig {
+            num_words: 12,
+            mode: Mode::Quotes,
+            line_width: 0.6,
+            ..GameConfig::default()
+        };
+        let args = ["--words", "50", "--theme", "solarized"]
+            .iter()
+            .map(|arg| arg.to_string());
+        let config = parse_args(args, saved).unwrap().config;
+
+        assert_eq!(config.num_words, 50);
+        assert_eq!(config.theme, Theme::solarized());
+        assert_eq!(config.mode, Mode::Quotes);
+        assert_eq!(config.line_width, 0.6);
+    }
+
+    #[test]
+    fn code_sets_up_the_shared_test() {
+        let shared = GameConfig {
+            num_words: 12,
+            seed: Some(99),
+            ..GameConfig::default()
+        };
+        let code = shared.to_share_code().unwrap();
+
+        let config = parse(&["--code", &code, "--words", "50"]).unwrap().config;
+        assert_eq!(config.num_words, 12);
+        assert_eq!(config.seed, Some(99));
+    }
+}
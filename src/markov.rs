@@ -1,69 +1,629 @@
 extern crate rand;
 
-use rand::Rng;
-use std::collections::HashMap;
+use rand::{Isaac64Rng, Rng, SeedableRng};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
 
-pub fn create_cache(tokens: Vec<String>) -> HashMap<String, Vec<String>> {
+use crate::{fnv1a, GameError};
+
+/// The key under which a cache lists the keys that start a sentence, as if
+/// they followed it. Tokens are never empty, so no real key can clash with it.
+pub const SENTENCE_START: &str = "";
+
+// how many words past the requested count generation goes to finish a
+// sentence
+const MAX_SENTENCE_OVERRUN: usize = 10;
+
+// first line of a saved chain, changed whenever the format is
+const CACHE_HEADER: &str = "rusty-typeracer chain 1";
+
+// how often `generate_text` starts again from a random key at a dead end. A
+// sparse corpus may dead end every few words, this keeps a tiny one from
+// going on forever
+const MAX_RESTARTS: usize = 20;
+
+/// Whether `token` ends a sentence, ignoring closing quotes and brackets.
+pub fn ends_sentence(token: &str) -> bool {
+    token
+        .trim_end_matches(['"', '\'', ')', ']'])
+        .ends_with(['.', '!', '?'])
+}
+
+/// Builds a markov chain keyed by `order` consecutive tokens joined with a
+/// space, mapping to every token that followed them. An order of 0 is treated
+/// as 1. Keys at the start of the corpus or after a token ending a sentence
+/// are also stored under `SENTENCE_START`.
+pub fn create_cache(tokens: Vec<String>, order: usize) -> HashMap<String, Vec<String>> {
     let mut cache = HashMap::new();
+    let order = order.max(1);
 
-    for i in 0..tokens.len() - 2 {
-        let first = tokens[i].clone();
-        let second = tokens[i + 1].clone();
-        let item = tokens[i + 2].clone();
+    // fewer than `order + 1` tokens leave the cache empty
+    for (start, window) in tokens.windows(order + 1).enumerate() {
+        let key = window[..order].join(" ");
+        let item = window[order].clone();
 
-        let key = format!("{} {}", first, second);
+        if starts_sentence(&tokens, start) {
+            cache.entry(SENTENCE_START.to_string()).or_insert_with(Vec::new).push(key.clone());
+        }
+        cache.entry(key).or_insert_with(Vec::new).push(item);
+    }
+
+    cache
+}
 
-        if !cache.contains_key(&key) {
-            cache.insert(key, vec![item]);
-        } else {
-            cache.get_mut(&key).unwrap().push(item);
+/// Same chain as `create_cache`, but each following token is stored once with
+/// the number of times it followed, which takes far less memory for common
+/// transitions. Tokens are kept in the order they first appeared, so seeded
+/// text doesn't change between runs. `WordChain` is the same chain in a
+/// fraction of the memory.
+pub fn create_counted_cache(
+    tokens: Vec<String>,
+    order: usize,
+) -> HashMap<String, Vec<(String, u32)>> {
+    let mut cache: HashMap<String, Vec<(String, u32)>> = HashMap::new();
+    let order = order.max(1);
+
+    for (start, window) in tokens.windows(order + 1).enumerate() {
+        let key = window[..order].join(" ");
+
+        if starts_sentence(&tokens, start) {
+            add_follower(cache.entry(SENTENCE_START.to_string()).or_default(), &key);
         }
+        add_follower(cache.entry(key).or_default(), &window[order]);
     }
 
     cache
 }
 
-pub fn generate_text(cache: HashMap<String, Vec<String>>, num_words: i32) -> Vec<String> {
+/// How varied the text generated from a chain can be.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    /// Keys with followers, not counting `SENTENCE_START`.
+    pub keys: usize,
+    /// Distinct words following a key, on average. Close to 1 the text
+    /// mostly repeats the corpus.
+    pub avg_branching: f64,
+    /// Followers leading to a key nothing ever followed, where generation has
+    /// to start again from somewhere else.
+    pub dead_ends: usize,
+}
+
+impl CacheStats {
+    fn new(keys: usize, followers: usize, dead_ends: usize) -> Self {
+        let avg_branching = if keys == 0 { 0.0 } else { followers as f64 / keys as f64 };
+        CacheStats { keys, avg_branching, dead_ends }
+    }
+}
 
-    let mut output = vec![];
+/// The stats of a `create_counted_cache` map.
+pub fn cache_stats(cache: &HashMap<String, Vec<(String, u32)>>) -> CacheStats {
+    let keys = cache.iter().filter(|(key, _)| key.as_str() != SENTENCE_START);
+    let (mut key_count, mut followers, mut dead_ends) = (0, 0, 0);
+    for (key, options) in keys {
+        key_count += 1;
+        followers += options.len();
+        // the key after a follower drops the first word of this one
+        let rest = key.split_once(' ').map(|(_, rest)| rest);
+        dead_ends += options
+            .iter()
+            .filter(|(word, _)| {
+                let next = rest.map_or_else(|| word.clone(), |rest| format!("{} {}", rest, word));
+                !cache.contains_key(&next)
+            })
+            .count();
+    }
+    CacheStats::new(key_count, followers, dead_ends)
+}
 
-    // Choose a random seed key
-    let mut rng = rand::thread_rng();
-    let mut keys = cache.keys();
-    let random_idx = rng.gen_range(0, keys.len());
+fn add_follower(options: &mut Vec<(String, u32)>, item: &str) {
+    match options.iter_mut().find(|(word, _)| word == item) {
+        Some((_, count)) => *count += 1,
+        None => options.push((item.to_string(), 1)),
+    }
+}
 
-    // Our random key
-    let seed_key = keys.nth(random_idx).unwrap();
+fn starts_sentence(tokens: &[String], index: usize) -> bool {
+    index == 0 || ends_sentence(&tokens[index - 1])
+}
 
-    let words: Vec<&str> = seed_key.split(" ").collect();
-    let mut first_word = String::from(words[0]);
-    let mut second_word = String::from(words[1]);
+/// Takes `num_words` words, then up to a few more if that finishes the
+/// sentence the last one was in.
+pub fn take_to_sentence_end<I>(words: &mut I, num_words: usize) -> Vec<String>
+    where
+    I: Iterator<Item = String>,
+{
+    let mut taken: Vec<String> = words.by_ref().take(num_words).collect();
+    if taken.last().is_none_or(|word| ends_sentence(word)) {
+        return taken;
+    }
+
+    let overrun: Vec<String> = words.take(MAX_SENTENCE_OVERRUN).collect();
+    if let Some(end) = overrun.iter().position(|word| ends_sentence(word)) {
+        taken.extend(overrun.into_iter().take(end + 1));
+    }
 
-    for _ in 0..num_words {
-        let key = format!("{} {}", first_word, second_word);
+    taken
+}
 
-        let options = match cache.get(&key) {
-            Some(opt) => opt,
+/// The tokens that followed a key in the chain.
+pub trait Followers {
+    /// Picks one, each as often as it followed the key in the corpus.
+    fn pick<R: Rng>(&self, rng: &mut R) -> &str;
+}
+
+impl Followers for Vec<String> {
+    // repeats already make common tokens more likely
+    fn pick<R: Rng>(&self, rng: &mut R) -> &str {
+        &self[rng.gen_range(0, self.len())]
+    }
+}
+
+impl Followers for Vec<(String, u32)> {
+    fn pick<R: Rng>(&self, rng: &mut R) -> &str {
+        pick_weighted::<String, R>(self, rng)
+    }
+}
+
+// picks an option as often as its count says, drawing from `rng` the same way
+// whatever the options are, so counted caches of strings and of word indices
+// generate the same text
+fn pick_weighted<'a, T, R: Rng>(options: &'a [(T, u32)], rng: &mut R) -> &'a T {
+    let total: u32 = options.iter().map(|(_, count)| count).sum();
+    let mut remaining = rng.gen_range(0, total);
+    for (option, count) in options {
+        if remaining < *count {
+            return option;
+        }
+        remaining -= count;
+    }
+    unreachable!("the counts add up to the total")
+}
+
+/// A markov chain `MarkovWords` can walk.
+pub trait Chain {
+    /// How the chain refers to a word.
+    type Word;
+
+    /// Whether there's nothing to generate from.
+    fn is_empty(&self) -> bool;
+
+    /// A key to start generating from, at the start of a sentence if the
+    /// chain knows any.
+    fn start<R: Rng>(&self, rng: &mut R) -> Vec<Self::Word>;
+
+    /// The key made of `words`, or `None` if nothing ever followed them.
+    fn find(&self, words: &[&str]) -> Option<Vec<Self::Word>>;
+
+    /// A word that followed `key`, or `None` if nothing ever did.
+    fn follow<R: Rng>(&self, key: &[Self::Word], rng: &mut R) -> Option<Self::Word>;
+
+    /// The text of `word`.
+    fn resolve(&self, word: Self::Word) -> String;
+}
+
+impl<F: Followers> Chain for HashMap<String, F> {
+    type Word = String;
+
+    fn is_empty(&self) -> bool {
+        HashMap::is_empty(self)
+    }
+
+    fn start<R: Rng>(&self, rng: &mut R) -> Vec<String> {
+        let key = match self.get(SENTENCE_START) {
+            Some(starts) => starts.pick(rng).to_string(),
             None => {
-               return output
+                // sorted since the map's iteration order differs between
+                // runs. Only caches made by hand lack sentence starts
+                let mut keys: Vec<&String> = self.keys().collect();
+                keys.sort();
+                keys[rng.gen_range(0, keys.len())].clone()
             }
         };
+        key.split(' ').map(String::from).collect()
+    }
+
+    fn find(&self, words: &[&str]) -> Option<Vec<String>> {
+        let key = words.join(" ");
+        if key == SENTENCE_START || !self.contains_key(&key) {
+            return None;
+        }
+        Some(words.iter().map(|word| word.to_string()).collect())
+    }
+
+    fn follow<R: Rng>(&self, key: &[String], rng: &mut R) -> Option<String> {
+        let options = self.get(&key.join(" "))?;
+        Some(options.pick(rng).to_string())
+    }
+
+    fn resolve(&self, word: String) -> String {
+        word
+    }
+}
+
+/// The chain `create_counted_cache` builds, with every distinct word stored
+/// once and keys and followers made of indices into those words. A large
+/// corpus takes a fraction of the memory, since a key is `order` numbers
+/// rather than a string and a follower one number. Words become strings
+/// again only as they're generated.
+pub struct WordChain {
+    words: Vec<Box<str>>,
+    followers: HashMap<Box<[u32]>, Vec<(u32, u32)>>,
+    // keys at the start of a sentence, with how often they started one
+    starts: Vec<(Box<[u32]>, u32)>,
+}
+
+impl WordChain {
+    /// Builds the chain of `order` words, 0 being treated as 1. Followers
+    /// and sentence starts keep the order they first appeared in, like
+    /// `create_counted_cache`, so a seed generates the same text with both.
+    pub fn new(tokens: Vec<String>, order: usize) -> Self {
+        let order = order.max(1);
+
+        let mut words: Vec<Box<str>> = Vec::new();
+        let mut word_ids: HashMap<&str, u32> = HashMap::new();
+        let ids: Vec<u32> = tokens
+            .iter()
+            .map(|token| {
+                *word_ids.entry(token.as_str()).or_insert_with(|| {
+                    words.push(token.as_str().into());
+                    (words.len() - 1) as u32
+                })
+            })
+            .collect();
+        let ends: Vec<bool> = words.iter().map(|word| ends_sentence(word)).collect();
+
+        let mut followers: HashMap<Box<[u32]>, Vec<(u32, u32)>> = HashMap::new();
+        let mut starts: Vec<(Box<[u32]>, u32)> = Vec::new();
+        // where each key is in `starts`, there can be many thousands
+        let mut start_index: HashMap<&[u32], usize> = HashMap::new();
+
+        for (start, window) in ids.windows(order + 1).enumerate() {
+            let key = &window[..order];
+
+            if start == 0 || ends[ids[start - 1] as usize] {
+                match start_index.get(key) {
+                    Some(index) => starts[*index].1 += 1,
+                    None => {
+                        start_index.insert(key, starts.len());
+                        starts.push((key.into(), 1));
+                    }
+                }
+            }
+
+            if !followers.contains_key(key) {
+                followers.insert(key.into(), Vec::new());
+            }
+            let options = followers.get_mut(key).expect("inserted above");
+            match options.iter_mut().find(|(word, _)| *word == window[order]) {
+                Some((_, count)) => *count += 1,
+                None => options.push((window[order], 1)),
+            }
+        }
+
+        // most keys have one or two followers, spare capacity would be most
+        // of their memory
+        for options in followers.values_mut() {
+            options.shrink_to_fit();
+        }
+        followers.shrink_to_fit();
+
+        WordChain { words, followers, starts }
+    }
+
+    /// Number of distinct words in the corpus.
+    pub fn vocabulary_len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Number of keys, like the length of a `create_counted_cache` map
+    /// without its `SENTENCE_START` entry.
+    pub fn len(&self) -> usize {
+        self.followers.len()
+    }
+
+    /// Whether the corpus was too short to make any keys.
+    pub fn is_empty(&self) -> bool {
+        self.followers.is_empty()
+    }
+
+    /// The same stats `cache_stats` gives for the counted cache.
+    pub fn stats(&self) -> CacheStats {
+        let mut followers = 0;
+        let mut dead_ends = 0;
+        let mut next: Vec<u32> = Vec::new();
+        for (key, options) in &self.followers {
+            followers += options.len();
+            for (word, _) in options {
+                next.clear();
+                next.extend_from_slice(&key[1..]);
+                next.push(*word);
+                if !self.followers.contains_key(&next[..]) {
+                    dead_ends += 1;
+                }
+            }
+        }
+        CacheStats::new(self.followers.len(), followers, dead_ends)
+    }
+
+    /// The words that followed `key`, `order` words joined with a space,
+    /// with their counts. Looks the words up one by one, so it's only meant
+    /// for inspecting the chain.
+    pub fn followers(&self, key: &str) -> Option<Vec<(&str, u32)>> {
+        let words: Vec<&str> = key.split(' ').collect();
+        let key = self.find(&words)?;
+        let options = self.followers.get(&key[..])?;
+        Some(options.iter().map(|(id, count)| (&*self.words[*id as usize], *count)).collect())
+    }
+}
+
+/// Identifies the chain `tokens` make at `order`, for `save_cache`.
+pub fn corpus_hash(tokens: &[String], order: usize) -> u32 {
+    fnv1a(&format!("{}\n{}", order.max(1), tokens.join(" ")))
+}
+
+/// Writes `chain` to `path` with the `corpus_hash` it was built from,
+/// replacing what was there. The file is written next to it first, so a
+/// crash halfway leaves the last one.
+pub fn save_cache(chain: &WordChain, corpus_hash: u32, path: &Path) -> Result<(), GameError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // tokens are split on whitespace, so a space never is inside a word
+    let mut contents = format!(
+        "{}\ncorpus {}\nwords {}\n",
+        CACHE_HEADER,
+        corpus_hash,
+        chain.words.join(" ")
+    );
+    let ids = |ids: &[u32]| ids.iter().map(u32::to_string).collect::<Vec<_>>().join(" ");
+    for (key, count) in &chain.starts {
+        contents += &format!("start {} {}\n", count, ids(key));
+    }
+    for (key, options) in &chain.followers {
+        let options: Vec<String> = options
+            .iter()
+            .map(|(word, count)| format!("{}:{}", word, count))
+            .collect();
+        contents += &format!("key {} {}\n", ids(key), options.join(" "));
+    }
+
+    let partial = path.with_extension("partial");
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path)?;
+
+    Ok(())
+}
+
+/// Reads the chain `save_cache` wrote to `path`. `None` if there's none, or
+/// if it was built from another corpus or order than `corpus_hash` says and
+/// has to be built again.
+pub fn load_cache(path: &Path, corpus_hash: u32) -> Result<Option<WordChain>, GameError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let invalid = || GameError::from(format!("Saved markov chain {} is damaged", path.display()));
+
+    let mut lines = contents.lines();
+    if lines.next() != Some(CACHE_HEADER) {
+        return Err(invalid());
+    }
+    let hash = lines.next().and_then(|line| line.strip_prefix("corpus ")).ok_or_else(invalid)?;
+    if hash.parse::<u32>().map_err(|_| invalid())? != corpus_hash {
+        return Ok(None);
+    }
+    let words: Vec<Box<str>> = match lines.next().and_then(|line| line.strip_prefix("words")) {
+        Some(words) => words.split_whitespace().map(Box::from).collect(),
+        None => return Err(invalid()),
+    };
+
+    let id = |id: &str| id.parse::<u32>().ok().filter(|id| (*id as usize) < words.len());
+    let mut chain = WordChain { words: Vec::new(), followers: HashMap::new(), starts: Vec::new() };
+    for line in lines {
+        let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+        let mut values = value.split(' ');
+        match field {
+            "start" => {
+                let count = values.next().and_then(|count| count.parse().ok()).ok_or_else(invalid)?;
+                let key: Box<[u32]> = values.map(id).collect::<Option<_>>().ok_or_else(invalid)?;
+                chain.starts.push((key, count));
+            }
+            "key" => {
+                let (mut key, mut options) = (Vec::new(), Vec::new());
+                for value in values {
+                    match value.split_once(':') {
+                        Some((word, count)) => {
+                            let count = count.parse().map_err(|_| invalid())?;
+                            options.push((id(word).ok_or_else(invalid)?, count));
+                        }
+                        None if options.is_empty() => key.push(id(value).ok_or_else(invalid)?),
+                        None => return Err(invalid()),
+                    }
+                }
+                chain.followers.insert(key.into(), options);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    let order = chain.followers.keys().next().map_or(0, |key| key.len());
+    let well_formed = |key: &[u32]| key.len() == order && order > 0;
+    if !chain.followers.iter().all(|(key, options)| well_formed(key) && !options.is_empty())
+        || !chain.starts.iter().all(|(key, count)| well_formed(key) && *count > 0)
+        || chain.starts.is_empty()
+    {
+        return Err(invalid());
+    }
+    chain.words = words;
+    Ok(Some(chain))
+}
+
+impl Chain for WordChain {
+    type Word = u32;
+
+    fn is_empty(&self) -> bool {
+        self.followers.is_empty()
+    }
+
+    fn start<R: Rng>(&self, rng: &mut R) -> Vec<u32> {
+        // the first key always starts a sentence, so there's one if the
+        // chain isn't empty
+        pick_weighted(&self.starts, rng).to_vec()
+    }
+
+    fn find(&self, words: &[&str]) -> Option<Vec<u32>> {
+        let key: Vec<u32> = words
+            .iter()
+            .map(|word| self.words.iter().position(|known| &**known == *word).map(|id| id as u32))
+            .collect::<Option<_>>()?;
+        self.followers.contains_key(&key[..]).then_some(key)
+    }
+
+    fn follow<R: Rng>(&self, key: &[u32], rng: &mut R) -> Option<u32> {
+        let options = self.followers.get(key)?;
+        Some(*pick_weighted(options, rng))
+    }
 
-        let Some(options) = cache.get(&key) else {
-           return output;
+    fn resolve(&self, word: u32) -> String {
+        self.words[word as usize].to_string()
+    }
+}
+
+/// About `num_words` words generated from `cache`, see
+/// `take_to_sentence_end`. They begin with `start` when it's a key of the
+/// cache, which it can only be for an order of 2. Otherwise generation
+/// starts from a random key, with a note saying so for the caller to show,
+/// or fails if `require_start` is set. Dead ends are skipped by going on
+/// from another random key, a few times at most, so there are fewer words
+/// only when the corpus keeps dead ending.
+pub fn generate_text(
+    cache: HashMap<String, Vec<String>>,
+    num_words: usize,
+    start: Option<(String, String)>,
+    require_start: bool,
+) -> Result<(Vec<String>, Option<String>), GameError> {
+    let mut words = MarkovWords::new(cache, rand::thread_rng())?.restarting(MAX_RESTARTS);
+    let mut note = None;
+    if let Some((first, second)) = start {
+        if !words.start_from(&[&first, &second]) {
+            let msg = format!("'{} {}' is not in the corpus", first, second);
+            if require_start {
+                return Err(GameError::from(msg));
+            }
+            note = Some(format!("{}, starting from a random key", msg));
+        }
+    }
+    Ok((take_to_sentence_end(&mut words, num_words), note))
+}
+
+/// Same as `generate_text`, but two calls with the same cache and seed always
+/// produce the same words. Isaac64 is used directly rather than `StdRng` so
+/// the output doesn't depend on the platform's word size.
+pub fn generate_text_seeded(
+    cache: HashMap<String, Vec<String>>,
+    num_words: usize,
+    seed: u64,
+) -> Result<Vec<String>, GameError> {
+    let mut words =
+        MarkovWords::new(cache, Isaac64Rng::from_seed(&[seed][..]))?.restarting(MAX_RESTARTS);
+    Ok(take_to_sentence_end(&mut words, num_words))
+}
+
+/// Words generated from a markov chain one at a time, so a test can take as
+/// many as it needs. Starts at the beginning of a sentence when the chain
+/// knows any. Stops when the chain reaches words that were never followed by
+/// anything, unless made `endless` or `restarting`. The chain can be shared,
+/// so it's built only once for many tests.
+pub struct MarkovWords<R: Rng, C: Chain = HashMap<String, Vec<String>>> {
+    cache: Rc<C>,
+    // the last `order` words, which form the key of the next lookup
+    window: VecDeque<C::Word>,
+    rng: R,
+    // dead ends left to start again from a random key at
+    restarts_left: usize,
+}
+
+impl<R: Rng, C: Chain> MarkovWords<R, C> {
+    pub fn new<T>(cache: T, rng: R) -> Result<Self, GameError>
+        where
+        T: Into<Rc<C>>,
+    {
+        let cache = cache.into();
+        if cache.is_empty() {
+            return Err(GameError::from(String::from(
+                "Corpus is too short to generate text",
+            )));
+        }
+
+        let mut words = MarkovWords {
+            cache,
+            window: VecDeque::new(),
+            rng,
+            restarts_left: 0,
         };
-        
+        words.jump_to_random_key();
+
+        Ok(words)
+    }
 
-        let new_word_idx = rng.gen_range(0, options.len());
-        let new_word = options[new_word_idx].clone();
+    /// Starts again from a random key at a dead end instead of stopping.
+    pub fn endless(mut self) -> Self {
+        self.restarts_left = usize::MAX;
+        self
+    }
 
-        output.push(first_word);
+    /// Starts again from a random key at up to `times` dead ends, then
+    /// stops at the next one.
+    pub fn restarting(mut self, times: usize) -> Self {
+        self.restarts_left = times;
+        self
+    }
 
-        first_word = second_word;
-        second_word = new_word;
+    /// Goes on from the key made of `words` instead, returning whether the
+    /// chain has it. Nothing changes when it doesn't.
+    pub fn start_from(&mut self, words: &[&str]) -> bool {
+        match self.cache.find(words) {
+            Some(key) => {
+                self.window = key.into();
+                true
+            }
+            None => false,
+        }
     }
 
-    output
+    fn jump_to_random_key(&mut self) {
+        self.window = self.cache.start(&mut self.rng).into();
+    }
+}
+
+impl<R: Rng, C: Chain> Iterator for MarkovWords<R, C> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let key = self.window.make_contiguous();
+
+            let Some(new_word) = self.cache.follow(key, &mut self.rng) else {
+                if self.restarts_left == 0 {
+                    return None;
+                }
+                self.restarts_left -= 1;
+                // every key was followed by something, so this can't dead
+                // end again straight away
+                self.jump_to_random_key();
+                continue;
+            };
+
+            let word = self.window.pop_front();
+            self.window.push_back(new_word);
+            return word.map(|word| self.cache.resolve(word));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -74,7 +634,7 @@ mod test {
     #[test]
     fn test_construct_markov() {
         let words = vec!("one".to_string(), "two".to_string(), "three".to_string(), "one".to_string(), "two".to_string(), "four".to_string(), "five".to_string(), "".to_string(), "".to_string());
-        let markov = create_cache(words);
+        let markov = create_cache(words, 2);
 
         assert!(markov.contains_key("one two"));
         assert!(markov.contains_key("two three"));
@@ -83,4 +643,341 @@ mod test {
         assert!(markov.contains_key("four five"));
         //assert_eq!(markov.len(), 4);
     }
+
+    #[test]
+    fn test_short_corpus_gives_empty_cache() {
+        for len in 0..3 {
+            let words = vec!["word".to_string(); len];
+            let markov = create_cache(words, 2);
+
+            assert!(markov.is_empty(), "{} tokens should give an empty cache", len);
+            assert!(generate_text(markov, 10, None, false).is_err());
+        }
+    }
+
+    #[test]
+    fn test_higher_order_reproduces_corpus() {
+        let corpus = "the cat sat on the mat and the dog ran to the rug";
+        let words: Vec<String> = corpus.split(' ').map(String::from).collect();
+
+        // "the" is followed by different words
+        let bigram = create_cache(words.clone(), 1);
+        assert!(bigram.values().any(|options| options.len() > 1));
+
+        // three words of context are never ambiguous in this corpus
+        let fourgram = create_cache(words, 3);
+        assert!(fourgram.values().all(|options| options.len() == 1));
+
+        let words = MarkovWords::new(fourgram, Isaac64Rng::from_seed(&[7][..])).unwrap();
+        let text = words.collect::<Vec<_>>().join(" ");
+        assert!(!text.is_empty());
+        assert!(corpus.contains(&text), "'{}' is not verbatim", text);
+    }
+
+    #[test]
+    fn test_same_seed_same_text() {
+        let words: Vec<String> = include_str!("./input.txt")
+            .split_whitespace()
+            .take(2000)
+            .map(String::from)
+            .collect();
+        let cache = create_cache(words, 2);
+
+        let first = generate_text_seeded(cache.clone(), 30, 42).unwrap();
+        let second = generate_text_seeded(cache.clone(), 30, 42).unwrap();
+        let other = generate_text_seeded(cache, 30, 43).unwrap();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_text_begins_with_a_known_start() {
+        let words: Vec<String> = include_str!("./input.txt")
+            .split_whitespace()
+            .take(2000)
+            .map(String::from)
+            .collect();
+        let cache = create_cache(words.clone(), 2);
+        let start = Some((String::from("the"), String::from("king")));
+        assert!(cache.contains_key("the king"));
+
+        for require_start in [false, true] {
+            let (text, note) =
+                generate_text(cache.clone(), 20, start.clone(), require_start).unwrap();
+            assert_eq!(text[..2], ["the", "king"]);
+            assert_eq!(note, None);
+        }
+
+        let chain = WordChain::new(words, 2);
+        let mut generated = MarkovWords::new(chain, Isaac64Rng::from_seed(&[1][..])).unwrap();
+        assert!(generated.start_from(&["the", "king"]));
+        assert_eq!(generated.take(2).collect::<Vec<_>>(), ["the", "king"]);
+    }
+
+    #[test]
+    fn test_unknown_start_is_random_or_an_error() {
+        let words: Vec<String> = include_str!("./input.txt")
+            .split_whitespace()
+            .take(2000)
+            .map(String::from)
+            .collect();
+        let cache = create_cache(words.clone(), 2);
+        let start = Some((String::from("quantum"), String::from("physics")));
+
+        let (text, note) = generate_text(cache.clone(), 20, start.clone(), false).unwrap();
+        assert!(text.len() >= 20);
+        assert_eq!(
+            note.as_deref(),
+            Some("'quantum physics' is not in the corpus, starting from a random key")
+        );
+        let error = generate_text(cache, 20, start, true).unwrap_err();
+        assert!(error.msg.contains("'quantum physics'"), "{}", error.msg);
+
+        let chain = WordChain::new(words.clone(), 2);
+        let mut generated = MarkovWords::new(chain, Isaac64Rng::from_seed(&[1][..])).unwrap();
+        assert!(!generated.start_from(&["quantum", "physics"]));
+        // the key listing sentence starts isn't a start itself
+        let mut generated =
+            MarkovWords::new(create_cache(words, 2), Isaac64Rng::from_seed(&[1][..])).unwrap();
+        assert!(!generated.start_from(&[SENTENCE_START]));
+    }
+
+    #[test]
+    fn test_counted_cache_stores_each_follower_once() {
+        let words: Vec<String> = "a b a b a c a b".split(' ').map(String::from).collect();
+        let cache = create_counted_cache(words, 1);
+
+        assert_eq!(cache["a"], vec![("b".to_string(), 3), ("c".to_string(), 1)]);
+        assert_eq!(cache["b"], vec![("a".to_string(), 2)]);
+        assert_eq!(cache["c"], vec![("a".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_counted_and_repeated_pick_alike() {
+        let words: Vec<String> = include_str!("./input.txt")
+            .split_whitespace()
+            .take(2000)
+            .map(String::from)
+            .collect();
+        let repeated = create_cache(words.clone(), 1);
+        let counted = create_counted_cache(words, 1);
+
+        // the most common key has the most varied followers
+        let (key, options) = repeated.iter().max_by_key(|(_, options)| options.len()).unwrap();
+        let mut rng = Isaac64Rng::from_seed(&[3][..]);
+        let draws = 20000;
+        let mut from_repeated: HashMap<&str, usize> = HashMap::new();
+        let mut from_counted: HashMap<&str, usize> = HashMap::new();
+        for _ in 0..draws {
+            *from_repeated.entry(options.pick(&mut rng)).or_default() += 1;
+            *from_counted.entry(counted[key].pick(&mut rng)).or_default() += 1;
+        }
+
+        for (word, count) in &counted[key] {
+            let expected = *count as f64 / options.len() as f64;
+            for picked in [&from_repeated, &from_counted] {
+                let share = *picked.get(word.as_str()).unwrap_or(&0) as f64 / draws as f64;
+                assert!((share - expected).abs() < 0.02, "'{}': {} vs {}", word, share, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn saved_chains_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!("typeracer-chain-{}", std::process::id()))
+            .join("chain");
+        let words: Vec<String> = include_str!("./input.txt")
+            .split_whitespace()
+            .take(2000)
+            .map(String::from)
+            .collect();
+        let hash = corpus_hash(&words, 2);
+        assert_eq!(load_cache(&path, hash).unwrap().map(|chain| chain.len()), None);
+
+        let chain = Rc::new(WordChain::new(words.clone(), 2));
+        save_cache(&chain, hash, &path).unwrap();
+        let loaded = Rc::new(load_cache(&path, hash).unwrap().expect("saved"));
+        assert_eq!(loaded.stats(), chain.stats());
+        assert_eq!(loaded.vocabulary_len(), chain.vocabulary_len());
+        for seed in 0..3 {
+            let built = MarkovWords::<_, WordChain>::new(
+                Rc::clone(&chain),
+                Isaac64Rng::from_seed(&[seed][..]),
+            )
+            .unwrap();
+            let read = MarkovWords::<_, WordChain>::new(
+                Rc::clone(&loaded),
+                Isaac64Rng::from_seed(&[seed][..]),
+            )
+            .unwrap();
+            assert!(built.take(200).eq(read.take(200)), "seed {}", seed);
+        }
+
+        // another corpus or order needs the chain built again
+        assert!(load_cache(&path, corpus_hash(&words[1..], 2)).unwrap().is_none());
+        assert!(load_cache(&path, corpus_hash(&words, 3)).unwrap().is_none());
+
+        let contents = fs::read_to_string(&path).unwrap();
+        for damaged in [
+            String::new(),
+            contents.replace(":", " "),
+            contents.replacen("start ", "start x", 1),
+        ] {
+            fs::write(&path, damaged).unwrap();
+            assert!(load_cache(&path, hash).is_err());
+        }
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn cache_stats_measure_the_branching() {
+        let words: Vec<String> = "a b c a b d a b c e".split(' ').map(String::from).collect();
+        let stats = cache_stats(&create_counted_cache(words.clone(), 2));
+
+        // "a b" goes on to c or d and "b c" to a or e, the rest to one word
+        assert_eq!(stats.keys, 5);
+        assert_eq!(stats.avg_branching, 7.0 / 5.0);
+        // nothing came after "c e"
+        assert_eq!(stats.dead_ends, 1);
+        assert_eq!(WordChain::new(words, 2).stats(), stats);
+
+        let empty = cache_stats(&HashMap::new());
+        assert_eq!((empty.keys, empty.avg_branching, empty.dead_ends), (0, 0.0, 0));
+    }
+
+    #[test]
+    fn test_word_chain_matches_the_counted_cache() {
+        let words: Vec<String> = include_str!("./input.txt")
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        let counted = create_counted_cache(words.clone(), 2);
+        let chain = WordChain::new(words, 2);
+
+        assert_eq!(chain.len(), counted.len() - 1);
+        assert!(chain.vocabulary_len() < chain.len());
+        let (key, options) = counted
+            .iter()
+            .find(|(key, _)| key.as_str() != SENTENCE_START)
+            .unwrap();
+        let options: Vec<(&str, u32)> = options
+            .iter()
+            .map(|(word, count)| (word.as_str(), *count))
+            .collect();
+        assert_eq!(chain.followers(key), Some(options));
+        assert_eq!(chain.followers("not-a-word here"), None);
+
+        let chain = Rc::new(chain);
+        for seed in 0..5 {
+            let from_counted =
+                MarkovWords::new(counted.clone(), Isaac64Rng::from_seed(&[seed][..])).unwrap();
+            let from_chain = MarkovWords::<_, WordChain>::new(
+                Rc::clone(&chain),
+                Isaac64Rng::from_seed(&[seed][..]),
+            )
+            .unwrap();
+            assert!(
+                from_counted.take(200).eq(from_chain.take(200)),
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn test_word_chain_of_a_short_corpus_is_empty() {
+        let words = vec!["word".to_string(); 2];
+        let chain = WordChain::new(words, 2);
+
+        assert!(chain.is_empty());
+        assert!(MarkovWords::new(chain, rand::thread_rng()).is_err());
+    }
+
+    #[test]
+    fn test_sentence_starts_are_collected() {
+        let corpus = "Who is there? Nay, answer me. Stand and unfold yourself.";
+        let words: Vec<String> = corpus.split(' ').map(String::from).collect();
+        let cache = create_counted_cache(words, 2);
+
+        let starts: Vec<&str> = cache[SENTENCE_START].iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(starts, vec!["Who is", "Nay, answer", "Stand and"]);
+    }
+
+    #[test]
+    fn test_generated_text_reads_as_sentences() {
+        let words: Vec<String> = include_str!("./input.txt")
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        let cache = create_counted_cache(words, 2);
+
+        for seed in 0..20 {
+            let mut generated =
+                MarkovWords::new(cache.clone(), Isaac64Rng::from_seed(&[seed][..])).unwrap();
+            let first = generated.next().unwrap();
+            assert!(first.starts_with(|c: char| !c.is_lowercase()), "starts with '{}'", first);
+
+            let text = take_to_sentence_end(&mut generated, 20);
+            assert!(text.len() >= 20 && text.len() <= 20 + MAX_SENTENCE_OVERRUN);
+        }
+    }
+
+    #[test]
+    fn test_take_to_sentence_end() {
+        let words = |text: &str| text.split(' ').map(String::from).collect::<Vec<_>>();
+
+        let mut stream = words("one two three. four five six seven").into_iter();
+        assert_eq!(take_to_sentence_end(&mut stream, 2), words("one two three."));
+
+        // no sentence ends soon enough, its words are left out
+        let mut stream = words("one two").into_iter().chain(std::iter::repeat(String::from("on")));
+        assert_eq!(take_to_sentence_end(&mut stream, 2), words("one two"));
+    }
+
+    #[test]
+    fn test_endless_words_never_run_dry() {
+        let corpus = "the cat sat on the mat and the dog ran to the rug";
+        let words: Vec<String> = corpus.split(' ').map(String::from).collect();
+        let cache = create_cache(words, 3);
+
+        // "to the rug" is a dead end
+        let stopping = MarkovWords::new(cache.clone(), Isaac64Rng::from_seed(&[7][..])).unwrap();
+        let stopped = stopping.count();
+        assert!(stopped < 100);
+
+        let endless = MarkovWords::new(cache.clone(), Isaac64Rng::from_seed(&[7][..]))
+            .unwrap()
+            .endless();
+        assert_eq!(endless.take(100).count(), 100);
+
+        let restarting = MarkovWords::new(cache, Isaac64Rng::from_seed(&[7][..]))
+            .unwrap()
+            .restarting(2);
+        let restarted = restarting.count();
+        assert!(
+            restarted > stopped && restarted < 100,
+            "{} after restarting, {} without",
+            restarted,
+            stopped
+        );
+    }
+
+    #[test]
+    fn test_generated_text_goes_on_past_dead_ends() {
+        // every sentence dead ends after a few words
+        let corpus = "one fish swam. two birds flew. red cats sat. blue dogs ran.";
+        let words: Vec<String> = corpus.split(' ').map(String::from).collect();
+
+        for seed in 0..10 {
+            let text = generate_text_seeded(create_cache(words.clone(), 2), 30, seed).unwrap();
+            assert!(text.len() >= 30, "seed {}: {:?}", seed, text);
+        }
+
+        // a corpus can't go on past the restarts it's allowed
+        let words: Vec<String> = "a b c".split(' ').map(String::from).collect();
+        let text = generate_text_seeded(create_cache(words, 2), 1000, 1).unwrap();
+        assert!(!text.is_empty() && text.len() <= MAX_RESTARTS + 1, "{:?}", text);
+    }
 }
@@ -0,0 +1,184 @@
+use std::io::Write;
+use std::time::Duration;
+
+use termion::event::Key;
+
+use crate::config::GameConfig;
+use crate::keys::KeyReader;
+use crate::text::Text;
+use crate::tui::GameTui;
+use crate::GameError;
+
+// the lengths to choose from, 30 words being the default test
+const WORD_COUNTS: [usize; 4] = [10, 30, 50, 100];
+const TIME_LIMITS: [u64; 4] = [15, 30, 60, 120];
+
+/// Lets the user choose between a test of some words and a timed one with
+/// the arrow keys, up and down switching between the two and left and right
+/// changing the length, then enter. Returns `config` set up for that test,
+/// starting from the choice closest to it. `None` means they quit with
+/// ctrl-c instead, or escape with `GameConfig::esc_quits`.
+pub fn pick_length<W: Write>(
+    tui: &mut GameTui<W>,
+    keys: &KeyReader,
+    config: &GameConfig,
+) -> Result<Option<GameConfig>, GameError> {
+    let mut timed = config.time_limit.is_some();
+    let mut words = closest(&WORD_COUNTS.map(|count| count as u64), config.num_words as u64);
+    let mut time = closest(&TIME_LIMITS, config.time_limit.map_or(30, |limit| limit.as_secs()));
+
+    loop {
+        show_menu(tui, timed, words, time, config)?;
+
+        let (chosen, choices) = if timed {
+            (&mut time, TIME_LIMITS.len())
+        } else {
+            (&mut words, WORD_COUNTS.len())
+        };
+        match keys.next_key()? {
+            Key::Up | Key::Down | Key::Char('k') | Key::Char('j') | Key::Char('\t') => {
+                timed = !timed
+            }
+            Key::Left | Key::Char('h') => *chosen = chosen.saturating_sub(1),
+            Key::Right | Key::Char('l') => *chosen = (*chosen + 1).min(choices - 1),
+            Key::Char('\n') if timed => {
+                // the words shown at first, more come as they're typed
+                let time_limit = Some(Duration::from_secs(TIME_LIMITS[time]));
+                return Ok(Some(GameConfig { time_limit, ..config.clone() }));
+            }
+            Key::Char('\n') => {
+                return Ok(Some(GameConfig {
+                    num_words: WORD_COUNTS[words],
+                    time_limit: None,
+                    ..config.clone()
+                }));
+            }
+            key if crate::quits(key, config.esc_quits) => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+// index of the choice nearest to `value`, the smaller one on a tie
+fn closest(choices: &[u64], value: u64) -> usize {
+    (0..choices.len()).min_by_key(|i| choices[*i].abs_diff(value)).unwrap_or(0)
+}
+
+fn show_menu<W: Write>(
+    tui: &mut GameTui<W>,
+    timed: bool,
+    words: usize,
+    time: usize,
+    config: &GameConfig,
+) -> Result<(), GameError> {
+    let theme = &config.theme;
+    let words_row = WORD_COUNTS.iter().map(|count| count.to_string());
+    let time_row = TIME_LIMITS.iter().map(|secs| format!("{}s", secs));
+    let lines = vec![
+        vec![Text::from("Choose a test").with_bold()],
+        vec![Text::from("")],
+        menu_row("words", words_row, words, !timed),
+        menu_row("time", time_row, time, timed),
+    ];
+
+    tui.reset_screen()?;
+    tui.display_lines(&lines)?;
+    tui.display_lines_bottom(&[[
+        Text::from("↑/↓").with_color(theme.hint),
+        Text::from(" words or time, ").with_faint(),
+        Text::from("←/→").with_color(theme.hint),
+        Text::from(" to change, ").with_faint(),
+        Text::from("enter").with_color(theme.hint),
+        Text::from(" to start, ").with_faint(),
+        Text::from(crate::quit_keys(config.esc_quits)).with_color(theme.hint),
+        Text::from(" to quit").with_faint(),
+    ]])?;
+    tui.hide_cursor()?;
+
+    Ok(())
+}
+
+// a label and its choices, padded so both rows line up when centered. Only
+// the row in use shows which of its choices is picked
+fn menu_row<I: Iterator<Item = String>>(
+    label: &str,
+    choices: I,
+    chosen: usize,
+    active: bool,
+) -> Vec<Text> {
+    let label = Text::from(format!("{:<6}", label));
+    let mut row = vec![if active { label } else { label.with_faint() }];
+    for (i, choice) in choices.enumerate() {
+        let choice = Text::from(format!(" {:>4} ", choice));
+        row.push(match (active, i == chosen) {
+            (true, true) => choice.with_invert(),
+            (true, false) => choice,
+            (false, true) => choice.with_underline().with_faint(),
+            (false, false) => choice.with_faint(),
+        });
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picked(keys: Vec<Key>, config: &GameConfig) -> Result<Option<GameConfig>, GameError> {
+        let mut tui = GameTui::headless(Vec::new(), (100, 30));
+        pick_length(&mut tui, &KeyReader::from_keys(keys), config)
+    }
+
+    fn length(config: Option<GameConfig>) -> Option<(usize, Option<u64>)> {
+        config.map(|config| (config.num_words, config.time_limit.map(|limit| limit.as_secs())))
+    }
+
+    #[test]
+    fn arrows_choose_words_or_time() {
+        let config = GameConfig::default();
+        let enter = Key::Char('\n');
+
+        assert_eq!(length(picked(vec![enter], &config).unwrap()), Some((30, None)));
+        let keys = vec![Key::Right, Key::Right, Key::Right, Key::Left, enter];
+        assert_eq!(length(picked(keys, &config).unwrap()), Some((50, None)));
+
+        // the word count stays for the words shown at first
+        let keys = vec![Key::Right, Key::Down, Key::Left, Key::Left, enter];
+        assert_eq!(length(picked(keys, &config).unwrap()), Some((30, Some(15))));
+        let keys = vec![Key::Down, Key::Right, Key::Right, Key::Right, Key::Up, Key::Up, enter];
+        assert_eq!(length(picked(keys, &config).unwrap()), Some((30, Some(120))));
+
+        assert_eq!(length(picked(vec![Key::Down, Key::Esc], &config).unwrap()), None);
+        assert_eq!(length(picked(vec![Key::Ctrl('c')], &config).unwrap()), None);
+        assert!(picked(vec![Key::Down], &config).is_err());
+
+        let config = GameConfig { esc_quits: false, ..config };
+        assert_eq!(length(picked(vec![Key::Esc, enter], &config).unwrap()), Some((30, None)));
+        assert_eq!(length(picked(vec![Key::Ctrl('c')], &config).unwrap()), None);
+    }
+
+    #[test]
+    fn menu_starts_near_the_config() {
+        let config = GameConfig { num_words: 60, time_limit: None, ..GameConfig::default() };
+        assert_eq!(length(picked(vec![Key::Char('\n')], &config).unwrap()), Some((50, None)));
+
+        let config = GameConfig {
+            time_limit: Some(Duration::from_secs(45)),
+            ..GameConfig::default()
+        };
+        let keys = vec![Key::Right, Key::Char('\n')];
+        assert_eq!(length(picked(keys, &config).unwrap()), Some((30, Some(60))));
+    }
+
+    #[test]
+    fn only_the_row_in_use_is_inverted() {
+        let mut tui = GameTui::headless(Vec::new(), (100, 30));
+        show_menu(&mut tui, true, 1, 2, &GameConfig::default()).unwrap();
+        let drawn = String::from_utf8_lossy(tui.writer()).into_owned();
+
+        assert!(drawn.contains(&Text::from("  60s ").with_invert().to_string()));
+        assert!(!drawn.contains(&Text::from("   30 ").with_invert().to_string()));
+        assert!(drawn.contains(&Text::from("   30 ").with_underline().with_faint().to_string()));
+        assert!(drawn.contains("ctrl-c/esc"));
+    }
+}
@@ -0,0 +1,153 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use termion::event::Key;
+
+use crate::config::GameConfig;
+use crate::keys::KeyReader;
+use crate::text::Text;
+use crate::tui::GameTui;
+use crate::GameError;
+
+// files listed at a time, the list scrolls to keep the chosen one in view
+const MAX_SHOWN: usize = 10;
+
+/// The files in `dir` a corpus can be picked from, sorted by name. Hidden
+/// files and directories are left out.
+pub fn corpus_files(dir: &Path) -> Result<Vec<PathBuf>, GameError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|error| GameError::from(format!("Could not read {}: {}", dir.display(), error)))?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if !hidden && entry.file_type()?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Lets the user choose one of `files` with the arrow keys and enter.
+/// `None` means they quit with ctrl-c instead, or escape with
+/// `GameConfig::esc_quits`.
+pub fn pick_file<W: Write>(
+    tui: &mut GameTui<W>,
+    keys: &KeyReader,
+    files: &[PathBuf],
+    config: &GameConfig,
+) -> Result<Option<PathBuf>, GameError> {
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    let mut chosen = 0;
+    loop {
+        show_menu(tui, files, chosen, config)?;
+
+        match keys.next_key()? {
+            Key::Up | Key::Char('k') => chosen = chosen.saturating_sub(1),
+            Key::Down | Key::Char('j') => chosen = (chosen + 1).min(files.len() - 1),
+            Key::Char('\n') => return Ok(Some(files[chosen].clone())),
+            key if crate::quits(key, config.esc_quits) => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+fn show_menu<W: Write>(
+    tui: &mut GameTui<W>,
+    files: &[PathBuf],
+    chosen: usize,
+    config: &GameConfig,
+) -> Result<(), GameError> {
+    let theme = &config.theme;
+    let first = chosen.saturating_sub(MAX_SHOWN - 1);
+
+    let mut lines = vec![[Text::from("Pick a corpus").with_bold()], [Text::from("")]];
+    for (i, file) in files.iter().enumerate().skip(first).take(MAX_SHOWN) {
+        let name = file.file_name().unwrap_or(file.as_os_str()).to_string_lossy();
+        let line = Text::from(name.into_owned());
+        lines.push([if i == chosen { line.with_invert() } else { line.with_faint() }]);
+    }
+
+    tui.reset_screen()?;
+    tui.display_lines(&lines)?;
+    tui.display_lines_bottom(&[[
+        Text::from("↑/↓").with_color(theme.hint),
+        Text::from(" to choose, ").with_faint(),
+        Text::from("enter").with_color(theme.hint),
+        Text::from(" to start, ").with_faint(),
+        Text::from(crate::quit_keys(config.esc_quits)).with_color(theme.hint),
+        Text::from(" to quit").with_faint(),
+    ]])?;
+    tui.hide_cursor()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picked(keys: Vec<Key>, files: &[PathBuf]) -> Result<Option<PathBuf>, GameError> {
+        picked_with(keys, files, &GameConfig::default())
+    }
+
+    fn picked_with(
+        keys: Vec<Key>,
+        files: &[PathBuf],
+        config: &GameConfig,
+    ) -> Result<Option<PathBuf>, GameError> {
+        let mut tui = GameTui::headless(Vec::new(), (100, 30));
+        pick_file(&mut tui, &KeyReader::from_keys(keys), files, config)
+    }
+
+    #[test]
+    fn arrows_choose_a_file() {
+        let files: Vec<PathBuf> = ["a.txt", "b.txt", "c.txt"].iter().map(PathBuf::from).collect();
+
+        assert_eq!(
+            picked(vec![Key::Char('\n')], &files).unwrap(),
+            Some(files[0].clone())
+        );
+        let keys = vec![
+            Key::Up,
+            Key::Down,
+            Key::Down,
+            Key::Down,
+            Key::Down,
+            Key::Up,
+            Key::Char('\n'),
+        ];
+        assert_eq!(picked(keys, &files).unwrap(), Some(files[1].clone()));
+
+        assert_eq!(picked(vec![Key::Down, Key::Ctrl('c')], &files).unwrap(), None);
+        assert_eq!(picked(vec![Key::Esc], &files).unwrap(), None);
+        let config = GameConfig { esc_quits: false, ..GameConfig::default() };
+        let keys = vec![Key::Esc, Key::Char('\n')];
+        assert_eq!(picked_with(keys, &files, &config).unwrap(), Some(files[0].clone()));
+        assert_eq!(picked(vec![], &[]).unwrap(), None);
+        assert!(picked(vec![Key::Down], &files).is_err());
+    }
+
+    #[test]
+    fn corpus_files_are_sorted_files() {
+        let dir =
+            std::env::temp_dir().join(format!("typeracer-test-corpora-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        for name in ["b.txt", "a.txt", ".hidden"] {
+            fs::write(dir.join(name), "some words").unwrap();
+        }
+
+        let files = corpus_files(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files.unwrap(), vec![dir.join("a.txt"), dir.join("b.txt")]);
+
+        assert!(corpus_files(&dir).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use rand::Rng;
+
+use crate::markov::{take_to_sentence_end, Chain, MarkovWords};
+use crate::GameError;
+
+/// Somewhere the words of a test come from, for texts the game doesn't know
+/// how to make itself. `Game::set_text_provider` plays them.
+pub trait TextProvider {
+    /// The words of the next test, about `num_words` of them. A timed test
+    /// asks again for more while it goes on, until there are none or it
+    /// fails.
+    fn next_test(&mut self, num_words: usize) -> Result<Vec<String>, GameError>;
+}
+
+impl<R: Rng, C: Chain> TextProvider for MarkovWords<R, C> {
+    // the sentence the last word is in is finished, like in the markov mode
+    fn next_test(&mut self, num_words: usize) -> Result<Vec<String>, GameError> {
+        Ok(take_to_sentence_end(self, num_words))
+    }
+}
+
+/// A random quote for every test, however many words it has.
+pub struct QuoteProvider<R: Rng> {
+    quotes: Vec<String>,
+    rng: R,
+}
+
+impl<R: Rng> QuoteProvider<R> {
+    pub fn new(quotes: Vec<String>, rng: R) -> Result<Self, GameError> {
+        if quotes.is_empty() {
+            return Err(GameError::from(String::from("There are no quotes to pick from")));
+        }
+        Ok(QuoteProvider { quotes, rng })
+    }
+}
+
+impl<R: Rng> TextProvider for QuoteProvider<R> {
+    fn next_test(&mut self, _num_words: usize) -> Result<Vec<String>, GameError> {
+        Ok(pick_quote(&self.quotes, &mut self.rng))
+    }
+}
+
+/// The words of a quote picked from `quotes`, which can't be empty.
+pub fn pick_quote<R: Rng>(quotes: &[String], rng: &mut R) -> Vec<String> {
+    let quote_idx = rng.gen_range(0, quotes.len());
+    quotes[quote_idx].split_whitespace().map(String::from).collect()
+}
+
+/// The same words for every test, mostly for trying out the game.
+pub struct EchoProvider {
+    words: Vec<String>,
+}
+
+impl EchoProvider {
+    pub fn new(words: Vec<String>) -> Self {
+        EchoProvider { words }
+    }
+}
+
+impl TextProvider for EchoProvider {
+    fn next_test(&mut self, _num_words: usize) -> Result<Vec<String>, GameError> {
+        Ok(self.words.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Isaac64Rng, SeedableRng};
+
+    fn words(text: &str) -> Vec<String> {
+        text.split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn providers_hand_out_tests() {
+        let mut echo = EchoProvider::new(words("one two"));
+        assert_eq!(echo.next_test(30).unwrap(), words("one two"));
+        assert_eq!(echo.next_test(1).unwrap(), words("one two"));
+
+        let quotes = vec![String::from("a  quote"), String::from("another one")];
+        let mut from_quotes =
+            QuoteProvider::new(quotes.clone(), Isaac64Rng::from_seed(&[1][..])).unwrap();
+        for _ in 0..5 {
+            let quote = from_quotes.next_test(30).unwrap().join(" ");
+            assert!(quote == "a quote" || quote == "another one", "{}", quote);
+        }
+        assert!(QuoteProvider::new(Vec::new(), Isaac64Rng::from_seed(&[1][..])).is_err());
+
+        let cache = crate::markov::create_cache(words("one two three. four five six."), 1);
+        let mut markov = MarkovWords::new(cache, Isaac64Rng::from_seed(&[1][..])).unwrap();
+        let test = markov.next_test(2).unwrap();
+        assert!(test.len() >= 2);
+    }
+}
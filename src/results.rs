@@ -1,30 +1,829 @@
-use std::time::{Duration, Instant};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-#[derive(Clone)]
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::keyboard::{Finger, KeyboardLayout};
+use crate::GameError;
+
+/// The outcome of one test, returned by `Game::run`. Read it through the
+/// methods, the fields may change.
+#[derive(Debug, Clone, PartialEq)]
 pub struct GameResults {
-    pub total_words: usize,
-    pub total_chars_typed: usize,
-    pub total_chars_in_text: usize,
-    pub total_char_errors: usize,
-    pub final_chars_typed_correctly: usize,
-    pub final_uncorrected_errors: usize,
-    pub started_at: Instant,
-    pub ended_at: Instant,
+    pub(crate) total_words: usize,
+    pub(crate) total_chars_typed: usize,
+    /// Every key that changed the input: typed characters, backspaces and a
+    /// key for every character a deleted word took with it.
+    pub(crate) total_keystrokes: usize,
+    /// Characters reached, i.e. the length of the typed input when the test
+    /// ended.
+    pub(crate) total_chars_in_text: usize,
+    /// Characters in the whole text, reached or not.
+    pub(crate) total_chars_in_target: usize,
+    pub(crate) total_char_errors: usize,
+    pub(crate) final_chars_typed_correctly: usize,
+    pub(crate) final_uncorrected_errors: usize,
+    /// Words of the text typed to their end with no mistake left in them.
+    pub(crate) correct_words: usize,
+    pub(crate) started_at: Instant,
+    pub(crate) ended_at: Instant,
+    /// Time spent paused, which doesn't count towards the test.
+    pub(crate) paused_duration: Duration,
+    pub(crate) time_limit: Option<Duration>,
+    /// When each typed character was pressed, relative to `started_at`.
+    pub(crate) keystrokes: Vec<Duration>,
+    /// Every finished word with the time from finishing the one before it,
+    /// or from the start, until reaching the space after it.
+    pub(crate) word_times: Vec<(String, Duration)>,
+    /// How often each character of the text was mistyped, by the character
+    /// that should have been typed.
+    pub(crate) mistaken_chars: HashMap<char, u32>,
+    /// How often each word of the text was mistyped, a mistake for every
+    /// wrong character in it.
+    pub(crate) mistaken_words: HashMap<String, u32>,
+    /// Most correct keystrokes in a row.
+    pub(crate) longest_streak: usize,
+    /// Wall clock time the test ended, since `Instant`s can't be stored.
+    pub(crate) finished_at: SystemTime,
+}
+
+/// Everything about a test worth keeping once it's over, with wall clock
+/// time instead of `Instant`s so it can be stored and read back. Derives
+/// `Serialize` and `Deserialize` with the `serde` feature.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResultsSummary {
+    /// Seconds since the unix epoch when the test ended.
+    pub timestamp: u64,
+    pub duration: Duration,
+    pub time_limit: Option<Duration>,
+    pub words: usize,
+    pub chars_typed: usize,
+    pub total_keystrokes: usize,
+    pub chars_reached: usize,
+    pub text_len: usize,
+    pub errors: usize,
+    pub uncorrected_errors: usize,
+    pub wpm: f64,
+    pub raw_wpm: f64,
+    pub word_wpm: f64,
+    pub accuracy: f64,
+    pub consistency: f64,
+    pub keystrokes: Vec<Duration>,
+    pub word_times: Vec<(String, Duration)>,
+    pub mistaken_chars: HashMap<char, u32>,
+    pub mistaken_words: HashMap<String, u32>,
+    pub longest_streak: usize,
 }
 
 impl GameResults {
+    /// Time spent typing, from the first keystroke to the end of the test
+    /// without pauses.
     pub fn duration(&self) -> Duration {
-        self.ended_at.duration_since(self.started_at)
+        self.ended_at
+            .duration_since(self.started_at)
+            .saturating_sub(self.paused_duration)
+    }
+
+    /// Words in the text, or typed in a timed test.
+    pub fn words(&self) -> usize {
+        self.total_words
+    }
+
+    /// Every character typed, including mistakes and ones deleted later.
+    pub fn chars_typed(&self) -> usize {
+        self.total_chars_typed
+    }
+
+    /// Every key pressed that changed the input, backspaces included. A
+    /// word deleted at once counts a key for every character of it, so
+    /// this is what typing the test took with one key at a time.
+    pub fn total_keystrokes(&self) -> usize {
+        self.total_keystrokes
+    }
+
+    /// Characters reached, i.e. the length of the typed input when the test
+    /// ended.
+    pub fn chars_reached(&self) -> usize {
+        self.total_chars_in_text
+    }
+
+    /// Characters in the whole text, reached or not.
+    pub fn text_len(&self) -> usize {
+        self.total_chars_in_target
+    }
+
+    /// Every mistyped character, whether it was corrected or not.
+    pub fn errors(&self) -> usize {
+        self.total_char_errors
+    }
+
+    /// Mistakes still in the input when the test ended.
+    pub fn uncorrected_errors(&self) -> usize {
+        self.final_uncorrected_errors
+    }
+
+    /// The time limit of a timed test.
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit
     }
 
+    /// When each typed character was pressed, relative to the first one.
+    pub fn keystrokes(&self) -> &[Duration] {
+        &self.keystrokes
+    }
+
+    /// Most correct keystrokes in a row. Only a mistake breaks a streak,
+    /// deleting doesn't.
+    pub fn longest_streak(&self) -> usize {
+        self.longest_streak
+    }
+
+    /// Every finished word with the time it took, in the order typed.
+    pub fn word_times(&self) -> &[(String, Duration)] {
+        &self.word_times
+    }
+
+    /// Share of keystrokes that were correct: `(chars typed - errors) / chars
+    /// typed`. Only characters that were reached are ever typed, so text left
+    /// over after quitting or running out of time doesn't count against it;
+    /// see `completion` for that. A test with no keystrokes is fully accurate.
     pub fn accuracy(&self) -> f64 {
+        if self.total_chars_typed == 0 {
+            return 1.0;
+        }
         (self.total_chars_typed as isize - self.total_char_errors as isize) as f64
             / self.total_chars_typed as f64
     }
 
+    /// Share of the text that was reached: `chars reached / chars in text`.
+    pub fn completion(&self) -> f64 {
+        if self.total_chars_in_target == 0 {
+            return 1.0;
+        }
+        self.total_chars_in_text as f64 / self.total_chars_in_target as f64
+    }
+
+    /// Net wpm: `(correct chars / 5 - uncorrected errors) / minutes`, never
+    /// below zero.
     pub fn wpm(&self) -> f64 {
         (self.final_chars_typed_correctly as f64 / 5.0 - self.final_uncorrected_errors as f64)
-            .max(0.0) as f64
+            .max(0.0)
             / (self.duration().as_secs_f64() / 60.0)
     }
+
+    /// Words of the text typed to their end without a mistake left in
+    /// them. Mistakes corrected along the way don't matter, nor does the
+    /// space after the word.
+    pub fn correct_words(&self) -> usize {
+        self.correct_words
+    }
+
+    /// Wpm by whole words: `correct words / minutes`, the way some
+    /// competitions score, rather than by characters. A word with any
+    /// mistake left in it doesn't count at all.
+    pub fn word_wpm(&self) -> f64 {
+        self.correct_words as f64 / (self.duration().as_secs_f64() / 60.0)
+    }
+
+    /// Raw wpm: `(chars typed / 5) / minutes`, counting every typed
+    /// character including mistakes that were corrected later. Five
+    /// characters make one word, as on monkeytype.
+    pub fn raw_wpm(&self) -> f64 {
+        self.total_chars_typed as f64 / 5.0 / (self.duration().as_secs_f64() / 60.0)
+    }
+
+    /// How uniform the time between keystrokes was, from 0 to 1. Like
+    /// monkeytype, this maps the coefficient of variation `cv` of the
+    /// intervals to `1 - tanh(cv + cv^3 / 3 + cv^5 / 5)`. Tests with fewer
+    /// than two intervals are perfectly consistent.
+    pub fn consistency(&self) -> f64 {
+        let intervals: Vec<f64> = self
+            .keystrokes
+            .windows(2)
+            .map(|pair| pair[1].saturating_sub(pair[0]).as_secs_f64())
+            .collect();
+        if intervals.len() < 2 {
+            return 1.0;
+        }
+
+        let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+        if mean == 0.0 {
+            return 1.0;
+        }
+        let variance = intervals
+            .iter()
+            .map(|interval| (interval - mean).powi(2))
+            .sum::<f64>()
+            / intervals.len() as f64;
+        let cv = variance.sqrt() / mean;
+
+        1.0 - (cv + cv.powi(3) / 3.0 + cv.powi(5) / 5.0).tanh()
+    }
+
+    /// Raw wpm of every second of the test, from the characters typed in
+    /// it. The last second counts even if the test ended partway through
+    /// it, so a test shorter than a second is a single value.
+    pub fn wpm_series(&self) -> Vec<f64> {
+        let seconds = self.duration().as_secs_f64().ceil() as usize;
+        let last_keystroke = self.keystrokes.last().map_or(0, |at| at.as_secs() as usize + 1);
+        let mut chars = vec![0usize; seconds.max(last_keystroke).max(1)];
+        for at in &self.keystrokes {
+            chars[at.as_secs() as usize] += 1;
+        }
+
+        chars.iter().map(|chars| *chars as f64 / 5.0 * 60.0).collect()
+    }
+
+    /// Share of the tests in `history`, given by their wpm, that were
+    /// slower than this one, from 0 to 1. `None` when there are fewer than
+    /// `MIN_RANKED_HISTORY` tests to compare with.
+    pub fn percentile_against(&self, history: &[f64]) -> Option<f64> {
+        if history.len() < MIN_RANKED_HISTORY {
+            return None;
+        }
+        let wpm = finite_or_zero(self.wpm());
+        let slower = history.iter().filter(|other| **other < wpm).count();
+        Some(slower as f64 / history.len() as f64)
+    }
+
+    /// Up to `count` words that took the longest, slowest first.
+    pub fn slowest_words(&self, count: usize) -> Vec<&(String, Duration)> {
+        let mut words: Vec<&(String, Duration)> = self.word_times.iter().collect();
+        // stable, so equally slow words stay in the order they were typed
+        words.sort_by_key(|(_, time)| Reverse(*time));
+        words.truncate(count);
+        words
+    }
+
+    /// Up to `count` characters mistyped most often with how often, most
+    /// missed first and ties in character order. Empty without mistakes.
+    pub fn top_mistaken_chars(&self, count: usize) -> Vec<(char, u32)> {
+        let mut chars: Vec<(char, u32)> = self
+            .mistaken_chars
+            .iter()
+            .map(|(c, misses)| (*c, *misses))
+            .collect();
+        chars.sort_by_key(|(c, misses)| (Reverse(*misses), *c));
+        chars.truncate(count);
+        chars
+    }
+
+    /// These results without the `Instant`s, see `ResultsSummary`. Rates
+    /// of a test too short to measure are zero.
+    pub fn summary(&self) -> Result<ResultsSummary, GameError> {
+        Ok(ResultsSummary {
+            timestamp: unix_timestamp(self.finished_at)?,
+            duration: self.duration(),
+            time_limit: self.time_limit,
+            words: self.total_words,
+            chars_typed: self.total_chars_typed,
+            total_keystrokes: self.total_keystrokes,
+            chars_reached: self.total_chars_in_text,
+            text_len: self.total_chars_in_target,
+            errors: self.total_char_errors,
+            uncorrected_errors: self.final_uncorrected_errors,
+            wpm: finite_or_zero(self.wpm()),
+            raw_wpm: finite_or_zero(self.raw_wpm()),
+            word_wpm: finite_or_zero(self.word_wpm()),
+            accuracy: self.accuracy(),
+            consistency: self.consistency(),
+            keystrokes: self.keystrokes.clone(),
+            word_times: self.word_times.clone(),
+            mistaken_chars: self.mistaken_chars.clone(),
+            mistaken_words: self.mistaken_words.clone(),
+            longest_streak: self.longest_streak,
+        })
+    }
+
+    /// Mistakes added up by the finger that types each missed character on
+    /// `layout`, most first and ties from left to right. Characters that
+    /// aren't on the layout are left out.
+    pub fn errors_by_finger(&self, layout: KeyboardLayout) -> Vec<(Finger, u32)> {
+        let mut fingers: HashMap<Finger, u32> = HashMap::new();
+        for (c, misses) in &self.mistaken_chars {
+            if let Some((_, finger)) = layout.key(*c) {
+                *fingers.entry(finger).or_insert(0) += misses;
+            }
+        }
+
+        let mut fingers: Vec<(Finger, u32)> = fingers.into_iter().collect();
+        fingers.sort_by_key(|(finger, misses)| (Reverse(*misses), *finger));
+        fingers
+    }
+
+    /// How often each word of the text was mistyped, see `mistaken_chars`.
+    pub fn mistaken_words(&self) -> &HashMap<String, u32> {
+        &self.mistaken_words
+    }
+
+    /// Appends these results as one line of JSON, creating the file and its
+    /// parent directories if needed. The mistyped words come last, and only
+    /// if there were any.
+    pub fn append_to(&self, path: &Path) -> Result<(), GameError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut line = format!(
+            "{{\"timestamp\":{},\"wpm\":{:.2},\"accuracy\":{:.4},\"words\":{},\"duration_ms\":{}",
+            unix_timestamp(self.finished_at)?,
+            finite_or_zero(self.wpm()),
+            finite_or_zero(self.accuracy()),
+            self.words(),
+            self.duration().as_millis(),
+        );
+        if !self.mistaken_words.is_empty() {
+            // sorted, so the same results always make the same line
+            let mut words: Vec<(&String, &u32)> = self.mistaken_words.iter().collect();
+            words.sort();
+            let words: Vec<String> = words
+                .into_iter()
+                .map(|(word, misses)| format!("{}:{}", json_string(word), misses))
+                .collect();
+            line += &format!(",\"mistaken_words\":{{{}}}", words.join(","));
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}}}", line)?;
+
+        Ok(())
+    }
+
+    /// These results as one line of CSV, without the newline. The fields
+    /// are in the order of `CSV_HEADER`: timestamp in seconds, wpm, raw wpm,
+    /// accuracy from 0 to 1, words, mistakes and duration in milliseconds.
+    /// Decimals always use `.`, whatever the locale.
+    pub fn to_csv_row(&self) -> Result<String, GameError> {
+        Ok(format!(
+            "{},{:.2},{:.2},{:.4},{},{},{}",
+            unix_timestamp(self.finished_at)?,
+            finite_or_zero(self.wpm()),
+            finite_or_zero(self.raw_wpm()),
+            finite_or_zero(self.accuracy()),
+            self.words(),
+            self.errors(),
+            self.duration().as_millis(),
+        ))
+    }
+
+    /// Appends `to_csv_row` to a CSV file, starting a new or empty file with
+    /// `CSV_HEADER`.
+    pub fn append_csv_to(&self, path: &Path) -> Result<(), GameError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", CSV_HEADER)?;
+        }
+        writeln!(file, "{}", self.to_csv_row()?)?;
+
+        Ok(())
+    }
+}
+
+/// Fewest tests `GameResults::percentile_against` ranks against, a couple
+/// of tests would make any ranking look extreme.
+pub const MIN_RANKED_HISTORY: usize = 5;
+
+/// Column names of `GameResults::to_csv_row`.
+pub const CSV_HEADER: &str = "timestamp,wpm,raw_wpm,accuracy,words,errors,duration_ms";
+
+fn unix_timestamp(time: SystemTime) -> Result<u64, GameError> {
+    Ok(time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|error| GameError::from(error.to_string()))?
+        .as_secs())
+}
+
+/// Reads the wpm of every test recorded by `GameResults::append_to`. A
+/// missing file is an empty history, and lines that can't be parsed are
+/// skipped.
+pub fn read_history_wpm(path: &Path) -> Result<Vec<f64>, GameError> {
+    let history = match fs::read_to_string(path) {
+        Ok(history) => history,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+
+    Ok(history.lines().filter_map(|line| history_field(line, "wpm")).collect())
+}
+
+/// The words mistyped in the last `recent` tests recorded by
+/// `GameResults::append_to`, with how often, added up. Read like
+/// `read_history_wpm`.
+pub fn read_history_mistaken_words(
+    path: &Path,
+    recent: usize,
+) -> Result<HashMap<String, u32>, GameError> {
+    let history = match fs::read_to_string(path) {
+        Ok(history) => history,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(error) => return Err(error.into()),
+    };
+
+    let lines: Vec<&str> = history.lines().collect();
+    let mut words = HashMap::new();
+    for line in &lines[lines.len().saturating_sub(recent)..] {
+        for (word, misses) in history_words(line).unwrap_or_default() {
+            *words.entry(word).or_insert(0) += misses;
+        }
+    }
+    Ok(words)
+}
+
+// the mistyped words of one line of history, `None` if they can't be read
+fn history_words(line: &str) -> Option<Vec<(String, u32)>> {
+    const KEY: &str = "\"mistaken_words\":{";
+    let Some(start) = line.find(KEY) else {
+        return Some(Vec::new());
+    };
+
+    let mut words = Vec::new();
+    let mut rest = &line[start + KEY.len()..];
+    while !rest.starts_with('}') {
+        let (word, after) = read_json_string(rest.strip_prefix(',').unwrap_or(rest))?;
+        let after = after.strip_prefix(':')?;
+        let end = after.find([',', '}'])?;
+        words.push((word, after[..end].parse().ok()?));
+        rest = &after[end..];
+    }
+    Some(words)
+}
+
+// `value` as a JSON string. Words are printable, only quotes and
+// backslashes need escaping
+fn json_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// the string `json_string` wrote at the start of `text`, and what's after it
+fn read_json_string(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[i + 2..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+// pulls a numeric field out of one line of history
+fn history_field(line: &str, name: &str) -> Option<f64> {
+    let key = format!("\"{}\":", name);
+    let start = line.find(&key)? + key.len();
+    let rest = &line[start..];
+    let end = rest.find([',', '}']).unwrap_or(rest.len());
+    rest[..end].trim().parse().ok()
+}
+
+// keeps the history valid JSON when a test had no keystrokes
+fn finite_or_zero(value: f64) -> f64 {
+    if value.is_finite() { value } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(duration: Duration) -> GameResults {
+        let started_at = Instant::now();
+        GameResults {
+            total_words: 10,
+            total_chars_typed: 50,
+            total_keystrokes: 56,
+            total_chars_in_text: 50,
+            total_chars_in_target: 50,
+            total_char_errors: 5,
+            final_chars_typed_correctly: 50,
+            final_uncorrected_errors: 0,
+            correct_words: 8,
+            started_at,
+            ended_at: started_at + duration,
+            paused_duration: Duration::ZERO,
+            time_limit: None,
+            keystrokes: Vec::new(),
+            word_times: Vec::new(),
+            mistaken_chars: HashMap::new(),
+            mistaken_words: HashMap::new(),
+            longest_streak: 0,
+            finished_at: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn append_to_writes_one_line_per_result() {
+        let dir = std::env::temp_dir().join(format!("typeracer-history-{}", std::process::id()));
+        let path = dir.join("nested").join("history.jsonl");
+
+        let results = results(Duration::from_secs(60));
+        results.append_to(&path).unwrap();
+        results.append_to(&path).unwrap();
+
+        let history = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = history.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("{\"timestamp\":"));
+        assert!(lines[0]
+            .ends_with("\"wpm\":10.00,\"accuracy\":0.9000,\"words\":10,\"duration_ms\":60000}"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn mistaken_words_are_read_back_from_the_history() {
+        let dir = std::env::temp_dir().join(format!("typeracer-words-{}", std::process::id()));
+        let path = dir.join("history.jsonl");
+        assert!(read_history_mistaken_words(&path, 10).unwrap().is_empty());
+
+        let mut results = results(Duration::from_secs(60));
+        results.mistaken_words =
+            HashMap::from([(String::from("the"), 2), (String::from("\"wpm\\"), 1)]);
+        results.append_to(&path).unwrap();
+        results.mistaken_words =
+            HashMap::from([(String::from("the"), 1), (String::from("cat"), 3)]);
+        results.append_to(&path).unwrap();
+        results.mistaken_words = HashMap::new();
+        results.append_to(&path).unwrap();
+        fs::write(
+            &path,
+            fs::read_to_string(&path).unwrap() + "{\"mistaken_words\":{\"dog\":x}}\n",
+        )
+        .unwrap();
+
+        // the word named like a field doesn't get in the way of reading it
+        assert_eq!(read_history_wpm(&path).unwrap(), vec![10.0, 10.0, 10.0]);
+        let words = read_history_mistaken_words(&path, 10).unwrap();
+        let expected = HashMap::from([
+            (String::from("the"), 3),
+            (String::from("\"wpm\\"), 1),
+            (String::from("cat"), 3),
+        ]);
+        assert_eq!(words, expected);
+        let words = read_history_mistaken_words(&path, 3).unwrap();
+        assert_eq!(words, HashMap::from([(String::from("the"), 1), (String::from("cat"), 3)]));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn append_csv_to_writes_the_header_once() {
+        let dir = std::env::temp_dir().join(format!("typeracer-csv-{}", std::process::id()));
+        let path = dir.join("results.csv");
+
+        let mut results = results(Duration::from_millis(30500));
+        results.total_chars_typed = 60;
+        results.append_csv_to(&path).unwrap();
+        results.append_csv_to(&path).unwrap();
+
+        let csv = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        let fields: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(fields.len(), CSV_HEADER.split(',').count());
+        assert_eq!(&fields[1..], ["19.67", "23.61", "0.9167", "10", "5", "30500"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn read_history_wpm_skips_bad_lines() {
+        let dir = std::env::temp_dir().join(format!("typeracer-best-{}", std::process::id()));
+        let path = dir.join("history.jsonl");
+
+        assert!(read_history_wpm(&path).unwrap().is_empty());
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            &path,
+            "{\"timestamp\":1,\"wpm\":42.50,\"accuracy\":1.0000}\nnot json\n\n{\"wpm\":80.1}\n",
+        )
+        .unwrap();
+        assert_eq!(read_history_wpm(&path).unwrap(), vec![42.5, 80.1]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn raw_wpm_counts_every_typed_char() {
+        let mut results = results(Duration::from_secs(30));
+        results.total_chars_typed = 60;
+        results.final_chars_typed_correctly = 45;
+        results.final_uncorrected_errors = 1;
+
+        // 60 chars are 12 words in half a minute
+        assert!((results.raw_wpm() - 24.0).abs() < 1e-9);
+        // 45 chars are 9 words, minus one uncorrected error
+        assert!((results.wpm() - 16.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn consistency_of_keystroke_timing() {
+        let mut results = results(Duration::from_secs(1));
+        assert_eq!(results.consistency(), 1.0);
+
+        results.keystrokes = vec![Duration::ZERO, Duration::from_millis(100)];
+        assert_eq!(results.consistency(), 1.0);
+
+        results.keystrokes = (0..10).map(|i| Duration::from_millis(i * 100)).collect();
+        assert!((results.consistency() - 1.0).abs() < 1e-9);
+
+        results.keystrokes = [0, 100, 150, 400, 450, 900]
+            .iter()
+            .map(|ms| Duration::from_millis(*ms))
+            .collect();
+        let consistency = results.consistency();
+        assert!(consistency > 0.0 && consistency < 0.5, "got {}", consistency);
+    }
+
+    #[test]
+    fn accuracy_and_completion_of_partial_test() {
+        let mut results = results(Duration::from_secs(10));
+        // stopped at character 10 of 40, two mistakes were fixed on the way
+        results.total_chars_typed = 12;
+        results.total_char_errors = 2;
+        results.total_chars_in_text = 10;
+        results.total_chars_in_target = 40;
+        results.final_chars_typed_correctly = 10;
+
+        assert!((results.accuracy() - 10.0 / 12.0).abs() < 1e-9);
+        assert!((results.completion() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn accuracy_without_keystrokes() {
+        let mut results = results(Duration::from_secs(10));
+        results.total_chars_typed = 0;
+        results.total_char_errors = 0;
+        results.total_chars_in_text = 0;
+
+        assert_eq!(results.accuracy(), 1.0);
+        assert_eq!(results.completion(), 0.0);
+    }
+
+    #[test]
+    fn word_wpm_counts_correct_words() {
+        let mut results = results(Duration::from_secs(30));
+        assert_eq!(results.word_wpm(), 16.0);
+
+        results.correct_words = 0;
+        assert_eq!(results.word_wpm(), 0.0);
+    }
+
+    #[test]
+    fn wpm_series_by_second() {
+        let mut long = results(Duration::from_millis(2500));
+        // 10 chars in the first second, none in the second, 5 in the last half
+        long.keystrokes = (0..10)
+            .map(|i| Duration::from_millis(i * 90))
+            .chain((0..5).map(|i| Duration::from_millis(2000 + i * 90)))
+            .collect();
+        assert_eq!(long.wpm_series(), vec![120.0, 0.0, 60.0]);
+
+        let mut short = results(Duration::from_millis(400));
+        short.keystrokes = vec![Duration::ZERO, Duration::from_millis(300)];
+        assert_eq!(short.wpm_series(), vec![24.0]);
+
+        short.keystrokes.clear();
+        assert_eq!(short.wpm_series(), vec![0.0]);
+    }
+
+    #[test]
+    fn slowest_words_come_first() {
+        let mut results = results(Duration::from_secs(10));
+        assert!(results.slowest_words(3).is_empty());
+
+        results.word_times = [
+            ("one", 300),
+            ("two", 900),
+            ("three", 500),
+            ("four", 900),
+            ("five", 100),
+        ]
+        .iter()
+        .map(|(word, ms)| (word.to_string(), Duration::from_millis(*ms)))
+        .collect();
+
+        let slowest: Vec<&str> = results
+            .slowest_words(3)
+            .iter()
+            .map(|(word, _)| word.as_str())
+            .collect();
+        assert_eq!(slowest, vec!["two", "four", "three"]);
+    }
+
+    #[test]
+    fn percentile_against_the_history() {
+        // 50 correct chars in a minute, 10 wpm
+        let results = results(Duration::from_secs(60));
+        assert_eq!(results.percentile_against(&[]), None);
+        assert_eq!(results.percentile_against(&[5.0, 12.0, 8.0, 9.0]), None);
+
+        assert_eq!(results.percentile_against(&[5.0, 12.0, 8.0, 9.0, 30.0]), Some(0.6));
+        // ties aren't slower
+        assert_eq!(results.percentile_against(&[10.0; 6]), Some(0.0));
+        assert_eq!(results.percentile_against(&[1.0; 8]), Some(1.0));
+    }
+
+    #[test]
+    fn top_mistaken_chars_by_count() {
+        let mut results = results(Duration::from_secs(10));
+        assert!(results.top_mistaken_chars(3).is_empty());
+
+        results.mistaken_chars = HashMap::from([('e', 4), ('t', 1), (' ', 4), ('a', 2)]);
+        assert_eq!(results.top_mistaken_chars(3), vec![(' ', 4), ('e', 4), ('a', 2)]);
+        assert_eq!(results.top_mistaken_chars(10).len(), 4);
+    }
+
+    #[test]
+    fn accessors_read_the_counts() {
+        let mut results = results(Duration::from_secs(10));
+        results.final_uncorrected_errors = 2;
+        results.total_chars_in_target = 80;
+
+        assert_eq!(results.words(), 10);
+        assert_eq!(results.chars_typed(), 50);
+        assert_eq!(results.total_keystrokes(), 56);
+        assert_eq!(results.chars_reached(), 50);
+        assert_eq!(results.text_len(), 80);
+        assert_eq!(results.errors(), 5);
+        assert_eq!(results.uncorrected_errors(), 2);
+        assert_eq!(results.time_limit(), None);
+        assert_eq!(results.clone(), results);
+    }
+
+    #[test]
+    fn summary_leaves_out_instants() {
+        // nothing typed in no time
+        assert_eq!(results(Duration::ZERO).summary().unwrap().wpm, 0.0);
+
+        let mut results = results(Duration::from_secs(60));
+        results.finished_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        results.word_times = vec![(String::from("ten"), Duration::from_millis(400))];
+
+        let summary = results.summary().unwrap();
+        assert_eq!(summary.timestamp, 1_700_000_000);
+        assert_eq!(summary.duration, Duration::from_secs(60));
+        assert_eq!(summary.words, 10);
+        assert_eq!(summary.wpm, 10.0);
+        assert_eq!(summary.word_wpm, 8.0);
+        assert_eq!(summary.accuracy, 0.9);
+        assert_eq!(summary.word_times, results.word_times);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn summary_round_trips_through_json() {
+        let mut results = results(Duration::from_millis(30500));
+        results.time_limit = Some(Duration::from_secs(30));
+        results.keystrokes = vec![Duration::ZERO, Duration::from_millis(120)];
+        results.mistaken_chars = HashMap::from([('e', 2), (' ', 1)]);
+        let summary = results.summary().unwrap();
+
+        let json = serde_json::to_string(&summary).unwrap();
+        let read: ResultsSummary = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, summary);
+    }
+
+    #[test]
+    fn errors_by_finger_on_a_layout() {
+        let mut results = results(Duration::from_secs(10));
+        assert!(results.errors_by_finger(KeyboardLayout::Qwerty).is_empty());
+
+        results.mistaken_chars = HashMap::from([('r', 2), ('t', 1), ('i', 3), (' ', 1), ('ü', 5)]);
+        assert_eq!(
+            results.errors_by_finger(KeyboardLayout::Qwerty),
+            vec![(Finger::LeftIndex, 3), (Finger::RightMiddle, 3), (Finger::Thumb, 1)]
+        );
+        // the same characters are elsewhere on dvorak
+        assert_eq!(
+            results.errors_by_finger(KeyboardLayout::Dvorak),
+            vec![
+                (Finger::LeftIndex, 3),
+                (Finger::RightRing, 2),
+                (Finger::Thumb, 1),
+                (Finger::RightMiddle, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn duration_leaves_out_pauses() {
+        let mut results = results(Duration::from_secs(90));
+        results.paused_duration = Duration::from_secs(30);
+
+        assert_eq!(results.duration(), Duration::from_secs(60));
+        assert!((results.raw_wpm() - 10.0).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,522 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::results::GameResults;
+
+/// A key that changes what's typed in a test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypedKey {
+    Char(char),
+    Backspace,
+    /// Deletes back to the start of the word, like ctrl-w.
+    DeleteWord,
+}
+
+/// Scores `keys`, each with the time it was pressed since the first one,
+/// as typed against `text` in one test without pauses or a time limit.
+/// Keys after the end of the text are ignored. With `strict_space`, a wrong
+/// key where a space goes counts as a mistake rather than being ignored.
+pub fn score(text: &str, keys: &[(TypedKey, Duration)], strict_space: bool) -> GameResults {
+    let mut scorer = Scorer::new(text.chars().collect(), strict_space);
+    let mut ended = Duration::ZERO;
+    for &(key, at) in keys {
+        if scorer.is_done() {
+            break;
+        }
+        match key {
+            TypedKey::Char(c) => {
+                scorer.type_char(c, at);
+            }
+            TypedKey::Backspace => {
+                scorer.backspace(0);
+            }
+            TypedKey::DeleteWord => {
+                scorer.delete_word(0);
+            }
+        }
+        ended = at;
+    }
+
+    let started_at = Instant::now();
+    scorer.finish(started_at, started_at + ended, Duration::ZERO, None)
+}
+
+/// Keeps score of a test key by key, for `Game::run` and `score`. What's
+/// typed is kept here, the text only grows, when a timed test needs more.
+pub struct Scorer {
+    text: Vec<char>,
+    input: Vec<char>,
+    strict_space: bool,
+    num_chars_typed: usize,
+    // every key that changed the input, a deleted word counting a key for
+    // every character of it
+    num_keystrokes: usize,
+    num_errors: usize,
+    // correct keystrokes in a row, deleting doesn't break a streak
+    streak: usize,
+    longest_streak: usize,
+    keystrokes: Vec<Duration>,
+    word_times: Vec<(String, Duration)>,
+    word_started_at: Duration,
+    // end of the last word that was timed
+    words_timed_to: usize,
+    mistaken_chars: HashMap<char, u32>,
+    mistaken_words: HashMap<String, u32>,
+    // spaces at the ends of lines typed by `skip_space`, where they don't
+    // count
+    skipped_spaces: Vec<usize>,
+    // skipped spaces the input didn't reach
+    unreached_skips: usize,
+}
+
+impl Scorer {
+    pub fn new(text: Vec<char>, strict_space: bool) -> Self {
+        Scorer {
+            text,
+            input: Vec::new(),
+            strict_space,
+            num_chars_typed: 0,
+            num_keystrokes: 0,
+            num_errors: 0,
+            streak: 0,
+            longest_streak: 0,
+            keystrokes: Vec::new(),
+            word_times: Vec::new(),
+            word_started_at: Duration::ZERO,
+            words_timed_to: 0,
+            mistaken_chars: HashMap::new(),
+            mistaken_words: HashMap::new(),
+            skipped_spaces: Vec::new(),
+            unreached_skips: 0,
+        }
+    }
+
+    /// Goes on from `input`, typed for `elapsed`, counted as if it had been
+    /// typed once without corrections.
+    pub fn resumed(mut self, input: Vec<char>, elapsed: Duration) -> Self {
+        self.num_errors = input
+            .iter()
+            .zip(&self.text)
+            .filter(|(typed, orig)| typed != orig)
+            .count();
+        self.num_chars_typed = input.len();
+        self.num_keystrokes = input.len();
+        self.word_started_at = elapsed;
+        self.words_timed_to = input.iter().rposition(|c| c.is_whitespace()).unwrap_or(0);
+        self.input = input;
+        self
+    }
+
+    pub fn text(&self) -> &[char] {
+        &self.text
+    }
+
+    /// What's typed so far, mistakes and skipped spaces included.
+    pub fn input(&self) -> &[char] {
+        &self.input
+    }
+
+    /// Every character typed, including mistakes and ones deleted since.
+    pub fn chars_typed(&self) -> usize {
+        self.num_chars_typed
+    }
+
+    pub fn errors(&self) -> usize {
+        self.num_errors
+    }
+
+    /// Every key that changed the input, deletions included, see
+    /// `GameResults::total_keystrokes`.
+    pub fn total_keystrokes(&self) -> usize {
+        self.num_keystrokes
+    }
+
+    /// Whether the whole text was typed.
+    pub fn is_done(&self) -> bool {
+        self.input.len() >= self.text.len()
+    }
+
+    /// Adds `words` to the end of the text, each after a space. A text
+    /// ending with a space, as with `GameConfig::final_space`, keeps it
+    /// after the new words instead.
+    pub fn extend_text(&mut self, words: &[String]) {
+        let final_space = self.text.last() == Some(&' ') && !words.is_empty();
+        if final_space {
+            self.text.pop();
+        }
+        for word in words {
+            self.text.push(' ');
+            self.text.extend(word.chars());
+        }
+        if final_space {
+            self.text.push(' ');
+        }
+    }
+
+    /// Types `c` at `at` since the start, returning the character of the
+    /// text it was typed for. `None` if it was ignored, because it isn't the
+    /// space between two words that can't run into each other, or
+    /// because the text is done.
+    pub fn type_char(&mut self, c: char, at: Duration) -> Option<char> {
+        let orig_char = *self.text.get(self.input.len())?;
+        if !self.strict_space && orig_char != c && orig_char.is_whitespace() {
+            return None;
+        }
+
+        self.keystrokes.push(at);
+        self.input.push(c);
+        self.num_chars_typed += 1;
+        self.num_keystrokes += 1;
+
+        // a word is done once the space or newline after it, or the end of
+        // the text, is reached for the first time
+        if orig_char.is_whitespace() {
+            self.time_word(self.input.len() - 1, at);
+        } else if self.is_done() {
+            self.time_word(self.input.len(), at);
+        }
+
+        if orig_char == c {
+            self.streak += 1;
+            self.longest_streak = self.longest_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+            self.num_errors += 1;
+            *self.mistaken_chars.entry(orig_char).or_insert(0) += 1;
+            if let Some(word) = self.word_at(self.input.len() - 1) {
+                *self.mistaken_words.entry(word).or_insert(0) += 1;
+            }
+        }
+
+        Some(orig_char)
+    }
+
+    /// Types the space at the end of a line, at `at`, for the user, which
+    /// then doesn't count as part of the text. The word before it is done.
+    pub fn skip_space(&mut self, at: Duration) {
+        self.time_word(self.input.len(), at);
+        self.input.push(' ');
+        self.skipped_spaces.push(self.input.len() - 1);
+    }
+
+    /// Leaves `count` spaces after the input out of the text, which
+    /// `skip_space` would have typed.
+    pub fn skip_unreached(&mut self, count: usize) {
+        self.unreached_skips = count;
+    }
+
+    /// Deletes the last character, unless there's nothing typed after
+    /// `from`. A skipped space goes along with the character before it.
+    /// Returns whether anything was deleted.
+    pub fn backspace(&mut self, from: usize) -> bool {
+        if self.input.len() <= from {
+            return false;
+        }
+
+        self.input.pop();
+        self.num_keystrokes += 1;
+        if self.skipped_spaces.last() == Some(&self.input.len()) {
+            self.skipped_spaces.pop();
+            if self.input.len() > from {
+                self.input.pop();
+            }
+        }
+        true
+    }
+
+    /// Deletes the word before the cursor, see `word_start`.
+    pub fn delete_word(&mut self, from: usize) {
+        let start = word_start(&self.input, from);
+        // skipped spaces go without being typed or deleted
+        let skipped = self.skipped_spaces.iter().filter(|at| **at >= start).count();
+        self.num_keystrokes += self.input.len() - start - skipped;
+        self.input.truncate(start);
+        self.skipped_spaces.retain(|at| *at < start);
+    }
+
+    // the word of the text `index` is in, none for the whitespace between
+    fn word_at(&self, index: usize) -> Option<String> {
+        if self.text[index].is_whitespace() {
+            return None;
+        }
+        let start = self.text[..index]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(0, |space| space + 1);
+        let end = self.text[index..]
+            .iter()
+            .position(|c| c.is_whitespace())
+            .map_or(self.text.len(), |len| index + len);
+        Some(self.text[start..end].iter().collect())
+    }
+
+    // times the word ending at `end` the first time it's reached
+    fn time_word(&mut self, end: usize, at: Duration) {
+        if end <= self.words_timed_to {
+            return;
+        }
+
+        let start = self.text[..end]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(0, |space| space + 1);
+        if start < end {
+            let word = self.text[start..end].iter().collect();
+            self.word_times.push((word, at - self.word_started_at));
+            self.word_started_at = at;
+        }
+        self.words_timed_to = end;
+    }
+
+    /// The results of the test, which ran from `started_at` to `ended_at`
+    /// and was paused for `paused_duration`.
+    pub fn finish(
+        self,
+        started_at: Instant,
+        ended_at: Instant,
+        paused_duration: Duration,
+        time_limit: Option<Duration>,
+    ) -> GameResults {
+        let final_chars_typed_correctly = self
+            .input
+            .iter()
+            .zip(&self.text)
+            .filter(|(typed_char, orig_char)| typed_char == orig_char)
+            .count();
+        let final_uncorrected_errors =
+            self.input.len().min(self.text.len()) - final_chars_typed_correctly;
+
+        let total_words = if time_limit.is_some() {
+            // only the words that were reached
+            self.input.split(|c| c.is_whitespace()).filter(|word| !word.is_empty()).count()
+        } else {
+            self.text.split(|c| c.is_whitespace()).filter(|word| !word.is_empty()).count()
+        };
+
+        // skipped spaces don't count as text, typed or not
+        let skipped = self.skipped_spaces.len();
+        GameResults {
+            total_words,
+            total_chars_typed: self.num_chars_typed,
+            total_keystrokes: self.num_keystrokes,
+            total_chars_in_text: self.input.len() - skipped,
+            total_chars_in_target: self.text.len() - skipped - self.unreached_skips,
+            total_char_errors: self.num_errors,
+            final_chars_typed_correctly: final_chars_typed_correctly - skipped,
+            final_uncorrected_errors,
+            correct_words: correct_words(&self.text, &self.input),
+            started_at,
+            ended_at,
+            paused_duration,
+            time_limit,
+            keystrokes: self.keystrokes,
+            word_times: self.word_times,
+            mistaken_chars: self.mistaken_chars,
+            mistaken_words: self.mistaken_words,
+            longest_streak: self.longest_streak,
+            finished_at: SystemTime::now(),
+        }
+    }
+}
+
+// words of `text` that `input` reached the end of with every char right
+fn correct_words(text: &[char], input: &[char]) -> usize {
+    let mut count = 0;
+    let mut start = 0;
+    for end in 0..=text.len() {
+        if end < text.len() && !text[end].is_whitespace() {
+            continue;
+        }
+        if start < end && end <= input.len() && input[start..end] == text[start..end] {
+            count += 1;
+        }
+        start = end + 1;
+    }
+    count
+}
+
+// where ctrl-w deletes the typed text back to: spaces and tabs typed last,
+// then the word before them. It stops at whitespace, at a newline, since
+// going back a line takes a backspace, and at `from`, what scrolled away.
+// Nothing is deleted when nothing's been typed since any of them
+fn word_start(typed: &[char], from: usize) -> usize {
+    let mut start = typed.len();
+    while start > from && matches!(typed[start - 1], ' ' | '\t') {
+        start -= 1;
+    }
+    while start > from && !typed[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(typed: &str) -> Vec<(TypedKey, Duration)> {
+        // a keystroke every 100ms, '<' for a backspace and '^' for ctrl-w
+        typed
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let key = match c {
+                    '<' => TypedKey::Backspace,
+                    '^' => TypedKey::DeleteWord,
+                    c => TypedKey::Char(c),
+                };
+                (key, Duration::from_millis(100 * i as u64))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn word_deletion_stops_at_whitespace_and_line_starts() {
+        let typed: Vec<char> = "one two".chars().collect();
+        assert_eq!(word_start(&typed, 0), 4);
+        assert_eq!(word_start(&typed[..4], 0), 0);
+        assert_eq!(word_start(&typed[..3], 0), 0);
+        // nothing typed, or nothing left that's still on screen
+        assert_eq!(word_start(&[], 0), 0);
+        assert_eq!(word_start(&typed, 7), 7);
+        assert_eq!(word_start(&typed, 5), 5);
+
+        let typed: Vec<char> = "one\n\t  two \t".chars().collect();
+        assert_eq!(word_start(&typed, 0), 7);
+        assert_eq!(word_start(&typed[..7], 0), 4);
+        assert_eq!(word_start(&typed[..4], 0), 4);
+    }
+
+    #[test]
+    fn scores_a_clean_test() {
+        let results = score("one two", &keys("one two"), false);
+
+        assert_eq!(results.words(), 2);
+        assert_eq!(results.chars_typed(), 7);
+        assert_eq!(results.errors(), 0);
+        assert_eq!(results.accuracy(), 1.0);
+        assert_eq!(results.longest_streak(), 7);
+        assert_eq!(results.duration(), Duration::from_millis(600));
+        let words: Vec<&str> = results.word_times().iter().map(|(word, _)| word.as_str()).collect();
+        assert_eq!(words, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn errors_count_even_once_corrected() {
+        let results = score("one two", &keys("onx<e twp"), false);
+
+        assert_eq!(results.chars_reached(), 7);
+        assert_eq!(results.chars_typed(), 8);
+        assert_eq!(results.errors(), 2);
+        assert_eq!(results.uncorrected_errors(), 1);
+        assert_eq!(results.longest_streak(), 4);
+        assert_eq!(results.top_mistaken_chars(2), vec![('e', 1), ('o', 1)]);
+        let words = HashMap::from([(String::from("one"), 1), (String::from("two"), 1)]);
+        assert_eq!(results.mistaken_words(), &words);
+    }
+
+    #[test]
+    fn only_words_left_without_mistakes_are_correct() {
+        // the mistake in "one" was corrected, the one in "two" wasn't
+        let results = score("one two three", &keys("onx<e twp three"), false);
+        assert_eq!(results.correct_words(), 2);
+
+        // a word isn't correct before its end is reached
+        assert_eq!(score("one two three", &keys("one thr"), false).correct_words(), 1);
+        assert_eq!(score("one two", &keys(""), false).correct_words(), 0);
+    }
+
+    #[test]
+    fn word_deletion_and_backspaces_apply_to_the_typed_stream() {
+        // "tow" deleted as a word, then the "x" with a backspace
+        let results = score("one two three", &keys("one tow^tx<wo three"), false);
+        assert_eq!(results.chars_reached(), 13);
+        assert_eq!(results.uncorrected_errors(), 0);
+        assert_eq!(results.errors(), 3);
+        assert_eq!(results.chars_typed(), 17);
+        // the 17 typed, 3 for "tow" and 1 for the "x"
+        assert_eq!(results.total_keystrokes(), 21);
+
+        // the word goes along with the space after it
+        let results = score("one two", &keys("one ^one two"), false);
+        assert_eq!(results.chars_typed(), 11);
+        assert_eq!(results.total_keystrokes(), 15);
+        assert_eq!(results.errors(), 0);
+
+        // deleting with nothing typed does nothing
+        let results = score("one", &keys("<^one"), false);
+        assert_eq!(results.chars_typed(), 3);
+        assert_eq!(results.total_keystrokes(), 3);
+        assert_eq!(results.errors(), 0);
+    }
+
+    #[test]
+    fn spaces_between_words_follow_strict_space() {
+        let results = score("a b", &keys("ax b"), false);
+        assert_eq!(results.chars_typed(), 3);
+        assert_eq!(results.errors(), 0);
+
+        let results = score("a b", &keys("axb"), true);
+        assert_eq!(results.chars_typed(), 3);
+        assert_eq!(results.errors(), 1);
+
+        // nothing past the end of the text
+        assert_eq!(score("a", &keys("abc"), false).chars_typed(), 1);
+    }
+
+    #[test]
+    fn skipped_spaces_dont_count() {
+        let mut scorer = Scorer::new("one two".chars().collect(), false);
+        for c in "one".chars() {
+            scorer.type_char(c, Duration::ZERO);
+        }
+        scorer.skip_space(Duration::ZERO);
+        assert_eq!(scorer.input().len(), 4);
+
+        // the skipped space goes with the char before it
+        assert!(scorer.backspace(0));
+        assert_eq!(scorer.input(), ['o', 'n']);
+        scorer.type_char('e', Duration::ZERO);
+        scorer.skip_space(Duration::ZERO);
+
+        let now = Instant::now();
+        let results = scorer.finish(now, now, Duration::ZERO, None);
+        assert_eq!(results.chars_reached(), 3);
+        assert_eq!(results.text_len(), 6);
+        assert_eq!(results.word_times().len(), 1);
+    }
+
+    #[test]
+    fn skipped_spaces_take_no_keystrokes() {
+        let mut scorer = Scorer::new("one two".chars().collect(), false);
+        for c in "onx".chars() {
+            scorer.type_char(c, Duration::ZERO);
+        }
+        scorer.skip_space(Duration::ZERO);
+        assert_eq!(scorer.total_keystrokes(), 3);
+
+        // one backspace takes the space along with the "x"
+        scorer.backspace(0);
+        assert_eq!(scorer.total_keystrokes(), 4);
+        scorer.type_char('x', Duration::ZERO);
+        scorer.skip_space(Duration::ZERO);
+        scorer.delete_word(0);
+        assert_eq!(scorer.total_keystrokes(), 8);
+        assert!(scorer.input().is_empty());
+    }
+
+    #[test]
+    fn more_words_go_before_a_final_space() {
+        let more = [String::from("three"), String::from("four")];
+
+        let mut scorer = Scorer::new("one two".chars().collect(), false);
+        scorer.extend_text(&more);
+        assert_eq!(scorer.text().iter().collect::<String>(), "one two three four");
+
+        let mut scorer = Scorer::new("one two ".chars().collect(), false);
+        scorer.extend_text(&more);
+        assert_eq!(scorer.text().iter().collect::<String>(), "one two three four ");
+        scorer.extend_text(&[]);
+        assert_eq!(scorer.text().len(), 19);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::GameError;
+
+// first line of a session file, changed whenever the format is
+const HEADER: &str = "rusty-typeracer session 1";
+
+/// A test in progress, saved now and then by `Game::run` so it can be picked
+/// up again with `Game::resume` if the game dies. Only what's needed to draw
+/// the test again is kept, keystroke timings are lost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// Seed the words came from, kept for the share code.
+    pub seed: u64,
+    pub words: Vec<String>,
+    /// The lines of a verbatim text, which the words were split from.
+    pub lines: Option<Vec<String>>,
+    /// Everything typed, mistakes included.
+    pub input: Vec<char>,
+    /// Time spent typing, without pauses.
+    pub elapsed: Duration,
+}
+
+impl Session {
+    /// Writes the session to `path`, replacing what was there. The file is
+    /// written next to it first, so a crash halfway leaves the last session.
+    pub fn save(&self, path: &Path) -> Result<(), GameError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut contents = format!(
+            "{}\nseed {}\nelapsed_ms {}\nwords {}\n",
+            HEADER,
+            self.seed,
+            self.elapsed.as_millis(),
+            self.words.join(" "),
+        );
+        for line in self.lines.iter().flatten() {
+            contents += &format!("line {}\n", escape(line.chars()));
+        }
+        contents += &format!("input {}\n", escape(self.input.iter().copied()));
+
+        let partial = path.with_extension("partial");
+        fs::write(&partial, contents)?;
+        fs::rename(&partial, path)?;
+
+        Ok(())
+    }
+
+    /// Reads the session saved at `path`, `None` if there's none.
+    pub fn load(path: &Path) -> Result<Option<Self>, GameError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let invalid = || GameError::from(format!("Saved session {} is damaged", path.display()));
+
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid());
+        }
+
+        let mut session = Session {
+            seed: 0,
+            words: Vec::new(),
+            lines: None,
+            input: Vec::new(),
+            elapsed: Duration::ZERO,
+        };
+        for line in lines {
+            let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+            match field {
+                "seed" => session.seed = value.parse().map_err(|_| invalid())?,
+                "elapsed_ms" => {
+                    session.elapsed = Duration::from_millis(value.parse().map_err(|_| invalid())?);
+                }
+                "words" => {
+                    session.words = value
+                        .split(' ')
+                        .filter(|word| !word.is_empty())
+                        .map(String::from)
+                        .collect()
+                }
+                "line" => session
+                    .lines
+                    .get_or_insert_with(Vec::new)
+                    .push(unescape(value).ok_or_else(invalid)?),
+                "input" => session.input = unescape(value).ok_or_else(invalid)?.chars().collect(),
+                _ => return Err(invalid()),
+            }
+        }
+
+        if session.words.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Some(session))
+    }
+
+    /// Deletes the session saved at `path`, if there is one.
+    pub fn remove(path: &Path) -> Result<(), GameError> {
+        match fs::remove_file(path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// keeps newlines, typed in verbatim texts, from splitting a field
+fn escape<I: Iterator<Item = char>>(chars: I) -> String {
+    let mut escaped = String::new();
+    for c in chars {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir()
+            .join(format!("typeracer-session-{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn sessions_round_trip() {
+        let path = session_path("round-trip");
+        let session = Session {
+            seed: 42,
+            words: vec![String::from("one"), String::from("two\\"), String::from("three")],
+            lines: Some(vec![String::from("one\ttwo\\"), String::new(), String::from(" three")]),
+            input: "one\ttwo\\\n\n tX".chars().collect(),
+            elapsed: Duration::from_millis(12_345),
+        };
+
+        session.save(&path).unwrap();
+        assert_eq!(Session::load(&path).unwrap(), Some(session.clone()));
+
+        let words_only = Session { lines: None, input: Vec::new(), ..session };
+        words_only.save(&path).unwrap();
+        assert_eq!(Session::load(&path).unwrap(), Some(words_only));
+
+        Session::remove(&path).unwrap();
+        assert_eq!(Session::load(&path).unwrap(), None);
+        // already gone
+        Session::remove(&path).unwrap();
+    }
+
+    #[test]
+    fn damaged_sessions_are_errors() {
+        let path = session_path("damaged");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        for contents in [
+            "",
+            "some other file\n",
+            "rusty-typeracer session 1\nseed x\nwords a b\n",
+            "rusty-typeracer session 1\nseed 1\n",
+        ] {
+            fs::write(&path, contents).unwrap();
+            let error = Session::load(&path).unwrap_err();
+            assert!(error.msg.contains("damaged"), "{:?}: {}", contents, error.msg);
+        }
+        Session::remove(&path).unwrap();
+    }
+}
@@ -1,18 +1,45 @@
-use std::{
-    fmt::Display,
-    io::{stdout, Stdout, Write},
-};
+use std::cell::Cell;
+use std::env;
+use std::ffi::OsStr;
+use std::fmt::Display;
+
+use rand::Rng;
 
+use crate::markov::ends_sentence;
 use termion::{
-    clear,
     color::{self, Color},
-    cursor::{self, DetectCursorPos},
-    style, terminal_size,
+    style,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+thread_local! {
+    static COLORS: Cell<bool> = const { Cell::new(true) };
+}
 
-use crate::GameError;
+/// Turns `Text::with_color` into a no-op when `enabled` is false, for the
+/// texts built on this thread afterwards. Other styles stay, so mistakes
+/// are still underlined.
+pub fn set_colors(enabled: bool) {
+    COLORS.with(|colors| colors.set(enabled));
+}
+
+/// Whether `Text::with_color` adds colors on this thread, see `set_colors`.
+pub fn colors_enabled() -> bool {
+    COLORS.with(Cell::get)
+}
 
-const MIN_LINE_WIDTH: usize = 50;
+/// Whether the environment wants colors: not when `NO_COLOR` is set to
+/// anything (see no-color.org), nor on a `dumb` terminal.
+pub fn colors_supported() -> bool {
+    colors_wanted(env::var_os("NO_COLOR").as_deref(), env::var_os("TERM").as_deref())
+}
+
+// `colors_supported` for the values of `NO_COLOR` and `TERM`
+fn colors_wanted(no_color: Option<&OsStr>, term: Option<&OsStr>) -> bool {
+    let no_color = no_color.is_some_and(|value| !value.is_empty());
+    let dumb = term.is_some_and(|term| term == "dumb");
+    !no_color && !dumb
+}
 
 pub trait HasLength {
     fn length(&self) -> usize;
@@ -27,7 +54,8 @@ pub struct Text {
 
 impl Text {
     pub fn new(text: String) -> Self {
-        let len = text.len();
+        // columns on screen, not bytes
+        let len = text.width();
         Text {
             raw_text: text.clone(),
             formatted_text: text.clone(),
@@ -48,8 +76,27 @@ impl Text {
         self
     }
 
+    pub fn with_bold(mut self) -> Self {
+        // 22m ends bold (and faint); termion's NoBold is 21m, which many
+        // terminals draw as a double underline instead
+        self.raw_text = format!("{}{}{}", style::Bold, self.raw_text, style::NoFaint);
+        self
+    }
+
+    pub fn with_italic(mut self) -> Self {
+        self.raw_text = format!("{}{}{}", style::Italic, self.raw_text, style::NoItalic);
+        self
+    }
+
     pub fn with_underline(mut self) -> Self {
-        self.raw_text = format!("{}{}{}", style::Underline, self.raw_text, style::Reset);
+        // NoUnderline rather than Reset, which would also end faint, bold and
+        // colors applied around this text
+        self.raw_text = format!("{}{}{}", style::Underline, self.raw_text, style::NoUnderline);
+        self
+    }
+
+    pub fn with_invert(mut self) -> Self {
+        self.raw_text = format!("{}{}{}", style::Invert, self.raw_text, style::NoInvert);
         self
     }
 
@@ -57,6 +104,9 @@ impl Text {
         where
         C: Color,
         {
+            if !colors_enabled() {
+                return self;
+            }
             self.raw_text = format!(
                 "{}{}{}",
                 color::Fg(color),
@@ -65,6 +115,77 @@ impl Text {
                 );
             self
         }
+
+    /// The text cut to at most `max_cols` columns, ending in an ellipsis
+    /// when anything was cut. Escape sequences are kept whole, so styles are
+    /// still turned off after it, and a wide character that doesn't fit is
+    /// left out rather than split.
+    pub fn truncate(self, max_cols: usize) -> Self {
+        if self.length <= max_cols {
+            return self;
+        }
+
+        // the ellipsis takes a column, unless there's none at all
+        let ellipsis = max_cols > 0;
+        let budget = max_cols.saturating_sub(1);
+        let (formatted_text, length) = cut_to_columns(&self.formatted_text, budget, ellipsis);
+        Text {
+            raw_text: cut_to_columns(&self.raw_text, budget, ellipsis).0,
+            formatted_text,
+            length,
+        }
+    }
+}
+
+const ELLIPSIS: char = '…';
+
+// the chars of `text` that fit in `budget` columns, then `ELLIPSIS` if any
+// were cut, and every escape sequence whether or not it comes after the cut.
+// Also returns the columns taken
+fn cut_to_columns(text: &str, budget: usize, ellipsis: bool) -> (String, usize) {
+    let mut cut = String::with_capacity(text.len());
+    let mut cols = 0;
+    let mut full = false;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            cut.push(c);
+            match chars.next() {
+                // CSI, up to its final byte like in `printable`
+                Some('[') => {
+                    cut.push('[');
+                    for c in chars.by_ref() {
+                        cut.push(c);
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                Some(c) => cut.push(c),
+                None => {}
+            }
+            continue;
+        }
+        if full {
+            continue;
+        }
+
+        // combining characters take no column and stay with the one before
+        let width = c.width().unwrap_or(0);
+        if cols + width > budget {
+            full = true;
+            if ellipsis {
+                cut.push(ELLIPSIS);
+                cols += 1;
+            }
+            continue;
+        }
+        cut.push(c);
+        cols += width;
+    }
+
+    (cut, cols)
 }
 
 impl HasLength for Text {
@@ -104,4 +225,367 @@ impl Display for Text {
     }
 }
 
+/// `text` without terminal escape sequences and control characters, which
+/// would otherwise be sent to the terminal as they are. Tabs and other
+/// whitespace become plain spaces.
+pub fn printable(text: &str) -> String {
+    let mut printable = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
 
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI, e.g. colors: parameters and intermediates, then one
+                // final byte
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC, e.g. window titles: ends with BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // any other escape is two characters long
+                _ => {}
+            },
+            c if c.is_whitespace() => printable.push(' '),
+            c if c.is_control() => {}
+            c => printable.push(c),
+        }
+    }
+
+    printable
+}
+
+const PUNCTUATION: [char; 6] = [',', '.', '!', '?', ';', ':'];
+
+/// Makes each word, with probability `rate`, either capitalized, followed by
+/// punctuation, or replaced by a number. The number of words never changes.
+pub fn add_punctuation<R: Rng>(words: Vec<String>, rate: f64, rng: &mut R) -> Vec<String> {
+    punctuate(words, rate, rng).collect()
+}
+
+/// Same as `add_punctuation`, but lazily, for words that are only generated
+/// as they're needed.
+pub fn punctuate<I, R>(words: I, rate: f64, mut rng: R) -> impl Iterator<Item = String>
+    where
+    I: IntoIterator<Item = String>,
+    R: Rng,
+{
+    words.into_iter().map(move |word| {
+        if rng.gen::<f64>() >= rate {
+            return word;
+        }
+
+        match rng.gen_range(0, 3) {
+            0 => {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => word,
+                }
+            }
+            1 => format!("{}{}", word, PUNCTUATION[rng.gen_range(0, PUNCTUATION.len())]),
+            _ => rng.gen_range(0, 1000).to_string(),
+        }
+    })
+}
+
+/// Capitalizes the first word and every word after one ending a sentence,
+/// so generated text reads like English. Punctuation before the first letter,
+/// like an opening quote, is left as it is, and so are all the other words.
+/// The number of words never changes.
+pub fn prettify_sentences(words: &mut Vec<String>) {
+    *words = prettify(words.drain(..)).collect();
+}
+
+/// Same as `prettify_sentences`, but lazily, for words that are only
+/// generated as they're needed.
+pub fn prettify<I>(words: I) -> impl Iterator<Item = String>
+    where
+    I: IntoIterator<Item = String>,
+{
+    let mut starts_sentence = true;
+    words.into_iter().map(move |word| {
+        let word = if starts_sentence { capitalized(&word) } else { word };
+        starts_sentence = ends_sentence(&word);
+        word
+    })
+}
+
+// `word` with its first letter in upper case
+fn capitalized(word: &str) -> String {
+    match word.char_indices().find(|(_, c)| c.is_alphabetic()) {
+        Some((at, first)) => {
+            let rest = &word[at + first.len_utf8()..];
+            format!("{}{}{}", &word[..at], first.to_uppercase(), rest)
+        }
+        None => word.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Isaac64Rng, SeedableRng};
+
+    #[test]
+    fn length_of_ascii() {
+        assert_eq!(Text::from("hello").length(), 5);
+    }
+
+    #[test]
+    fn length_of_accented() {
+        assert_eq!(Text::from("café naïve").length(), 10);
+    }
+
+    #[test]
+    fn length_of_wide_chars() {
+        assert_eq!(Text::from("日本語").length(), 6);
+    }
+
+    #[test]
+    fn length_of_slice_sums_columns() {
+        let texts = [Text::from("é"), Text::from("日").with_faint()];
+        assert_eq!(texts[..].length(), 3);
+    }
+
+    #[test]
+    fn colors_can_be_turned_off() {
+        let colored = Text::from("日本 go").with_color(color::Red).with_faint();
+        assert!(colored.to_string().contains(&color::Fg(color::Red).to_string()));
+
+        set_colors(false);
+        let plain = Text::from("日本 go").with_color(color::Red);
+        let faint = plain.clone().with_faint();
+        set_colors(true);
+
+        assert_eq!(plain.to_string(), "日本 go");
+        assert_eq!(faint.to_string(), format!("{}日本 go{}", style::Faint, style::NoFaint));
+        assert_eq!(plain.length(), colored.length());
+        assert!(colors_enabled());
+    }
+
+    #[test]
+    fn colors_follow_the_environment() {
+        let xterm = Some(OsStr::new("xterm-256color"));
+
+        assert!(colors_wanted(None, xterm));
+        assert!(colors_wanted(None, None));
+        // an empty NO_COLOR doesn't count
+        assert!(colors_wanted(Some(OsStr::new("")), xterm));
+        assert!(!colors_wanted(Some(OsStr::new("1")), xterm));
+        assert!(!colors_wanted(None, Some(OsStr::new("dumb"))));
+    }
+
+    #[test]
+    fn printable_strips_escapes_and_controls() {
+        assert_eq!(printable("plain words"), "plain words");
+        assert_eq!(printable("\x1b[31mred\x1b[0m and \x1b[1;38;5;10mgreen"), "red and green");
+        assert_eq!(printable("tab\tseparated"), "tab separated");
+        assert_eq!(printable("nul\0byte\x07"), "nulbyte");
+        assert_eq!(printable("\x1b]0;title\x07after \x1b]2;x\x1b\\end"), "after end");
+        assert_eq!(printable("\x1bctrunc\x1b"), "trunc");
+        assert_eq!(printable("café 日本"), "café 日本");
+    }
+
+    #[test]
+    fn truncate_cuts_to_the_columns() {
+        let cut = |text: &str, max_cols: usize| {
+            let text = Text::from(text).truncate(max_cols);
+            (text.text().clone(), text.length())
+        };
+
+        assert_eq!(cut("typewriter", 10), (String::from("typewriter"), 10));
+        assert_eq!(cut("typewriter", 20), (String::from("typewriter"), 10));
+        assert_eq!(cut("typewriter", 5), (String::from("type…"), 5));
+        assert_eq!(cut("typewriter", 1), (String::from("…"), 1));
+        assert_eq!(cut("typewriter", 0), (String::new(), 0));
+
+        assert_eq!(cut("naïveté", 5), (String::from("naïv…"), 5));
+        // the accent goes with the letter it's on
+        assert_eq!(cut("cafe\u{301}s", 5), (String::from("cafe\u{301}s"), 5));
+        assert_eq!(cut("cafe\u{301}s", 4), (String::from("caf…"), 4));
+
+        // a wide character that doesn't fit is left out whole
+        assert_eq!(cut("日本語です", 6), (String::from("日本…"), 5));
+        assert_eq!(cut("日本語です", 7), (String::from("日本語…"), 7));
+        assert_eq!(cut("日本語です", 2), (String::from("…"), 1));
+    }
+
+    #[test]
+    fn truncate_keeps_the_styles_whole() {
+        let text = Text::from("typewriter").with_underline().with_color(color::Red).truncate(5);
+
+        assert_eq!(printable(text.raw_text()), "type…");
+        let (at_text, at_end) = decode_attributes(text.raw_text());
+        assert_eq!(at_text, vec!["color", "underline"]);
+        assert!(at_end.is_empty());
+
+        let nested = Text::from(format!("ab{}", Text::from("日本").with_faint())).truncate(4);
+        assert_eq!(printable(nested.raw_text()), "ab…");
+        assert!(nested.raw_text().ends_with(&style::NoFaint.to_string()));
+    }
+
+    #[test]
+    fn add_punctuation_keeps_word_count() {
+        let words: Vec<String> = ["alpha", "beta", "gamma", "delta"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        let mut rng = Isaac64Rng::from_seed(&[1][..]);
+
+        assert_eq!(add_punctuation(words.clone(), 0.0, &mut rng), words);
+
+        let changed = add_punctuation(words.clone(), 1.0, &mut rng);
+        assert_eq!(changed.len(), words.len());
+        for (word, changed) in words.iter().zip(changed.iter()) {
+            assert_ne!(word, changed);
+        }
+    }
+
+    #[test]
+    fn prettify_capitalizes_sentence_starts() {
+        let mut words: Vec<String> = [
+            "the", "king", "is", "dead.", "long", "live", "(the", "king!)", "\"what", "now?\"",
+            "42", "said", "élise.", "ça", "va",
+        ]
+        .iter()
+        .map(|word| word.to_string())
+        .collect();
+        let count = words.len();
+
+        prettify_sentences(&mut words);
+        assert_eq!(words.len(), count);
+        assert_eq!(
+            words.join(" "),
+            "The king is dead. Long live (the king!) \"What now?\" 42 said élise. Ça va",
+        );
+
+        // already pretty text stays the same
+        let pretty = words.clone();
+        prettify_sentences(&mut words);
+        assert_eq!(words, pretty);
+
+        let mut empty = Vec::new();
+        prettify_sentences(&mut empty);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn prettify_carries_on_lazily() {
+        let generated = ["one.", "two", "three?", "four"].into_iter().map(String::from);
+        let words: Vec<String> = prettify(generated).take(3).collect();
+
+        assert_eq!(words, ["One.", "Two", "three?"]);
+    }
+
+    #[test]
+    fn bold_and_italic_keep_length() {
+        let text = Text::from("sans").with_bold().with_color(color::Red).with_italic();
+
+        assert_eq!(text.length(), 4);
+        assert_eq!(text.text(), "sans");
+    }
+
+    #[test]
+    fn bold_and_italic_resets_are_balanced() {
+        let text = Text::from("x").with_bold().with_color(color::Red).with_italic();
+        let raw = text.raw_text();
+
+        assert_eq!(raw.matches(&style::Bold.to_string()).count(), 1);
+        assert_eq!(raw.matches(&style::NoFaint.to_string()).count(), 1);
+        assert_eq!(raw.matches(&style::Italic.to_string()).count(), 1);
+        assert_eq!(raw.matches(&style::NoItalic.to_string()).count(), 1);
+        assert!(!raw.contains(&style::NoBold.to_string()));
+        // each style ends after the text it was applied to
+        assert!(raw.find('x').unwrap() < raw.find(&style::NoFaint.to_string()).unwrap());
+        assert!(raw.ends_with(&style::NoItalic.to_string()));
+    }
+
+    // attributes turned on by the SGR escape codes in `raw`, when the first
+    // printable char is reached and at the end
+    fn decode_attributes(raw: &str) -> (Vec<&'static str>, Vec<&'static str>) {
+        fn apply(active: &mut Vec<&'static str>, code: &str) {
+            let mut set = |name: &'static str, on: bool| {
+                active.retain(|attribute| *attribute != name);
+                if on {
+                    active.push(name);
+                }
+            };
+            match code {
+                "" | "0" => active.clear(),
+                "1" => set("bold", true),
+                "2" => set("faint", true),
+                "3" => set("italic", true),
+                "4" => set("underline", true),
+                "22" => {
+                    set("bold", false);
+                    set("faint", false);
+                }
+                "23" => set("italic", false),
+                "24" => set("underline", false),
+                "39" => set("color", false),
+                code if code.starts_with("38;") => set("color", true),
+                code => panic!("unexpected code {}", code),
+            }
+        }
+
+        let mut active = Vec::new();
+        let mut at_text = None;
+        let mut rest = raw;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("\x1b[") {
+                let end = after.find('m').unwrap();
+                apply(&mut active, &after[..end]);
+                rest = &after[end + 1..];
+            } else {
+                at_text.get_or_insert_with(|| active.clone());
+                rest = &rest[rest.chars().next().unwrap().len_utf8()..];
+            }
+        }
+
+        let mut at_text = at_text.unwrap();
+        at_text.sort();
+        active.sort();
+        (at_text, active)
+    }
+
+    #[test]
+    fn styles_stack_in_any_order() {
+        let orders = [
+            Text::from("x").with_faint().with_underline().with_color(color::Red),
+            Text::from("x").with_underline().with_faint().with_color(color::Red),
+            Text::from("x").with_color(color::Red).with_underline().with_faint(),
+            Text::from("x").with_underline().with_color(color::Red).with_faint(),
+        ];
+
+        for text in orders {
+            let (at_text, at_end) = decode_attributes(text.raw_text());
+            assert_eq!(at_text, vec!["color", "faint", "underline"], "{:?}", text);
+            assert!(at_end.is_empty(), "{:?} leaves {:?} on", text, at_end);
+        }
+    }
+
+    #[test]
+    fn underline_keeps_surrounding_styles() {
+        let text = Text::from(format!("a{}b", Text::from("x").with_underline()))
+            .with_faint()
+            .with_color(color::Red);
+
+        // ending the underline must not end the outer faint and color, which
+        // a full reset would
+        let raw = text.raw_text();
+        assert!(!raw.contains(&style::Reset.to_string()));
+        let (at_text, at_end) = decode_attributes(raw);
+        assert_eq!(at_text, vec!["color", "faint"]);
+        assert!(at_end.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use termion::color::{self, Color};
+
+/// A concrete terminal color, so themes can be picked at runtime while
+/// `Text::with_color` stays generic over `Color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeColor {
+    /// The terminal's own foreground color.
+    Default,
+    /// One of the 256 ANSI palette colors.
+    Ansi(u8),
+    /// A 24-bit color, for terminals that support it.
+    Rgb(u8, u8, u8),
+}
+
+impl Color for ThemeColor {
+    fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThemeColor::Default => color::Reset.write_fg(f),
+            ThemeColor::Ansi(value) => color::AnsiValue(value).write_fg(f),
+            ThemeColor::Rgb(r, g, b) => color::Rgb(r, g, b).write_fg(f),
+        }
+    }
+
+    fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThemeColor::Default => color::Reset.write_bg(f),
+            ThemeColor::Ansi(value) => color::AnsiValue(value).write_bg(f),
+            ThemeColor::Rgb(r, g, b) => color::Rgb(r, g, b).write_bg(f),
+        }
+    }
+}
+
+/// Colors used while typing and on the results screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Correctly typed characters, and good news on the results screen.
+    pub correct: ThemeColor,
+    /// Mistyped characters.
+    pub error: ThemeColor,
+    /// Text that hasn't been typed yet, drawn faint in this color.
+    pub faint: ThemeColor,
+    /// Key names in the hint line and highlighted results.
+    pub hint: ThemeColor,
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 3] = ["default", "high-contrast", "solarized"];
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            correct: ThemeColor::Ansi(14),
+            error: ThemeColor::Ansi(11),
+            faint: ThemeColor::Default,
+            hint: ThemeColor::Ansi(15),
+        }
+    }
+
+    pub fn solarized() -> Self {
+        Theme {
+            correct: ThemeColor::Rgb(133, 153, 0),
+            error: ThemeColor::Rgb(220, 50, 47),
+            faint: ThemeColor::Rgb(88, 110, 117),
+            hint: ThemeColor::Rgb(38, 139, 210),
+        }
+    }
+
+    /// Looks up a built-in theme by one of `Theme::NAMES`.
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Theme::default()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "solarized" => Some(Theme::solarized()),
+            _ => None,
+        }
+    }
+
+    /// The name of this theme if it's a built-in one, see `by_name`.
+    pub fn name(&self) -> Option<&'static str> {
+        Theme::NAMES.into_iter().find(|name| Theme::by_name(name) == Some(*self))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        // same escape codes as color::LightGreen, color::Red and color::Blue
+        Theme {
+            correct: ThemeColor::Ansi(10),
+            error: ThemeColor::Ansi(1),
+            faint: ThemeColor::Default,
+            hint: ThemeColor::Ansi(4),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_theme_matches_termion_colors() {
+        let theme = Theme::default();
+
+        assert_eq!(
+            color::Fg(theme.correct).to_string(),
+            color::Fg(color::LightGreen).to_string()
+        );
+        assert_eq!(color::Fg(theme.error).to_string(), color::Fg(color::Red).to_string());
+        assert_eq!(color::Fg(theme.hint).to_string(), color::Fg(color::Blue).to_string());
+    }
+
+    #[test]
+    fn every_name_is_a_theme() {
+        for name in Theme::NAMES {
+            assert!(Theme::by_name(name).is_some(), "{} is not a theme", name);
+        }
+        assert!(Theme::by_name("neon").is_none());
+        assert_eq!(Theme::solarized().name(), Some("solarized"));
+        let custom = Theme { hint: ThemeColor::Ansi(2), ..Theme::default() };
+        assert_eq!(custom.name(), None);
+    }
+}
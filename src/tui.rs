@@ -1,22 +1,35 @@
 use std::{
     fmt::Display,
     io::{stdout, Stdout, Write},
+    thread,
+    time::{Duration, Instant},
 };
 
 use termion::{
     clear,
-    cursor::{self, DetectCursorPos},
+    cursor,
     raw::{IntoRawMode, RawTerminal},
-    style,
     terminal_size,
 };
 
 use crate::Text;
+use crate::config::{Alignment, CursorStyle};
+use crate::theme::{Theme, ThemeColor};
 use crate::GameError;
 use crate::text::HasLength;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 const MIN_LINE_WIDTH: usize = 50;
 
+/// Columns left empty before left aligned lines.
+pub const LEFT_MARGIN: u16 = 4;
+
+/// Drawn in place of a newline that has to be typed.
+pub const NEWLINE_SYMBOL: char = '↵';
+
+/// Drawn at the start of the columns a tab takes up.
+pub const TAB_SYMBOL: char = '→';
+
 
 #[derive(Clone, Copy)]
 struct LinePos {
@@ -37,6 +50,9 @@ impl Default for LinePos {
 
 struct CursorPos {
     pub lines: Vec<LinePos>,
+    // column of each char from the start of its line, then the width of the
+    // line, for lines with chars that aren't one column wide. Empty otherwise
+    pub columns: Vec<Vec<u16>>,
     pub cur_line: usize,
     pub cur_char_in_line: u16,
 }
@@ -45,6 +61,7 @@ impl CursorPos {
     pub fn new() -> Self {
         Self {
             lines: Vec::new(),
+            columns: Vec::new(),
             cur_line: 0,
             cur_char_in_line: 0,
         }
@@ -52,18 +69,16 @@ impl CursorPos {
 
     pub fn next(&mut self) -> (u16, u16) {
         let line = self.lines[self.cur_line];
-        let max_chars_index = line.length - 1;
+        // an empty line still has the one place for the cursor
+        let max_chars_index = line.length.saturating_sub(1);
 
         if self.cur_char_in_line < max_chars_index {
             // more chars in line
             self.cur_char_in_line += 1;
-        } else {
-            // reached the end of line
-            if self.cur_line + 1 < self.lines.len() {
-                // more lines available
-                self.cur_line += 1;
-                self.cur_char_in_line = 0;
-            }
+        } else if self.cur_line + 1 < self.lines.len() {
+            // reached the end of line, more lines available
+            self.cur_line += 1;
+            self.cur_char_in_line = 0;
         }
 
         self.cur_pos()
@@ -73,13 +88,10 @@ impl CursorPos {
         if self.cur_char_in_line > 0 {
             // more chars behind in line
             self.cur_char_in_line -= 1;
-        } else {
-            // reached the start of line
-            if self.cur_line > 0 {
-                // more lines available
-                self.cur_line -= 1;
-                self.cur_char_in_line = self.lines[self.cur_line].length - 1;
-            }
+        } else if self.cur_line > 0 {
+            // reached the start of line, more lines available
+            self.cur_line -= 1;
+            self.cur_char_in_line = self.lines[self.cur_line].length.saturating_sub(1);
         }
 
         self.cur_pos()
@@ -87,15 +99,79 @@ impl CursorPos {
 
     pub fn cur_pos(&self) -> (u16, u16) {
         let line = self.lines[self.cur_line];
-        (line.x + self.cur_char_in_line, line.y)
+        debug_assert!(
+            self.cur_char_in_line < line.length.max(1),
+            "cursor past the end of its line"
+        );
+        // never past the line, which `GameTui::track_line` checked is on
+        // screen
+        let char_in_line = self.cur_char_in_line.min(line.length.saturating_sub(1));
+        let column = self
+            .columns
+            .get(self.cur_line)
+            .and_then(|columns| columns.get(char_in_line as usize))
+            .copied()
+            .unwrap_or(char_in_line);
+        (line.x + column, line.y)
+    }
+
+    /// Moves to the char at `index` in the text, or to the last char if the
+    /// text is shorter than that
+    pub fn set_index(&mut self, index: usize) -> (u16, u16) {
+        let mut remaining = index;
+        for (line_no, line) in self.lines.iter().enumerate() {
+            if remaining < line.length as usize {
+                self.cur_line = line_no;
+                self.cur_char_in_line = remaining as u16;
+                return self.cur_pos();
+            }
+            remaining -= line.length as usize;
+        }
+
+        self.cur_line = self.lines.len() - 1;
+        self.cur_char_in_line = self.lines[self.cur_line].length.saturating_sub(1);
+        self.cur_pos()
+    }
+
+    /// Index in the text of the char the cursor is on, as `set_index` takes
+    /// it.
+    pub fn index(&self) -> usize {
+        let before: usize = self
+            .lines
+            .iter()
+            .take(self.cur_line)
+            .map(|line| line.length as usize)
+            .sum();
+        before + self.cur_char_in_line as usize
     }
 }
 
-pub struct GameTui {
-    stdout: RawTerminal<Stdout>,
+pub struct GameTui<W: Write = RawTerminal<Stdout>> {
+    stdout: W,
+    // stands in for the real terminal size when headless
+    size: Option<(u16, u16)>,
     cursor_pos: CursorPos,
+    top_lines_len: usize,
     bottom_lines_len: usize,
-    track_lines: bool,
+    // terminal size the words were last laid out for
+    layout_size: Option<(u16, u16)>,
+    // fraction of the terminal width the words are wrapped to
+    line_width: f64,
+    // most words on a line
+    line_words: usize,
+    alignment: Alignment,
+    cursor_style: CursorStyle,
+    // columns a tab takes up
+    tab_width: u16,
+    // whether the last line of words ends with a space like the others
+    final_space: bool,
+    // mistakes drawn as the char typed rather than the one of the text
+    show_typed_mistakes: bool,
+    // flushes closer together than this are put off, see `set_flush_interval`
+    flush_interval: Option<Duration>,
+    flushed_at: Option<Instant>,
+    // something was drawn since the last flush that went out
+    unflushed: bool,
 }
 
 type MaybeError<T = ()> = Result<T, GameError>;
@@ -103,14 +179,129 @@ type MaybeError<T = ()> = Result<T, GameError>;
 impl GameTui {
 
     pub fn new() -> Self {
+        Self::with_writer(stdout().into_raw_mode().unwrap(), None)
+    }
+}
+
+impl<W: Write> GameTui<W> {
+
+    /// Draws into `writer` rather than the terminal, pretending the terminal
+    /// is `size` columns by rows. Nothing touches the real terminal, so this
+    /// works without a TTY, e.g. in tests.
+    pub fn headless(writer: W, size: (u16, u16)) -> Self {
+        Self::with_writer(writer, Some(size))
+    }
+
+    fn with_writer(writer: W, size: Option<(u16, u16)>) -> Self {
         Self {
-            stdout: stdout().into_raw_mode().unwrap(),
+            stdout: writer,
+            size,
             cursor_pos: CursorPos::new(),
-            track_lines: false,
+            top_lines_len: 0,
             bottom_lines_len: 0,
+            layout_size: None,
+            line_width: 0.4,
+            line_words: MAX_WORDS_PER_LINE,
+            alignment: Alignment::Center,
+            cursor_style: CursorStyle::BlinkingBar,
+            tab_width: 4,
+            final_space: false,
+            show_typed_mistakes: false,
+            flush_interval: None,
+            flushed_at: None,
+            unflushed: false,
+        }
+    }
+
+    /// Everything written so far, when headless.
+    pub fn writer(&self) -> &W {
+        &self.stdout
+    }
+
+    /// Changes the pretend terminal size of a headless TUI.
+    pub fn set_terminal_size(&mut self, size: (u16, u16)) {
+        self.size = Some(size);
+    }
+
+    /// Wraps the words of `display_words` to `fraction` of the terminal
+    /// width, 40% by default.
+    pub fn set_line_width(&mut self, fraction: f64) {
+        self.line_width = fraction;
+    }
+
+    /// Centers lines, the default, or puts them at `LEFT_MARGIN`.
+    pub fn set_alignment(&mut self, alignment: Alignment) {
+        self.alignment = alignment;
+    }
+
+    /// The cursor `reset_screen` switches to, a blinking bar by default.
+    pub fn set_cursor_style(&mut self, style: CursorStyle) {
+        self.cursor_style = style;
+    }
+
+    /// Wraps the words of `display_words` after `words` words even if the
+    /// line has room for more, 10 by default.
+    pub fn set_line_words(&mut self, words: usize) {
+        self.line_words = words.max(1);
+    }
+
+    /// Draws tabs `width` columns wide, 4 by default, starting with
+    /// `TAB_SYMBOL`.
+    pub fn set_tab_width(&mut self, width: u16) {
+        self.tab_width = width.max(1);
+    }
+
+    /// Ends the last line of `display_words` with a space too, which makes
+    /// the text one character longer, off by default. Verbatim lines end
+    /// where their text does either way.
+    pub fn set_final_space(&mut self, final_space: bool) {
+        self.final_space = final_space;
+    }
+
+    /// Draws mistakes as the char that was typed, see `mistyped_text`,
+    /// rather than the char of the text, which is the default.
+    pub fn set_show_typed_mistakes(&mut self, show_typed: bool) {
+        self.show_typed_mistakes = show_typed;
+    }
+
+    /// `typed_text`, or `mistyped_text` with `set_show_typed_mistakes`.
+    pub fn typed_char_text(&self, typed_char: char, orig_char: char, theme: &Theme) -> Text {
+        if self.show_typed_mistakes {
+            mistyped_text(typed_char, orig_char, theme)
+        } else {
+            typed_text(typed_char, orig_char, theme)
         }
     }
 
+    /// Puts off every flush that comes less than `interval` after the last
+    /// one, so drawing many keys typed quickly takes fewer writes to the
+    /// terminal. What was put off goes out with the first flush once it's
+    /// due, see `flush_pending`. `None`, the default, flushes every time, and
+    /// setting it flushes what was put off right away.
+    pub fn set_flush_interval(&mut self, interval: Option<Duration>) -> MaybeError {
+        self.flush_interval = interval;
+        if interval.is_none() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    // some detached sessions report no size at all, which would center
+    // everything on column 0
+    fn terminal_size(&self) -> MaybeError<(u16, u16)> {
+        let (width, height) = match self.size {
+            Some(size) => size,
+            None => terminal_size()?,
+        };
+        if width == 0 || height == 0 {
+            return Err(GameError::from(format!(
+                        "Could not determine terminal size, got {} columns and {} lines",
+                        width, height,
+                        )));
+        }
+        Ok((width, height))
+    }
+
     pub fn reset(&mut self) {
         self.cursor_pos = CursorPos::new();
     }
@@ -118,19 +309,38 @@ impl GameTui {
 
     // Private ?
     pub fn flush(&mut self) -> MaybeError {
+        let due = match (self.flush_interval, self.flushed_at) {
+            (Some(interval), Some(at)) => at.elapsed() >= interval,
+            _ => true,
+        };
+        if !due {
+            self.unflushed = true;
+            return Ok(());
+        }
+
         self.stdout.flush()?;
+        self.unflushed = false;
+        self.flushed_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Flushes what `set_flush_interval` put off, if it's due by now.
+    pub fn flush_pending(&mut self) -> MaybeError {
+        if self.unflushed {
+            self.flush()?;
+        }
         Ok(())
     }
 
     pub fn reset_screen(&mut self) -> MaybeError {
-        let (sizex, sizey) = terminal_size()?;
+        let (sizex, sizey) = self.terminal_size()?;
 
         write!(
             self.stdout,
             "{}{}{}",
             clear::All,
             cursor::Goto(sizex / 2, sizey / 2),
-            cursor::BlinkingBar
+            cursor_code(self.cursor_style),
             )?;
         self.flush()?;
 
@@ -138,51 +348,78 @@ impl GameTui {
     }
 
     pub fn display_a_line(&mut self, text: &[Text]) -> MaybeError {
-        self.display_a_line_raw(text)?;
+        let len = text.length() as u16;
+        write!(self.stdout, "{}", cursor::Left(len / 2),)?;
+        for t in text {
+            self.display_raw_text(t)?;
+        }
+        write!(self.stdout, "{}", cursor::Left(len),)?;
         self.flush()?;
 
         Ok(())
     }
 
-    fn display_a_line_raw<T, U>(&mut self, text: U) -> MaybeError
+    // draws a line centered on column `center_x` of row `y`, or at the left
+    // margin when left aligned
+    fn display_a_line_raw<T, U>(&mut self, text: U, center_x: u16, y: u16) -> MaybeError
         where
             U: AsRef<[T]>,
         [T]: HasLength,
         T: Display,
         {
-            let len = text.as_ref().length() as u16;
-            write!(self.stdout, "{}", cursor::Left(len / 2),)?;
-
-            if self.track_lines {
-                let (x, y) = self.stdout.cursor_pos()?;
-                self.cursor_pos.lines.push(LinePos { x, y, length: len });
-            }
+            let x = self.line_x(text.as_ref().length() as u16, center_x);
+            write!(self.stdout, "{}", cursor::Goto(x, y))?;
 
             for t in text.as_ref() {
                 self.display_raw_text(t)?;
             }
-            write!(self.stdout, "{}", cursor::Left(len),)?;
 
             Ok(())
         }
 
+    // the column a line `len` columns wide starts at, the same `cursor::Left`
+    // would stop at. Computed here rather than asking the terminal, whose
+    // reply may be read by the key reader thread instead
+    fn line_x(&self, len: u16, center_x: u16) -> u16 {
+        match self.alignment {
+            Alignment::Center => center_x.saturating_sub(len / 2).max(1),
+            Alignment::Left => LEFT_MARGIN + 1,
+        }
+    }
+
     pub fn display_lines<T, U>(&mut self, lines: &[T]) -> MaybeError
         where
         T: AsRef<[U]>,
     [U]: HasLength,
     U: Display,
     {
-        let (sizex, sizey) = terminal_size()?;
+        let (sizex, sizey) = self.terminal_size()?;
 
         let line_offset = lines.len() as u16 / 2;
 
         for (line_no, line) in lines.iter().enumerate() {
-            write!(
-                self.stdout,
-                "{}",
-                cursor::Goto(sizex / 2, sizey / 2 + (line_no as u16) - line_offset)
-                )?;
-            self.display_a_line_raw(line.as_ref())?;
+            let y = sizey / 2 + (line_no as u16) - line_offset;
+            self.display_a_line_raw(line.as_ref(), sizex / 2, y)?;
+        }
+        self.flush()?;
+
+        Ok(())
+    }
+
+    /// Draws `lines` from the first row down, the text keeping clear of
+    /// them like it does of the bottom lines. No lines frees the rows again.
+    pub fn display_lines_top<T, U>(&mut self, lines: &[T]) -> MaybeError
+        where
+        T: AsRef<[U]>,
+    [U]: HasLength,
+    U: Display,
+    {
+        let (sizex, _) = self.terminal_size()?;
+
+        self.top_lines_len = lines.len();
+
+        for (line_no, line) in lines.iter().enumerate() {
+            self.display_a_line_raw(line.as_ref(), sizex / 2, line_no as u16 + 1)?;
         }
         self.flush()?;
 
@@ -195,95 +432,323 @@ impl GameTui {
     [U]: HasLength,
     U: Display,
     {
-        let (sizex, sizey) = terminal_size()?;
+        let (sizex, sizey) = self.terminal_size()?;
 
         let line_offset = lines.len() as u16;
         self.bottom_lines_len = lines.len();
 
         for (line_no, line) in lines.iter().enumerate() {
-            write!(
-                self.stdout,
-                "{}",
-                cursor::Goto(sizex / 2, sizey - 1 + (line_no as u16) - line_offset)
-                )?;
-            self.display_a_line_raw(line.as_ref())?;
+            let y = sizey - 1 + (line_no as u16) - line_offset;
+            self.display_a_line_raw(line.as_ref(), sizex / 2, y)?;
         }
         self.flush()?;
 
         Ok(())
     }
 
-    pub fn display_words(&mut self, words: &[String]) -> MaybeError<Vec<Text>> {
+    /// Redraws the last of the bottom lines, then puts the cursor back where
+    /// the user is typing.
+    pub fn update_status_line<U>(&mut self, line: &[U]) -> MaybeError
+        where
+        [U]: HasLength,
+        U: Display,
+    {
+        let (sizex, sizey) = self.terminal_size()?;
+        let y = sizey.saturating_sub(2);
+
+        write!(self.stdout, "{}{}", cursor::Goto(1, y), clear::CurrentLine)?;
+        self.display_a_line_raw(line, sizex / 2, y)?;
+        self.move_to_cur_pos()?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    /// Draws a bar filled to `fraction` of its width, from 0 to 1, with a
+    /// percentage on the last row, under the hint line. Like the status
+    /// line it puts the cursor back where the user is typing.
+    pub fn display_progress(&mut self, fraction: f64, color: ThemeColor) -> MaybeError {
+        const BAR_WIDTH: usize = 20;
+        let (sizex, sizey) = self.terminal_size()?;
+        let fraction = if fraction.is_finite() { fraction.clamp(0.0, 1.0) } else { 0.0 };
+        let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+
+        let line = [
+            Text::from("━".repeat(filled)).with_color(color),
+            Text::from("─".repeat(BAR_WIDTH - filled)).with_faint(),
+            Text::from(format!(" {:>3.0}%", fraction * 100.0)).with_faint(),
+        ];
+        write!(self.stdout, "{}{}", cursor::Goto(1, sizey), clear::CurrentLine)?;
+        self.display_a_line_raw(&line[..], sizex / 2, sizey)?;
+        self.move_to_cur_pos()?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    /// Draws `elapsed` as minutes and seconds in the top right corner, then
+    /// puts the cursor back where the user is typing.
+    pub fn display_timer(&mut self, elapsed: Duration) -> MaybeError {
+        let (sizex, _) = self.terminal_size()?;
+        let secs = elapsed.as_secs();
+        let timer = Text::from(format!("{}:{:02}", secs / 60, secs % 60)).with_faint();
+
+        let x = sizex.saturating_sub(timer.length() as u16).max(1);
+        write!(self.stdout, "{}{}{}", cursor::Goto(x, 1), clear::UntilNewline, timer)?;
+        self.move_to_cur_pos()?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    /// A bar for each of `values`, as high as the value compared to the
+    /// largest one, for a line of the results. When there are more values
+    /// than fit in half the terminal, neighbouring ones are averaged.
+    pub fn sparkline(&self, values: &[f64], color: ThemeColor) -> MaybeError<Text> {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let (sizex, _) = self.terminal_size()?;
+        let width = (sizex as usize / 2).max(1);
+
+        let values: Vec<f64> = values
+            .chunks(values.len().div_ceil(width).max(1))
+            .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+            .collect();
+        let max = values.iter().cloned().fold(0.0, f64::max);
+
+        let line: String = values
+            .iter()
+            .map(|value| {
+                if max > 0.0 {
+                    let bar = (value / max * BARS.len() as f64).ceil() as usize;
+                    BARS[bar.clamp(1, BARS.len()) - 1]
+                } else {
+                    BARS[0]
+                }
+            })
+            .collect();
+
+        Ok(Text::from(line).with_color(color))
+    }
+
+    pub fn display_words(&mut self, words: &[String], color: ThemeColor) -> MaybeError<Vec<Text>> {
         self.reset();
-        let mut current_len = 0;
-        let mut max_word_len = 0;
-        let mut line = Vec::new();
-        let mut lines = Vec::new();
-        let (terminal_width, terminal_height) = terminal_size()?;
-        // 40% of terminal width
-        let max_width = terminal_width * 2 / 5;
-        const MAX_WORDS_PER_LINE: usize = 10;
-        for word in words {
-            max_word_len = std::cmp::max(max_word_len, word.len() + 1);
-
-            let new_len = current_len + word.len() as u16 + 1;
-            if line.len() < MAX_WORDS_PER_LINE && new_len <= max_width {
-                // add to line
-                line.push(word.clone());
-                current_len += word.len() as u16 + 1
-            } else {
-                // add an extra space at the end of each line because
-                lines.push(Text::from(line.join(" ") + " ").with_faint());
+        let (terminal_width, terminal_height) = self.terminal_size()?;
+        self.layout_size = Some((terminal_width, terminal_height));
+        let max_width = line_width(terminal_width, self.line_width);
+        let mut lines = wrap_words(words, max_width, self.line_words);
+        if let Some(last) = lines.last_mut().filter(|last| self.final_space && !last.is_empty()) {
+            last.push(' ');
+        }
+        let lines: Vec<Text> = lines
+            .into_iter()
+            .map(|line| Text::from(line).with_faint().with_color(color))
+            .collect();
 
-                // clear line
-                line = vec![word.clone()];
-                current_len = word.len() as u16 + 1;
-            }
+        // wide words were broken up to fit the line width
+        self.check_fits(lines.len(), MIN_LINE_WIDTH)?;
+
+        // centered like `display_lines`, each line placed by its width but
+        // tracked by its chars, which the cursor moves over
+        let line_offset = lines.len() as u16 / 2;
+        for (line_no, line) in lines.iter().enumerate() {
+            let x = self.line_x(line.length() as u16, terminal_width / 2);
+            let y = terminal_height / 2 + line_no as u16 - line_offset;
+            let length = line.text().chars().count() as u16;
+            self.track_line(LinePos { x, y, length }, self.columns(line.text()))?;
+            write!(self.stdout, "{}", cursor::Goto(x, y))?;
+            self.display_raw_text(line)?;
         }
 
-        lines.push(Text::from(line.join(" ")).with_faint());
+        self.move_to_cur_pos()?;
+        self.flush()?;
+
+        Ok(lines)
+    }
+
+
+    /// Draws `lines` as they were written, left aligned in a block centered
+    /// on the screen, each but the last followed by `NEWLINE_SYMBOL`, which
+    /// stands for the newline to type there. Returns the lines drawn.
+    pub fn display_verbatim(
+        &mut self,
+        lines: &[String],
+        color: ThemeColor,
+    ) -> MaybeError<Vec<Text>> {
+        self.reset();
+        let (terminal_width, terminal_height) = self.terminal_size()?;
+        self.layout_size = Some((terminal_width, terminal_height));
+
+        let texts: Vec<Text> = lines
+            .iter()
+            .enumerate()
+            .map(|(line_no, line)| {
+                let line = if line_no + 1 < lines.len() {
+                    format!("{}{}", line, NEWLINE_SYMBOL)
+                } else {
+                    line.clone()
+                };
+                Text::from(line).with_faint().with_color(color)
+            })
+            .collect();
+
+        let columns: Vec<Vec<u16>> = texts.iter().map(|text| self.columns(text.text())).collect();
+        let block_width = texts
+            .iter()
+            .zip(&columns)
+            .map(|(text, columns)| columns.last().map_or(text.length(), |width| *width as usize))
+            .max()
+            .unwrap_or(0);
+        self.check_fits(texts.len(), block_width + 2)?;
+
+        let x = match self.alignment {
+            Alignment::Center => ((terminal_width as usize - block_width) / 2 + 1) as u16,
+            Alignment::Left => LEFT_MARGIN + 1,
+        };
+        let top = terminal_height / 2 - texts.len() as u16 / 2;
+        for (line_no, (text, columns)) in texts.iter().zip(columns).enumerate() {
+            let y = top + line_no as u16;
+            self.track_line(LinePos { x, y, length: text.text().chars().count() as u16 }, columns)?;
+            write!(self.stdout, "{}", cursor::Goto(x, y))?;
+            self.display_raw_text(text)?;
+        }
+
+        self.move_to_cur_pos()?;
+        self.flush()?;
+
+        Ok(texts)
+    }
 
-        max_word_len = std::cmp::max(max_word_len + 1, MIN_LINE_WIDTH);
-        if lines.len() + self.bottom_lines_len + 2 > terminal_height as usize {
+    // where each char of `line` starts, see `CursorPos::columns`. Tabs, wide
+    // chars and combining ones don't take up one column
+    fn columns(&self, line: &str) -> Vec<u16> {
+        if line.chars().all(|c| c != '\t' && c.width() == Some(1)) {
+            return Vec::new();
+        }
+
+        let mut column = 0;
+        let mut columns: Vec<u16> = line
+            .chars()
+            .map(|c| {
+                let start = column;
+                column += if c == '\t' { self.tab_width } else { c.width().unwrap_or(0) as u16 };
+                start
+            })
+            .collect();
+        columns.push(column);
+        columns
+    }
+
+    // remembers where a line of the text is for the cursor, failing rather
+    // than letting the line, and the cursor with it, run off the screen
+    fn track_line(&mut self, line: LinePos, columns: Vec<u16>) -> MaybeError {
+        let (terminal_width, _) = self.terminal_size()?;
+        let width = columns.last().copied().unwrap_or(line.length);
+        let end = line.x as usize - 1 + width as usize;
+        if end > terminal_width as usize {
+            return Err(GameError::from(format!(
+                        "Terminal width is too low! A line needs columns {} to {}, got {} columns",
+                        line.x, end, terminal_width,
+                        )));
+        }
+
+        self.cursor_pos.lines.push(line);
+        self.cursor_pos.columns.push(columns);
+        Ok(())
+    }
+
+    // errors out if `num_lines` of text, with the top and bottom lines and a
+    // margin, or `min_width` columns don't fit on the terminal. The text is
+    // centered, so the top lines take as many rows again below it
+    fn check_fits(&self, num_lines: usize, min_width: usize) -> MaybeError {
+        let (terminal_width, terminal_height) = self.terminal_size()?;
+        let min_height = num_lines + self.bottom_lines_len.max(2 * self.top_lines_len) + 2;
+
+        if min_height > terminal_height as usize {
             return Err(GameError::from(format!(
                         "Terminal height is too short! Game requires at least {} lines, got {} lines",
-                        lines.len() + self.bottom_lines_len + 2,
+                        min_height,
                         terminal_height,
                         )));
-        } else if max_word_len > terminal_width as usize {
+        } else if min_width > terminal_width as usize {
             return Err(GameError::from(format!(
                         "Terminal width is too low! Game requires at least {} columns, got {} columns",
-                        max_word_len, terminal_width,
+                        min_width, terminal_width,
                         )));
         }
 
-        self.track_lines = true;
-        self.display_lines(
-            lines
-            .iter()
-            .cloned()
-            .map(|line| [line])
-            .collect::<Vec<[Text; 1]>>()
-            .as_slice(),
-            )?;
-        self.track_lines = false;
-
-        self.move_to_cur_pos()?;
-        self.flush()?;
-
-        Ok(lines)
+        Ok(())
     }
 
+    /// Whether the terminal changed size since the words were laid out, which
+    /// leaves the tracked line positions pointing at the wrong cells.
+    pub fn has_resized(&self) -> MaybeError<bool> {
+        let size = self.terminal_size()?;
+        Ok(self.layout_size.is_some_and(|layout_size| layout_size != size))
+    }
 
     pub fn display_raw_text<T>(&mut self, text: &T) -> MaybeError
         where
         T: Display,
         {
-            write!(self.stdout, "{}", text)?;
+            let text = text.to_string();
+            if text.contains('\t') {
+                // a tab would jump to the terminal's own tab stop
+                let tab = format!("{:<width$}", TAB_SYMBOL, width = self.tab_width as usize);
+                write!(self.stdout, "{}", text.replace('\t', &tab))?;
+            } else {
+                write!(self.stdout, "{}", text)?;
+            }
             Ok(())
         }
 
 
+    /// Shows an error the game can't go on after from the top left corner,
+    /// wrapped to the terminal however small it is, with a hint to press a
+    /// key on the last row.
+    pub fn display_error(&mut self, error: &GameError) -> MaybeError {
+        let (sizex, sizey) = self.terminal_size()?;
+        let width = sizex.max(1) as usize;
+
+        let words: Vec<String> = error.msg.split_whitespace().map(String::from).collect();
+        // a word wider than the terminal is broken up
+        let lines: Vec<String> = wrap_words(&words, width, MAX_WORDS_PER_LINE)
+            .iter()
+            .map(|line| line.trim_end().to_string())
+            .collect();
+
+        write!(self.stdout, "{}{}", clear::All, cursor::Show)?;
+        let rows = sizey.saturating_sub(1).max(1) as usize;
+        for (row, line) in lines.iter().take(rows).enumerate() {
+            let line = Text::from(line.as_str()).with_bold();
+            write!(self.stdout, "{}{}", cursor::Goto(1, row as u16 + 1), line)?;
+        }
+
+        let hint: String = "press a key to exit".chars().take(width).collect();
+        write!(self.stdout, "{}{}", cursor::Goto(1, sizey.max(1)), Text::from(hint).with_faint())?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    /// Rings the terminal bell.
+    pub fn bell(&mut self) -> MaybeError {
+        write!(self.stdout, "\x07")?;
+        self.flush()?;
+        Ok(())
+    }
+
+    /// Inverts the colors of the whole screen for `duration`, then restores
+    /// them. Nothing is redrawn, so the cursor stays where it was.
+    pub fn flash(&mut self, duration: Duration) -> MaybeError {
+        // DECSCNM, reverse video for the whole screen
+        write!(self.stdout, "\x1b[?5h")?;
+        self.flush()?;
+        thread::sleep(duration);
+        write!(self.stdout, "\x1b[?5l")?;
+        self.flush()?;
+        Ok(())
+    }
+
     pub fn hide_cursor(&mut self) -> MaybeError {
         write!(self.stdout, "{}", cursor::Hide)?;
         self.flush()?;
@@ -309,6 +774,47 @@ impl GameTui {
         }
 
 
+    /// Draws `text` over the char at `index` in the text and leaves the
+    /// cursor on it. Unlike `replace_text`, this doesn't depend on where the
+    /// cursor was, so it lands on the right cell across wrapped lines.
+    pub fn replace_text_at<T>(&mut self, index: usize, text: T) -> MaybeError
+        where
+        T: Display,
+        {
+            self.move_to_char(index)?;
+            self.display_raw_text(&text)?;
+            self.move_to_char(index)?;
+
+            Ok(())
+        }
+
+    /// Draws the laid out text again as it stands after `input` was typed
+    /// over `original`, typed chars in the correct or error color and the
+    /// rest faint, and leaves the cursor on the next char to type.
+    pub fn redraw_state(&mut self, original: &[char], input: &[char], theme: &Theme) -> MaybeError {
+        if original.is_empty() {
+            return Ok(());
+        }
+
+        self.move_to_char(0)?;
+        for (at, orig_char) in original.iter().enumerate() {
+            let text = match input.get(at) {
+                Some(typed_char) => self.typed_char_text(*typed_char, *orig_char, theme),
+                None => untyped_text(*orig_char, theme),
+            };
+            self.display_raw_text(&text)?;
+            self.move_to_next_char()?;
+        }
+        self.move_to_char(input.len())
+    }
+
+    pub fn move_to_char(&mut self, index: usize) -> MaybeError {
+        let (x, y) = self.cursor_pos.set_index(index);
+        write!(self.stdout, "{}", cursor::Goto(x, y))?;
+
+        Ok(())
+    }
+
     pub fn move_to_next_char(&mut self) -> MaybeError {
         let (x, y) = self.cursor_pos.next();
         write!(self.stdout, "{}", cursor::Goto(x, y))?;
@@ -336,6 +842,91 @@ impl GameTui {
     pub fn current_line(&self) -> usize {
         self.cursor_pos.cur_line
     }
+
+    /// The line the cursor is on and the char it's on in that line, counted
+    /// in chars rather than columns.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        (self.cursor_pos.cur_line, self.cursor_pos.cur_char_in_line as usize)
+    }
+
+    /// Index in the text of the char the cursor is on, which is how many
+    /// chars are typed while the test goes on. The cursor stays on the last
+    /// char once the text is typed.
+    pub fn cursor_index(&self) -> usize {
+        self.cursor_pos.index()
+    }
+
+    /// Lines the text was laid out on by the last `display_words` or
+    /// `display_verbatim`.
+    pub fn num_lines(&self) -> usize {
+        self.cursor_pos.lines.len()
+    }
+}
+
+// columns the words are wrapped to, at least `MIN_LINE_WIDTH` but leaving a
+// margin on both sides, which `check_fits` complains about if it can't
+fn line_width(terminal_width: u16, fraction: f64) -> usize {
+    let width = (terminal_width as f64 * fraction) as usize;
+    width.max(MIN_LINE_WIDTH).min((terminal_width as usize).saturating_sub(2))
+}
+
+const MAX_WORDS_PER_LINE: usize = 10;
+
+// Breaks words into lines of at most `max_width` columns and `max_words`
+// words, counting a space after every word. Each line but the last ends with
+// that space, so it can be typed like the others. A word wider than
+// `max_width` is broken up over lines of their own without the space, which
+// join back into the same text, its last part going on like a word.
+fn wrap_words(words: &[String], max_width: usize, max_words: usize) -> Vec<String> {
+    let mut current_len = 0;
+    let mut line: Vec<&str> = Vec::new();
+    let mut lines = Vec::new();
+
+    // blank tokens of a corpus would only show as extra spaces
+    for word in words.iter().filter(|word| !word.is_empty()) {
+        let mut word = word.as_str();
+        if word.width() > max_width {
+            if !line.is_empty() {
+                lines.push(line.join(" ") + " ");
+                line.clear();
+                current_len = 0;
+            }
+            while word.width() > max_width {
+                let (part, rest) = word.split_at(columns_end(word, max_width));
+                lines.push(part.to_string());
+                word = rest;
+            }
+        }
+
+        // columns, not bytes, so accented and wide words wrap where they show
+        let word_len = word.width() + 1;
+
+        if !line.is_empty()
+            && (line.len() >= max_words || current_len + word_len > max_width)
+        {
+            lines.push(line.join(" ") + " ");
+            line.clear();
+            current_len = 0;
+        }
+        line.push(word);
+        current_len += word_len;
+    }
+
+    lines.push(line.join(" "));
+    lines
+}
+
+// where `word` is split for its first part to take up at most `columns`
+// columns, but at least one char. Combining chars stay with the one before
+fn columns_end(word: &str, columns: usize) -> usize {
+    let mut width = 0;
+    for (at, c) in word.char_indices() {
+        width += c.width().unwrap_or(0);
+        if width > columns && at > 0 {
+            return at;
+        }
+    }
+    word.len()
 }
 
 impl Default for GameTui {
@@ -344,25 +935,196 @@ impl Default for GameTui {
     }
 }
 
-impl Drop for GameTui {
+// the escape code switching to `style`
+fn cursor_code(style: CursorStyle) -> &'static str {
+    match style {
+        CursorStyle::BlinkingBar => cursor::BlinkingBar.as_ref(),
+        CursorStyle::SteadyBar => cursor::SteadyBar.as_ref(),
+        CursorStyle::BlinkingBlock => cursor::BlinkingBlock.as_ref(),
+        CursorStyle::SteadyBlock => cursor::SteadyBlock.as_ref(),
+        CursorStyle::BlinkingUnderline => cursor::BlinkingUnderline.as_ref(),
+        CursorStyle::SteadyUnderline => cursor::SteadyUnderline.as_ref(),
+    }
+}
+
+impl<W: Write> Drop for GameTui<W> {
 
     fn drop(&mut self) {
         write!(
             self.stdout,
-            "{}{}{}",
+            "{}{}{}{}",
             clear::All,
+            // the terminal's usual cursor, visible even if a paused test
+            // hid it
             cursor::SteadyBlock,
+            cursor::Show,
             cursor::Goto(1, 1)
             )
             .expect("Could not reset terminal while exiting");
-        self.flush().expect("Could not flush stdout while exiting");
+        // straight to the terminal, a put off flush would never come
+        self.stdout.flush().expect("Could not flush stdout while exiting");
     }
 }
 
+/// How a typed char is drawn over the text, the char that should have been
+/// typed underlined if it's wrong.
+pub fn typed_text(typed_char: char, orig_char: char, theme: &Theme) -> Text {
+    if typed_char == orig_char {
+        Text::from(shown_char(typed_char)).with_color(theme.correct)
+    } else {
+        Text::from(shown_char(orig_char)).with_underline().with_color(theme.error)
+    }
+}
+
+/// Like `typed_text`, but with a wrong char drawn as it was typed. A char
+/// that takes up other columns than the one of the text, like a tab or a
+/// wide char, is drawn as the one of the text, so the line stays as it
+/// was laid out.
+pub fn mistyped_text(typed_char: char, orig_char: char, theme: &Theme) -> Text {
+    let (typed, orig) = (shown_char(typed_char), shown_char(orig_char));
+    if typed_char == orig_char || typed.width() != orig.width() {
+        return typed_text(typed_char, orig_char, theme);
+    }
+    Text::from(typed).with_underline().with_color(theme.error)
+}
+
+/// How a char that hasn't been typed yet is drawn.
+pub fn untyped_text(orig_char: char, theme: &Theme) -> Text {
+    Text::from(shown_char(orig_char)).with_faint().with_color(theme.faint)
+}
+
+/// A newline would move the terminal's cursor rather than show up.
+pub fn shown_char(c: char) -> char {
+    if c == '\n' { NEWLINE_SYMBOL } else { c }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // keeps what was flushed apart from what's still buffered
+    #[derive(Default)]
+    struct Flushes {
+        buffered: Vec<u8>,
+        flushed: Vec<u8>,
+        count: usize,
+    }
+
+    impl Write for Flushes {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.buffered.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushed.append(&mut self.buffered);
+            self.count += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flushes_are_put_off_until_due() {
+        let mut tui = GameTui::headless(Flushes::default(), (100, 30));
+        tui.set_flush_interval(Some(Duration::from_millis(50))).unwrap();
+        tui.flush().unwrap();
+        assert_eq!(tui.writer().count, 1);
+
+        // nothing goes out until the interval passed
+        for c in "abc".chars() {
+            tui.display_raw_text(&Text::from(c.to_string())).unwrap();
+            tui.flush().unwrap();
+        }
+        tui.flush_pending().unwrap();
+        assert_eq!(tui.writer().count, 1);
+        assert!(tui.writer().flushed.is_empty());
+
+        thread::sleep(Duration::from_millis(60));
+        tui.flush_pending().unwrap();
+        assert_eq!(tui.writer().count, 2);
+        assert!(String::from_utf8_lossy(&tui.writer().flushed).contains('c'));
+        // nothing left to flush
+        tui.flush_pending().unwrap();
+        assert_eq!(tui.writer().count, 2);
+
+        // turning it off flushes right away, and every time after
+        tui.display_raw_text(&Text::from("d")).unwrap();
+        tui.set_flush_interval(None).unwrap();
+        assert!(tui.writer().buffered.is_empty());
+        tui.flush().unwrap();
+        assert_eq!(tui.writer().count, 4);
+    }
+
+    #[test]
+    fn terminals_without_a_size_are_errors() {
+        for size in [(0, 0), (100, 0), (0, 30)] {
+            let mut tui = GameTui::headless(Vec::new(), size);
+            let error = tui.reset_screen().unwrap_err();
+            assert!(error.msg.starts_with("Could not determine terminal size"), "{}", error.msg);
+            let words = vec![String::from("word"); 3];
+            assert!(tui.display_words(&words, ThemeColor::Default).is_err());
+            assert!(tui.writer().is_empty());
+        }
+    }
+
+    #[test]
+    fn mistakes_are_drawn_as_the_text_or_as_typed() {
+        let theme = Theme::default();
+        let wrong = |c: char| Text::from(c).with_underline().with_color(theme.error).to_string();
+        assert_eq!(typed_text('x', 'b', &theme).to_string(), wrong('b'));
+        assert_eq!(mistyped_text('x', 'b', &theme).to_string(), wrong('x'));
+        assert_eq!(
+            mistyped_text('a', 'a', &theme).to_string(),
+            typed_text('a', 'a', &theme).to_string()
+        );
+        // where a tab or a wide char goes, the line would shift
+        assert_eq!(
+            mistyped_text('x', '\t', &theme).to_string(),
+            typed_text('x', '\t', &theme).to_string()
+        );
+        assert_eq!(
+            mistyped_text('x', '字', &theme).to_string(),
+            typed_text('x', '字', &theme).to_string()
+        );
+        // a typed newline shows as one
+        assert_eq!(mistyped_text('\n', 'x', &theme).to_string(), wrong(NEWLINE_SYMBOL));
+
+        let mut tui = GameTui::headless(Vec::new(), (100, 30));
+        assert_eq!(
+            tui.typed_char_text('x', 'b', &theme).to_string(),
+            typed_text('x', 'b', &theme).to_string()
+        );
+        tui.set_show_typed_mistakes(true);
+        assert_eq!(
+            tui.typed_char_text('x', 'b', &theme).to_string(),
+            mistyped_text('x', 'b', &theme).to_string()
+        );
+    }
+
+    #[test]
+    fn cursor_position_follows_the_text() {
+        let mut tui = GameTui::headless(Vec::new(), (100, 30));
+        let words = vec![String::from("word"); 12];
+        tui.display_words(&words, ThemeColor::Default).unwrap();
+        // ten words on the first line, see `set_line_words`
+        assert_eq!(tui.num_lines(), 2);
+        assert_eq!((tui.cursor_position(), tui.cursor_index()), ((0, 0), 0));
+
+        for _ in 0..52 {
+            tui.move_to_next_char().unwrap();
+        }
+        assert_eq!((tui.cursor_position(), tui.cursor_index()), ((1, 2), 52));
+        tui.move_to_prev_char().unwrap();
+        tui.move_to_prev_char().unwrap();
+        tui.move_to_prev_char().unwrap();
+        assert_eq!((tui.cursor_position(), tui.cursor_index()), ((0, 49), 49));
+
+        // stays on the last char
+        tui.move_to_char(100).unwrap();
+        assert_eq!((tui.cursor_position(), tui.cursor_index()), ((1, 8), 58));
+        assert_eq!(tui.current_line(), 1);
+    }
+
     #[test]
     fn cursor_next_move_to_new_line() {
         let mut cursor = CursorPos::new();
@@ -391,13 +1153,17 @@ mod tests {
 
         let mut cursor = CursorPos::new();
 
-        let mut linePos1 = LinePos::default();
-        linePos1.length = 3;
+        let line_pos1 = LinePos {
+            length: 3,
+            ..LinePos::default()
+        };
 
-        let mut linePos2 = LinePos::default();
-        linePos2.length = 4;
+        let line_pos2 = LinePos {
+            length: 4,
+            ..LinePos::default()
+        };
 
-        cursor.lines = vec!(linePos1, linePos2);
+        cursor.lines = vec!(line_pos1, line_pos2);
 
         cursor.next();
 
@@ -410,17 +1176,468 @@ mod tests {
         
         let mut cursor = CursorPos::new();
 
-        let mut linePos1 = LinePos::default();
-        linePos1.length = 3;
+        let line_pos1 = LinePos {
+            length: 3,
+            ..LinePos::default()
+        };
 
-        let mut linePos2 = LinePos::default();
-        linePos2.length = 4;
+        let line_pos2 = LinePos {
+            length: 4,
+            ..LinePos::default()
+        };
 
-        cursor.lines = vec!(linePos1, linePos2);
+        cursor.lines = vec!(line_pos1, line_pos2);
 
         cursor.next();
         cursor.prev();
 
         assert_eq!(cursor.cur_char_in_line, 0);
     }
+
+    #[test]
+    fn cursor_backspace_across_wrapped_line() {
+        let mut cursor = CursorPos::new();
+        // "one two " wrapped above "three"
+        cursor.lines = vec![
+            LinePos { x: 10, y: 5, length: 8 },
+            LinePos { x: 12, y: 6, length: 5 },
+        ];
+
+        // type past the wrap point
+        for _ in 0..10 {
+            cursor.next();
+        }
+        assert_eq!(cursor.cur_pos(), (14, 6));
+
+        // backspace back across it, one char at a time
+        let mut by_index = CursorPos::new();
+        by_index.lines = cursor.lines.clone();
+        for index in (0..10).rev() {
+            assert_eq!(cursor.prev(), by_index.set_index(index), "char {}", index);
+        }
+        assert_eq!(by_index.set_index(7), (17, 5));
+        assert_eq!(by_index.set_index(8), (12, 6));
+    }
+
+    #[test]
+    fn display_progress_on_the_last_row() {
+        let mut tui = GameTui::headless(Vec::new(), (80, 24));
+        tui.display_words(&words("some words"), ThemeColor::Default).unwrap();
+
+        for (fraction, shown) in [(0.5, " 50%"), (2.0, "100%"), (f64::NAN, "  0%")] {
+            tui.stdout.clear();
+            tui.display_progress(fraction, ThemeColor::Default).unwrap();
+
+            let output = String::from_utf8_lossy(&tui.stdout).into_owned();
+            assert!(output.starts_with(&cursor::Goto(1, 24).to_string()));
+            assert!(output.contains(shown), "{} in {:?}", shown, output);
+        }
+        assert_eq!(String::from_utf8_lossy(&tui.stdout).matches('━').count(), 0);
+    }
+
+    #[test]
+    fn redraw_state_draws_typed_chars_and_the_rest_faint() {
+        let theme = Theme::default();
+        let mut tui = GameTui::headless(Vec::new(), (80, 24));
+        tui.display_words(&words("ab cd"), ThemeColor::Default).unwrap();
+        let start = tui.cursor_pos.set_index(0);
+        tui.stdout.clear();
+
+        let original: Vec<char> = "ab cd".chars().collect();
+        tui.redraw_state(&original, &['a', 'x', ' '], &theme).unwrap();
+
+        let mut expected = cursor::Goto(start.0, start.1).to_string();
+        let drawn = [
+            typed_text('a', 'a', &theme),
+            typed_text('x', 'b', &theme),
+            typed_text(' ', ' ', &theme),
+            untyped_text('c', &theme),
+            untyped_text('d', &theme),
+        ];
+        for (at, text) in drawn.iter().enumerate() {
+            // the cursor stays on the last char once it's there
+            let next = (start.0 + (at as u16 + 1).min(4), start.1);
+            expected += &format!("{}{}", text, cursor::Goto(next.0, next.1));
+        }
+        expected += &cursor::Goto(start.0 + 3, start.1).to_string();
+        assert_eq!(String::from_utf8_lossy(&tui.stdout), expected);
+        assert_eq!(tui.cursor_pos.cur_char_in_line, 3);
+    }
+
+    #[test]
+    fn text_keeps_clear_of_the_top_lines() {
+        let mut tui = GameTui::headless(Vec::new(), (80, 10));
+        tui.display_lines_top(&[[Text::from("markov")], [Text::from("seed 1")]]).unwrap();
+        assert!(String::from_utf8_lossy(&tui.stdout).contains(&cursor::Goto(37, 2).to_string()));
+
+        // three words a line
+        tui.display_words(&words(&"abcdefghijklmno ".repeat(12)), ThemeColor::Default).unwrap();
+        assert!(tui.cursor_pos.lines.iter().all(|line| line.y > 2));
+
+        let error = tui
+            .display_words(&words(&"abcdefghijklmno ".repeat(15)), ThemeColor::Default)
+            .unwrap_err();
+        assert!(error.msg.contains("at least 11 lines"), "{}", error.msg);
+
+        tui.display_lines_top::<[Text; 1], Text>(&[]).unwrap();
+        tui.display_words(&words(&"abcdefghijklmno ".repeat(15)), ThemeColor::Default).unwrap();
+    }
+
+    #[test]
+    fn sparkline_scales_to_the_largest_value() {
+        let tui = GameTui::headless(Vec::new(), (20, 10));
+
+        let line = tui.sparkline(&[0.0, 30.0, 60.0, 120.0], ThemeColor::Default).unwrap();
+        assert_eq!(line.text(), "▁▂▄█");
+        assert_eq!(tui.sparkline(&[42.0], ThemeColor::Default).unwrap().text(), "█");
+        assert_eq!(tui.sparkline(&[0.0, 0.0], ThemeColor::Default).unwrap().text(), "▁▁");
+
+        // 25 values in 10 columns, averaged three at a time
+        let long: Vec<f64> = (0..25).map(|i| i as f64).collect();
+        assert_eq!(tui.sparkline(&long, ThemeColor::Default).unwrap().length(), 9);
+    }
+
+    #[test]
+    fn line_width_follows_the_fraction() {
+        // 16 columns with the space
+        let long_words = words(&"abcdefghijklmno ".repeat(40));
+        let mut widths = Vec::new();
+        for fraction in [0.2, 0.4, 0.8] {
+            let mut tui = GameTui::headless(Vec::new(), (200, 50));
+            tui.set_line_width(fraction);
+            let lines = tui.display_words(&long_words, ThemeColor::Default).unwrap();
+            widths.push(lines.iter().map(|line| line.length()).max().unwrap());
+        }
+        // 20% would be 40 columns, less than the minimum of 50
+        assert_eq!(widths, vec![48, 80, 160]);
+
+        assert_eq!(line_width(60, 0.8), 50);
+        assert_eq!(line_width(51, 0.8), 49);
+    }
+
+    #[test]
+    fn lines_follow_the_alignment() {
+        let text = words(&"word ".repeat(15));
+        let mut starts = Vec::new();
+        for alignment in [Alignment::Center, Alignment::Left] {
+            let mut tui = GameTui::headless(Vec::new(), (100, 30));
+            tui.set_alignment(alignment);
+            tui.display_words(&text, ThemeColor::Default).unwrap();
+            starts.push(tui.cursor_pos.lines.iter().map(|line| line.x).collect::<Vec<_>>());
+
+            tui.display_verbatim(&words("one two"), ThemeColor::Default).unwrap();
+            starts.push(tui.cursor_pos.lines.iter().map(|line| line.x).collect());
+        }
+
+        // lines of 50 and 25 columns around column 50
+        assert_eq!(starts[0], vec![25, 38]);
+        assert_eq!(starts[1], vec![49, 49]);
+        assert_eq!(starts[2], vec![LEFT_MARGIN + 1; 2]);
+        assert_eq!(starts[3], vec![LEFT_MARGIN + 1; 2]);
+    }
+
+    #[test]
+    fn lines_off_the_screen_are_an_error() {
+        let mut tui = GameTui::headless(Vec::new(), (20, 10));
+        let track = |tui: &mut GameTui<Vec<u8>>, length: u16| {
+            let x = tui.line_x(length, 10);
+            tui.track_line(LinePos { x, y: 5, length }, Vec::new())
+        };
+
+        track(&mut tui, 20).unwrap();
+        assert_eq!(tui.cursor_pos.lines.len(), 1);
+
+        tui.set_alignment(Alignment::Left);
+        let error = track(&mut tui, 17).unwrap_err();
+        assert!(error.msg.contains("columns 5 to 21, got 20"), "{}", error.msg);
+        let error = track(&mut tui, 30).unwrap_err();
+        assert!(error.msg.starts_with("Terminal width is too low"), "{}", error.msg);
+        assert_eq!(tui.cursor_pos.lines.len(), 1);
+    }
+
+    #[test]
+    fn cursor_style_during_tests_only() {
+        let mut screen = Vec::new();
+        {
+            let mut tui = GameTui::headless(&mut screen, (80, 24));
+            tui.set_cursor_style(CursorStyle::SteadyUnderline);
+            tui.reset_screen().unwrap();
+        }
+        let screen = String::from_utf8(screen).unwrap();
+
+        let underline = screen.find(&cursor::SteadyUnderline.to_string()).expect("style was set");
+        let block = screen.rfind(&cursor::SteadyBlock.to_string()).expect("style was restored");
+        assert!(underline < block);
+        assert!(!screen.contains(&cursor::BlinkingBar.to_string()));
+    }
+
+    #[test]
+    fn cursor_steps_over_wide_and_combining_chars() {
+        let mut tui = GameTui::headless(Vec::new(), (80, 24));
+        tui.set_alignment(Alignment::Left);
+        tui.display_words(&words("日本 cafe\u{301} é"), ThemeColor::Default).unwrap();
+
+        // one place for each char, the accent sharing the column after the e
+        let line = tui.cursor_pos.lines[0];
+        assert_eq!(line.length, 10);
+        let x = LEFT_MARGIN + 1;
+        let mut columns = vec![tui.cursor_pos.cur_pos().0 - x];
+        for _ in 0..9 {
+            columns.push(tui.cursor_pos.next().0 - x);
+        }
+        assert_eq!(columns, vec![0, 2, 4, 5, 6, 7, 8, 9, 9, 10]);
+        assert_eq!(tui.cursor_pos.set_index(8), (x + 9, line.y));
+    }
+
+    #[test]
+    fn cursor_steps_over_tabs() {
+        let mut tui = GameTui::headless(Vec::new(), (80, 24));
+        tui.set_tab_width(4);
+        let lines = vec![String::from("\tif x {"), String::from("\t\ty")];
+
+        tui.display_verbatim(&lines, ThemeColor::Default).unwrap();
+
+        // 11 columns wide as drawn, with the newline symbol
+        let x = tui.cursor_pos.lines[0].x;
+        assert_eq!(x, 35);
+        assert_eq!(tui.cursor_pos.cur_pos(), (x, 11));
+        assert_eq!(tui.cursor_pos.next(), (x + 4, 11));
+        assert_eq!(tui.cursor_pos.next(), (x + 5, 11));
+        assert_eq!(tui.cursor_pos.set_index(8), (x, 12));
+        assert_eq!(tui.cursor_pos.next(), (x + 4, 12));
+        assert_eq!(tui.cursor_pos.next(), (x + 8, 12));
+        assert_eq!(tui.cursor_pos.prev(), (x + 4, 12));
+
+        let screen = String::from_utf8_lossy(tui.writer()).into_owned();
+        assert!(screen.contains("→   if x {↵"), "{:?}", screen);
+        assert!(!screen.contains('\t'));
+    }
+
+    #[test]
+    fn display_timer_keeps_the_cursor() {
+        let mut tui = GameTui::headless(Vec::new(), (80, 24));
+        tui.display_words(&words("some words"), ThemeColor::Default).unwrap();
+        tui.move_to_char(3).unwrap();
+        let cursor = tui.cursor_pos.cur_pos();
+
+        tui.stdout.clear();
+        tui.display_timer(Duration::from_secs(75)).unwrap();
+
+        let output = String::from_utf8_lossy(&tui.stdout).into_owned();
+        assert!(output.starts_with(&cursor::Goto(76, 1).to_string()));
+        assert!(output.contains("1:15"));
+        assert!(output.ends_with(&cursor::Goto(cursor.0, cursor.1).to_string()));
+    }
+
+    #[test]
+    fn display_verbatim_keeps_lines_left_aligned() {
+        let mut tui = GameTui::headless(Vec::new(), (80, 24));
+        let lines: Vec<String> = ["fn main() {", "    println!();", "", "}"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+
+        let texts = tui.display_verbatim(&lines, ThemeColor::Default).unwrap();
+
+        let shown: Vec<&str> = texts.iter().map(|text| text.text().as_str()).collect();
+        assert_eq!(shown, vec!["fn main() {↵", "    println!();↵", "↵", "}"]);
+        let positions: Vec<(u16, u16, u16)> =
+            tui.cursor_pos.lines.iter().map(|line| (line.x, line.y, line.length)).collect();
+        // the widest line is 16 columns, in the middle of 80
+        assert_eq!(positions, vec![(33, 10, 12), (33, 11, 16), (33, 12, 1), (33, 13, 1)]);
+
+        // the blank line is one newline to type
+        assert_eq!(tui.cursor_pos.set_index(28), (33, 12));
+        assert_eq!(tui.cursor_pos.set_index(29), (33, 13));
+    }
+
+    fn words(text: &str) -> Vec<String> {
+        text.split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn wrap_words_counts_columns() {
+        // 6 columns each, but 11 or 12 bytes
+        let accented = words("éèêëēė ïîìíīį ûüùúūų öôòóōő");
+        for max_width in 7..30 {
+            let lines = wrap_words(&accented, max_width, MAX_WORDS_PER_LINE);
+            for line in &lines {
+                assert!(line.width() <= max_width, "'{}' is wider than {}", line, max_width);
+            }
+            assert_eq!(lines.concat(), accented.join(" "));
+        }
+
+        // three fit on 21 columns, which byte lengths would have broken
+        assert_eq!(wrap_words(&accented, 21, MAX_WORDS_PER_LINE).len(), 2);
+    }
+
+    #[test]
+    fn wrap_words_with_combining_chars() {
+        // "e" and a combining acute accent show as one column
+        let combining = words("cafe\u{301} re\u{301}sume\u{301} nai\u{308}ve");
+        assert_eq!(combining.iter().map(|word| word.width()).collect::<Vec<_>>(), vec![4, 6, 5]);
+
+        let lines = wrap_words(&combining, 12, MAX_WORDS_PER_LINE);
+        assert_eq!(lines, vec!["cafe\u{301} re\u{301}sume\u{301} ", "nai\u{308}ve"]);
+    }
+
+    #[test]
+    fn wrap_words_gives_wide_words_their_own_line() {
+        let lines = wrap_words(&words("a extraordinarily b"), 10, MAX_WORDS_PER_LINE);
+
+        // no empty line before the wide word, which goes on after its break
+        assert_eq!(lines, vec!["a ", "extraordin", "arily b"]);
+    }
+
+    #[test]
+    fn wrap_words_breaks_up_words_wider_than_the_lines() {
+        let long = "y".repeat(60);
+        let text = vec![String::from("ab"), long.clone(), String::from("cd")];
+        let lines = wrap_words(&text, 40, MAX_WORDS_PER_LINE);
+
+        assert_eq!(lines, vec![String::from("ab "), "y".repeat(40), "y".repeat(20) + " cd"]);
+        assert_eq!(lines.concat(), text.join(" "));
+
+        // wide chars aren't cut in half, combining ones stay with theirs
+        let wide = vec!["字".repeat(5), "e\u{301}".repeat(5)];
+        assert_eq!(
+            wrap_words(&wide, 3, MAX_WORDS_PER_LINE),
+            vec![
+                "字",
+                "字",
+                "字",
+                "字",
+                "字 ",
+                "e\u{301}e\u{301}e\u{301}",
+                "e\u{301}e\u{301}"
+            ]
+        );
+
+        let mut tui = GameTui::headless(Vec::new(), (100, 30));
+        let lines = tui
+            .display_words(&[String::from("ab"), "x".repeat(60)], ThemeColor::Default)
+            .unwrap();
+        // 50 columns at least
+        assert_eq!(lines.len(), 3);
+        assert_eq!(tui.cursor_pos.lines[1].length, 50);
+        // the cursor moves on to the rest of the word
+        let (x, y) = tui.cursor_pos.set_index(53);
+        assert_eq!((x, y), (tui.cursor_pos.lines[2].x, tui.cursor_pos.lines[2].y));
+    }
+
+    #[test]
+    fn wrap_words_stops_at_the_word_cap() {
+        let short = words("ab ".repeat(24).trim_end());
+
+        // 8 words take 24 columns
+        assert_eq!(wrap_words(&short, 60, 10).len(), 3);
+        assert_eq!(wrap_words(&short, 60, 4).len(), 6);
+        assert_eq!(wrap_words(&short, 60, 1).len(), 24);
+        // the width still wraps first when the cap is high
+        assert_eq!(wrap_words(&short, 24, 100).len(), 3);
+        assert_eq!(wrap_words(&short, 60, 100).len(), 2);
+
+        let mut tui = GameTui::headless(Vec::new(), (100, 30));
+        tui.set_line_words(6);
+        assert_eq!(tui.display_words(&short, ThemeColor::Default).unwrap().len(), 4);
+        tui.set_line_words(0);
+        assert_eq!(tui.display_words(&short, ThemeColor::Default).unwrap().len(), 24);
+    }
+
+    #[test]
+    fn final_space_ends_the_last_line_too() {
+        let mut tui = GameTui::headless(Vec::new(), (80, 24));
+        // ten words to a line at most
+        let text = words("a b c d e f g h i j k");
+        let shown = |tui: &mut GameTui<Vec<u8>>| -> Vec<String> {
+            let lines = tui.display_words(&text, ThemeColor::Default).unwrap();
+            lines.iter().map(|line| line.text().clone()).collect()
+        };
+
+        assert_eq!(shown(&mut tui), vec!["a b c d e f g h i j ", "k"]);
+        tui.set_final_space(true);
+        assert_eq!(shown(&mut tui), vec!["a b c d e f g h i j ", "k "]);
+        // the cursor can move onto it
+        tui.move_to_char(21).unwrap();
+        assert_eq!(tui.cursor_pos.cur_pos().0, tui.cursor_pos.lines[1].x + 1);
+    }
+
+    #[test]
+    fn empty_words_leave_no_empty_lines() {
+        let mut tui = GameTui::headless(Vec::new(), (80, 24));
+        let blanks = vec![
+            String::new(),
+            String::from("a"),
+            String::new(),
+            String::from("b"),
+            String::new(),
+        ];
+        let lines = tui.display_words(&blanks, ThemeColor::Default).unwrap();
+        assert_eq!(
+            lines.iter().map(|line| line.text()).collect::<Vec<_>>(),
+            vec!["a b"]
+        );
+        let lines = tui
+            .display_words(&[String::new(), String::new()], ThemeColor::Default)
+            .unwrap();
+        assert_eq!(lines[0].text(), "");
+        // nor does a final space make one up
+        tui.set_final_space(true);
+        let lines = tui.display_words(&[String::new()], ThemeColor::Default).unwrap();
+        assert_eq!(lines[0].text(), "");
+
+        // an empty line left anyway is stepped over rather than underflowing
+        for _ in 0..3 {
+            tui.move_to_next_char().unwrap();
+        }
+        for _ in 0..3 {
+            tui.move_to_prev_char().unwrap();
+        }
+
+        let mut cursor = CursorPos::new();
+        cursor.lines = vec![
+            LinePos { x: 1, y: 1, length: 2 },
+            LinePos { x: 1, y: 2, length: 0 },
+            LinePos { x: 1, y: 3, length: 1 },
+        ];
+        let moves: Vec<_> = (0..4).map(|_| cursor.next()).collect();
+        assert_eq!(moves, vec![(2, 1), (1, 2), (1, 3), (1, 3)]);
+        let moves: Vec<_> = (0..4).map(|_| cursor.prev()).collect();
+        assert_eq!(moves, vec![(1, 2), (2, 1), (1, 1), (1, 1)]);
+        assert_eq!(cursor.set_index(1), (2, 1));
+        cursor.lines.truncate(2);
+        assert_eq!(cursor.set_index(5), (1, 2));
+    }
+
+    #[test]
+    fn display_error_fits_a_tiny_terminal() {
+        let mut tui = GameTui::headless(Vec::new(), (12, 6));
+        let error = GameError::from(String::from(
+            "Terminal height is too short! Game requires at least 12 lines",
+        ));
+
+        tui.display_error(&error).unwrap();
+
+        let screen = String::from_utf8_lossy(tui.writer()).into_owned();
+        for (row, line) in [(1, "Terminal"), (2, "height is"), (4, "Game"), (5, "requires at")] {
+            let drawn = format!("{}{}", cursor::Goto(1, row), Text::from(line).with_bold());
+            assert!(screen.contains(&drawn), "row {} isn't '{}' in {:?}", row, line, screen);
+        }
+        // the rest is cut off to leave the last row for the hint
+        assert!(!screen.contains("least"));
+        assert!(screen.contains(&format!(
+            "{}{}",
+            cursor::Goto(1, 6),
+            Text::from("press a key ").with_faint()
+        )));
+    }
+
+    #[test]
+    fn cursor_set_index_past_the_end() {
+        let mut cursor = CursorPos::new();
+        cursor.lines = vec![LinePos { x: 1, y: 1, length: 3 }];
+
+        assert_eq!(cursor.set_index(10), (3, 1));
+        assert_eq!(cursor.cur_char_in_line, 2);
+    }
 }